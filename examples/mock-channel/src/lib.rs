@@ -10,6 +10,12 @@ struct WebhookPayload {
     user_id: String,
     /// Optional conversation/thread ID.
     thread_id: Option<String>,
+    /// Platform message ID, used by the host to drop redelivered webhooks.
+    message_id: Option<String>,
+    /// Where to send the reply (simulates a platform's send-message API).
+    reply_url: Option<String>,
+    /// A JPEG photo the user sent, hosted by the platform.
+    photo_url: Option<String>,
 }
 
 /// Normalized message returned to the host.
@@ -18,12 +24,23 @@ struct NormalizedMessage {
     content: String,
     account: String,
     peer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message_id: Option<String>,
+    /// Platform media, passed to the model as attachments.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<serde_json::Value>,
+    /// Echoed back to `format_outgoing` by the host.
+    metadata: serde_json::Value,
 }
 
 /// Outgoing response to format for the platform.
 #[derive(Deserialize)]
 struct OutgoingResponse {
     content: String,
+    #[serde(default)]
+    peer: Option<String>,
+    #[serde(default)]
+    metadata: serde_json::Value,
 }
 
 /// Platform-formatted reply.
@@ -46,6 +63,18 @@ pub fn parse_incoming(input: String) -> FnResult<String> {
         content: payload.text,
         account: payload.user_id,
         peer: payload.thread_id.unwrap_or_else(|| "main".into()),
+        message_id: payload.message_id,
+        attachments: payload
+            .photo_url
+            .into_iter()
+            .map(|url| {
+                serde_json::json!({
+                    "mime_type": "image/jpeg",
+                    "source": { "type": "url", "url": url },
+                })
+            })
+            .collect(),
+        metadata: serde_json::json!({ "reply_url": payload.reply_url }),
     };
 
     let output = serde_json::to_string(&normalized)
@@ -56,8 +85,9 @@ pub fn parse_incoming(input: String) -> FnResult<String> {
 
 /// Format a normalized agent response into platform-specific payload.
 ///
-/// Input: `{"content": "response text"}`
-/// Output: `{"text": "response text", "channel": "mock"}`
+/// Input: `{"content": "response text", "peer": "...", "metadata": {...}}`
+/// Output: `{"text": "response text", "channel": "mock"}`, or a proxy request
+/// `{"url": ..., "body": {...}}` when the incoming webhook gave a `reply_url`.
 #[plugin_fn]
 pub fn format_outgoing(input: String) -> FnResult<String> {
     let response: OutgoingResponse = serde_json::from_str(&input)
@@ -68,8 +98,14 @@ pub fn format_outgoing(input: String) -> FnResult<String> {
         channel: "mock".into(),
     };
 
-    let output = serde_json::to_string(&reply)
-        .map_err(|e| Error::msg(format!("serialize failed: {e}")))?;
+    let output = match response.metadata.get("reply_url").and_then(|u| u.as_str()) {
+        Some(url) => serde_json::to_string(&serde_json::json!({
+            "url": url,
+            "body": { "text": reply.text, "channel": reply.channel, "peer": response.peer },
+        })),
+        None => serde_json::to_string(&reply),
+    }
+    .map_err(|e| Error::msg(format!("serialize failed: {e}")))?;
 
     Ok(output)
 }
@@ -0,0 +1,223 @@
+//! Human approval for sensitive tool calls.
+//!
+//! Tools listed in a plugin's or agent's `require_approval` policy do not run
+//! as soon as the model asks for them. The runner emits a
+//! `tool_approval_request` event and waits for `tool.approve` or `tool.deny`
+//! on the tool call's id. A denied or unanswered call is not run; the model
+//! gets an error tool result instead.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// A human's answer to an approval request.
+#[derive(Debug)]
+pub enum Decision {
+    /// Run the tool, with `input` replacing the model's input when set.
+    Approve {
+        input: Option<serde_json::Value>,
+    },
+    Deny {
+        reason: Option<String>,
+    },
+}
+
+/// Tool calls waiting for a decision, keyed by tool call id.
+#[derive(Default)]
+pub struct Approvals {
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+struct Pending {
+    session_key: String,
+    tx: oneshot::Sender<Decision>,
+}
+
+impl Approvals {
+    /// Register a request for a decision on `tool_use_id`. The request is
+    /// withdrawn when the returned handle is dropped.
+    fn request(
+        self: &Arc<Self>,
+        tool_use_id: &str,
+        session_key: &str,
+        timeout: Duration,
+    ) -> PendingApproval {
+        let (tx, rx) = oneshot::channel();
+        self.lock().insert(
+            tool_use_id.to_string(),
+            Pending {
+                session_key: session_key.to_string(),
+                tx,
+            },
+        );
+        PendingApproval {
+            approvals: Arc::clone(self),
+            tool_use_id: tool_use_id.to_string(),
+            rx,
+            timeout,
+        }
+    }
+
+    /// Session of a pending request, for access checks.
+    pub fn session_key(&self, tool_use_id: &str) -> Option<String> {
+        self.lock()
+            .get(tool_use_id)
+            .map(|pending| pending.session_key.clone())
+    }
+
+    /// Answer a pending request. Returns false if nothing is waiting on it.
+    pub fn resolve(&self, tool_use_id: &str, decision: Decision) -> bool {
+        let pending = self.lock().remove(tool_use_id);
+        pending.is_some_and(|pending| pending.tx.send(decision).is_ok())
+    }
+
+    /// Requests waiting for a decision.
+    pub fn count(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Pending>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A registered approval request, answered through [`Approvals::resolve`].
+pub struct PendingApproval {
+    approvals: Arc<Approvals>,
+    tool_use_id: String,
+    rx: oneshot::Receiver<Decision>,
+    timeout: Duration,
+}
+
+impl PendingApproval {
+    /// Wait for the decision. Returns the input to run the tool with, or why
+    /// it must not run.
+    pub async fn wait(mut self, input: &serde_json::Value) -> Result<serde_json::Value, String> {
+        match tokio::time::timeout(self.timeout, &mut self.rx).await {
+            Ok(Ok(Decision::Approve { input: edited })) => {
+                Ok(edited.unwrap_or_else(|| input.clone()))
+            }
+            Ok(Ok(Decision::Deny {
+                reason: Some(reason),
+            })) => Err(format!("tool call denied by the user: {reason}")),
+            Ok(Ok(Decision::Deny { reason: None })) => {
+                Err("tool call denied by the user".to_string())
+            }
+            Ok(Err(_)) => Err("approval request cancelled".to_string()),
+            Err(_) => Err(format!("tool call not approved within {:?}", self.timeout)),
+        }
+    }
+}
+
+impl Drop for PendingApproval {
+    fn drop(&mut self) {
+        self.approvals.lock().remove(&self.tool_use_id);
+    }
+}
+
+/// Which of a run's tool calls need approval, and where to wait for it.
+#[derive(Clone)]
+pub struct ApprovalGate {
+    /// Tool names; `*` matches every tool.
+    tools: Vec<String>,
+    approvals: Arc<Approvals>,
+    session_key: String,
+    timeout: Duration,
+}
+
+impl ApprovalGate {
+    pub fn new(
+        tools: Vec<String>,
+        approvals: Arc<Approvals>,
+        session_key: impl Into<String>,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            tools,
+            approvals,
+            session_key: session_key.into(),
+            timeout,
+        }
+    }
+
+    pub fn requires(&self, tool: &str) -> bool {
+        self.tools.iter().any(|t| t == "*" || t == tool)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Open a request for a decision on a tool call. Call this before
+    /// announcing the request, so an immediate `tool.approve` finds it.
+    pub fn request(&self, tool_use_id: &str) -> PendingApproval {
+        self.approvals
+            .request(tool_use_id, &self.session_key, self.timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn decisions_reach_the_waiting_call_once() {
+        let approvals = Arc::new(Approvals::default());
+        let gate = ApprovalGate::new(
+            vec!["fetch".into()],
+            Arc::clone(&approvals),
+            "s",
+            Duration::from_secs(5),
+        );
+        assert!(gate.requires("fetch"));
+        assert!(!gate.requires("echo"));
+
+        let input = serde_json::json!({ "url": "https://example.com" });
+        let pending = gate.request("t1");
+        assert_eq!(approvals.session_key("t1").as_deref(), Some("s"));
+        let waiting = tokio::spawn(async move { pending.wait(&input).await });
+        let edited = serde_json::json!({ "url": "https://example.org" });
+        assert!(approvals.resolve(
+            "t1",
+            Decision::Approve {
+                input: Some(edited.clone())
+            }
+        ));
+        assert_eq!(waiting.await.unwrap(), Ok(edited));
+        assert!(!approvals.resolve("t1", Decision::Deny { reason: None }));
+        assert_eq!(approvals.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn unanswered_requests_time_out_and_are_withdrawn() {
+        let approvals = Arc::new(Approvals::default());
+        let gate = ApprovalGate::new(
+            vec!["*".into()],
+            Arc::clone(&approvals),
+            "s",
+            Duration::from_millis(10),
+        );
+        let result = gate.request("t2").wait(&serde_json::Value::Null).await;
+        assert_eq!(
+            result,
+            Err("tool call not approved within 10ms".to_string())
+        );
+        assert_eq!(approvals.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn replaced_requests_are_cancelled_not_denied() {
+        let approvals = Arc::new(Approvals::default());
+        let gate = ApprovalGate::new(
+            vec!["*".into()],
+            Arc::clone(&approvals),
+            "s",
+            Duration::from_secs(5),
+        );
+        let first = gate.request("t3");
+        let _second = gate.request("t3");
+        let result = first.wait(&serde_json::Value::Null).await;
+        assert_eq!(result, Err("approval request cancelled".to_string()));
+    }
+}
@@ -0,0 +1,162 @@
+//! Tools the client runs itself.
+//!
+//! A `chat.send` may declare `client_tools` next to the server's plugins, for
+//! things only the client can do (read the clipboard, query a local app).
+//! When the model calls one, the runner emits a `client_tool_call` event and
+//! waits for the client's `tool.result` on the same connection. A client that
+//! does not answer in time, or disconnects, gives the model an error result.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use crate::sandbox::ToolCallResult;
+
+/// One connection's client tool calls waiting for `tool.result`, keyed by
+/// tool call id.
+#[derive(Debug, Default)]
+pub struct ClientCalls {
+    pending: Mutex<HashMap<String, oneshot::Sender<ToolCallResult>>>,
+}
+
+impl ClientCalls {
+    fn request(self: &Arc<Self>, tool_use_id: &str, timeout: Duration) -> PendingCall {
+        let (tx, rx) = oneshot::channel();
+        self.lock().insert(tool_use_id.to_string(), tx);
+        PendingCall {
+            calls: Arc::clone(self),
+            tool_use_id: tool_use_id.to_string(),
+            rx,
+            timeout,
+        }
+    }
+
+    /// Deliver a result. Returns false if no call is waiting on `tool_use_id`.
+    pub fn resolve(&self, tool_use_id: &str, result: ToolCallResult) -> bool {
+        let pending = self.lock().remove(tool_use_id);
+        pending.is_some_and(|tx| tx.send(result).is_ok())
+    }
+
+    /// Fail every waiting call; the connection that would answer is gone.
+    pub fn disconnect(&self) {
+        self.lock().clear();
+    }
+
+    /// Calls waiting for a result.
+    pub fn count(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, oneshot::Sender<ToolCallResult>>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Fails a connection's waiting calls when dropped, i.e. when the connection
+/// that would answer them closes.
+pub struct DisconnectGuard(pub Arc<ClientCalls>);
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        self.0.disconnect();
+    }
+}
+
+/// A registered client tool call, answered through [`ClientCalls::resolve`].
+pub struct PendingCall {
+    calls: Arc<ClientCalls>,
+    tool_use_id: String,
+    rx: oneshot::Receiver<ToolCallResult>,
+    timeout: Duration,
+}
+
+impl PendingCall {
+    /// Wait for the client's result.
+    pub async fn wait(mut self) -> ToolCallResult {
+        let content = match tokio::time::timeout(self.timeout, &mut self.rx).await {
+            Ok(Ok(result)) => return result,
+            Ok(Err(_)) => "client disconnected before returning a result".to_string(),
+            Err(_) => format!("client returned no result within {:?}", self.timeout),
+        };
+        ToolCallResult {
+            content,
+            is_error: true,
+        }
+    }
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        self.calls.lock().remove(&self.tool_use_id);
+    }
+}
+
+/// The client tools of one run and the connection that answers them.
+#[derive(Clone)]
+pub struct ClientTools {
+    names: Vec<String>,
+    calls: Arc<ClientCalls>,
+    timeout: Duration,
+}
+
+impl ClientTools {
+    pub fn new(names: Vec<String>, calls: Arc<ClientCalls>, timeout: Duration) -> Self {
+        Self {
+            names,
+            calls,
+            timeout,
+        }
+    }
+
+    pub fn handles(&self, tool: &str) -> bool {
+        self.names.iter().any(|name| name == tool)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Open a call for the client to answer. Call this before sending the
+    /// `client_tool_call` event, so an immediate `tool.result` finds it.
+    pub fn request(&self, tool_use_id: &str) -> PendingCall {
+        self.calls.request(tool_use_id, self.timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn results_timeouts_and_disconnects() {
+        let calls = Arc::new(ClientCalls::default());
+        let tools = ClientTools::new(
+            vec!["clipboard".into()],
+            Arc::clone(&calls),
+            Duration::from_secs(5),
+        );
+        assert!(tools.handles("clipboard"));
+        assert!(!tools.handles("echo"));
+
+        let waiting = tokio::spawn(tools.request("c1").wait());
+        assert_eq!(calls.count(), 1);
+        let result = ToolCallResult {
+            content: "copied text".into(),
+            is_error: false,
+        };
+        assert!(calls.resolve("c1", result));
+        assert_eq!(waiting.await.unwrap().content, "copied text");
+        assert_eq!(calls.count(), 0);
+
+        let waiting = tokio::spawn(tools.request("c2").wait());
+        drop(DisconnectGuard(Arc::clone(&calls)));
+        let result = waiting.await.unwrap();
+        assert!(result.is_error);
+        assert!(result.content.contains("disconnected"));
+
+        let impatient = ClientTools::new(vec![], calls, Duration::from_millis(10));
+        let result = impatient.request("c3").wait().await;
+        assert_eq!(result.content, "client returned no result within 10ms");
+    }
+}
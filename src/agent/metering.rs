@@ -185,6 +185,13 @@ impl TokenCounter {
         }
     }
 
+    /// Swap in new budget limits, keeping the usage counted so far.
+    pub fn set_limits(&mut self, budget: &BudgetConfig) {
+        self.session_limit = budget.session;
+        self.daily_limit = budget.daily;
+        self.monthly_limit = budget.monthly;
+    }
+
     // --- T030: Pre-call budget checking ---
 
     /// Check if the budget allows an LLM call for the given session.
@@ -285,6 +292,19 @@ impl TokenCounter {
             "token usage recorded"
         );
 
+        let labels = [("provider", provider), ("model", model)];
+        crate::metrics::add(
+            crate::metrics::TOKENS,
+            &[labels[0], labels[1], ("kind", "input")],
+            input_tokens as f64,
+        );
+        crate::metrics::add(
+            crate::metrics::TOKENS,
+            &[labels[0], labels[1], ("kind", "output")],
+            output_tokens as f64,
+        );
+        crate::metrics::add(crate::metrics::COST_USD, &labels, cost);
+
         self.records.push(record);
     }
 
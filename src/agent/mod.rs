@@ -1,3 +1,5 @@
+pub mod approval;
+pub mod client_tools;
 pub mod metering;
 pub mod providers;
 
@@ -10,6 +12,8 @@ use tokio::sync::{RwLock, mpsc};
 use tracing::{info, warn};
 
 use crate::sandbox::PluginHost;
+use approval::ApprovalGate;
+use client_tools::ClientTools;
 
 /// Minimal LLM agent runner. Calls provider APIs with tool support.
 ///
@@ -19,6 +23,10 @@ use crate::sandbox::PluginHost;
 #[derive(Clone)]
 pub struct AgentRunner {
     client: Client,
+    /// Tool calls that must be approved before they run.
+    approvals: Option<ApprovalGate>,
+    /// Tools the requesting client runs itself.
+    client_tools: Option<ClientTools>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,12 +51,84 @@ pub enum AgentEvent {
         content: String,
         is_error: bool,
     },
+    /// The run is paused until the tool call `id` is approved or denied.
+    ApprovalRequest {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+        timeout_secs: u64,
+    },
+    /// The client is asked to run one of its own tools and answer with
+    /// `tool.result`.
+    ClientToolCall {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+        timeout_secs: u64,
+    },
     Usage {
         input_tokens: u32,
         output_tokens: u32,
     },
     Done,
     Error(String),
+    /// The run was cancelled by the client; no further events follow.
+    Cancelled,
+}
+
+impl From<&AgentEvent> for crate::types::StreamEvent {
+    fn from(event: &AgentEvent) -> Self {
+        use crate::types::StreamEvent;
+        match event {
+            AgentEvent::Text(text) => StreamEvent::Text(text.clone()),
+            AgentEvent::ToolUse { id, name, input } => StreamEvent::ToolUse {
+                id: id.clone(),
+                name: name.clone(),
+                input: input.clone(),
+            },
+            AgentEvent::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => StreamEvent::ToolResult {
+                tool_use_id: tool_use_id.clone(),
+                content: content.clone(),
+                is_error: *is_error,
+            },
+            AgentEvent::ApprovalRequest {
+                id,
+                name,
+                input,
+                timeout_secs,
+            } => StreamEvent::ToolApprovalRequest {
+                id: id.clone(),
+                name: name.clone(),
+                input: input.clone(),
+                timeout_secs: *timeout_secs,
+            },
+            AgentEvent::ClientToolCall {
+                id,
+                name,
+                input,
+                timeout_secs,
+            } => StreamEvent::ClientToolCall {
+                id: id.clone(),
+                name: name.clone(),
+                input: input.clone(),
+                timeout_secs: *timeout_secs,
+            },
+            AgentEvent::Usage {
+                input_tokens,
+                output_tokens,
+            } => StreamEvent::Usage {
+                input_tokens: *input_tokens,
+                output_tokens: *output_tokens,
+            },
+            AgentEvent::Done => StreamEvent::Done,
+            AgentEvent::Error(err) => StreamEvent::Error(err.clone()),
+            AgentEvent::Cancelled => StreamEvent::Cancelled,
+        }
+    }
 }
 
 /// Maximum tool-use loop iterations to prevent infinite loops.
@@ -58,9 +138,23 @@ impl AgentRunner {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
+            approvals: None,
+            client_tools: None,
         }
     }
 
+    /// Hold tool calls the gate names until a human approves them.
+    pub fn with_approvals(mut self, approvals: ApprovalGate) -> Self {
+        self.approvals = Some(approvals);
+        self
+    }
+
+    /// Hand calls to the client's own tools back to the client.
+    pub fn with_client_tools(mut self, client_tools: ClientTools) -> Self {
+        self.client_tools = Some(client_tools);
+        self
+    }
+
     /// Run an agent turn with tool-use loop support.
     ///
     /// Streams events back via the channel. If the LLM responds with tool_use,
@@ -150,7 +244,10 @@ impl AgentRunner {
                                 AgentEvent::Done => {
                                     // Don't forward Done yet — we may need to continue the loop
                                 }
-                                AgentEvent::ToolResult { .. } => {
+                                AgentEvent::ToolResult { .. }
+                                | AgentEvent::ApprovalRequest { .. }
+                                | AgentEvent::ClientToolCall { .. }
+                                | AgentEvent::Cancelled => {
                                     // Shouldn't come from provider, but forward if it does
                                     let _ = tx.send(event).await;
                                 }
@@ -176,6 +273,43 @@ impl AgentRunner {
                 return Ok(());
             }
 
+            // Wait for approvals and client tools before taking the plugin
+            // lock, so a human or client taking their time does not hold up
+            // plugin reloads. Edited inputs replace the model's in the
+            // history it sees next.
+            let mut tool_calls = tool_calls;
+            let mut settled: Vec<Option<crate::sandbox::ToolCallResult>> =
+                Vec::with_capacity(tool_calls.len());
+            for (id, name, input) in &mut tool_calls {
+                if let Some(client_tools) = self.client_tools.as_ref().filter(|c| c.handles(name)) {
+                    info!(tool = %name, tool_use_id = %id, "waiting for client tool result");
+                    let pending = client_tools.request(id);
+                    let _ = tx
+                        .send(AgentEvent::ClientToolCall {
+                            id: id.clone(),
+                            name: name.clone(),
+                            input: input.clone(),
+                            timeout_secs: client_tools.timeout().as_secs(),
+                        })
+                        .await;
+                    settled.push(Some(pending.wait().await));
+                    continue;
+                }
+                match self.review(id, name, input, &tx).await {
+                    Ok(approved) => {
+                        *input = approved;
+                        settled.push(None);
+                    }
+                    Err(reason) => {
+                        info!(tool = %name, "tool call not approved: {reason}");
+                        settled.push(Some(crate::sandbox::ToolCallResult {
+                            content: reason,
+                            is_error: true,
+                        }));
+                    }
+                }
+            }
+
             // Build the assistant message with tool_use content blocks
             let mut assistant_content: Vec<serde_json::Value> = Vec::new();
             for (id, name, input) in &tool_calls {
@@ -197,8 +331,10 @@ impl AgentRunner {
             let mut tool_result_content: Vec<serde_json::Value> = Vec::new();
             let plugin_host = plugins.read().await;
 
-            for (id, name, input) in &tool_calls {
-                let result = if plugin_host.has_plugin(name) {
+            for ((id, name, input), settled) in tool_calls.iter().zip(settled) {
+                let result = if let Some(result) = settled {
+                    result
+                } else if plugin_host.has_plugin(name) {
                     plugin_host.call_tool(name, input)
                 } else {
                     crate::sandbox::ToolCallResult {
@@ -242,6 +378,31 @@ impl AgentRunner {
         }
     }
 
+    /// Ask for approval if the gate requires it. Returns the input to run the
+    /// tool with, or why it must not run.
+    async fn review(
+        &self,
+        id: &str,
+        name: &str,
+        input: &serde_json::Value,
+        tx: &mpsc::Sender<AgentEvent>,
+    ) -> Result<serde_json::Value, String> {
+        let Some(gate) = self.approvals.as_ref().filter(|gate| gate.requires(name)) else {
+            return Ok(input.clone());
+        };
+        info!(tool = %name, tool_use_id = %id, "waiting for tool approval");
+        let pending = gate.request(id);
+        let _ = tx
+            .send(AgentEvent::ApprovalRequest {
+                id: id.to_string(),
+                name: name.to_string(),
+                input: input.clone(),
+                timeout_secs: gate.timeout().as_secs(),
+            })
+            .await;
+        pending.wait(input).await
+    }
+
     /// Simple run without tool-use loop (for backward compatibility).
     pub async fn run(
         &self,
@@ -401,6 +562,8 @@ impl Default for AgentRunner {
 
 #[cfg(test)]
 mod tests {
+    use super::approval::{ApprovalGate, Approvals, Decision};
+    use super::client_tools::{ClientCalls, ClientTools};
     use super::{AgentEvent, AgentRunner, providers};
     use crate::sandbox::PluginHost;
     use async_trait::async_trait;
@@ -467,4 +630,150 @@ mod tests {
         assert_eq!(text_count, 64);
         assert!(saw_done);
     }
+
+    /// Asks for one `fetch` call, then replies with the tool call it made and
+    /// the result it got back.
+    struct FetchProvider;
+
+    #[async_trait]
+    impl providers::LlmProvider for FetchProvider {
+        async fn call_streaming(
+            &self,
+            messages: &[serde_json::Value],
+            _tools: &[serde_json::Value],
+            _system_prompt: Option<&str>,
+            tx: mpsc::Sender<AgentEvent>,
+        ) -> anyhow::Result<()> {
+            if let [.., call, result] = messages
+                && result["content"][0]["type"] == "tool_result"
+            {
+                let summary =
+                    serde_json::json!([call["content"][0]["input"], result["content"][0]]);
+                tx.send(AgentEvent::Text(summary.to_string())).await?;
+            } else {
+                tx.send(AgentEvent::ToolUse {
+                    id: "call-1".into(),
+                    name: "fetch".into(),
+                    input: serde_json::json!({ "url": "https://example.com" }),
+                })
+                .await?;
+            }
+            tx.send(AgentEvent::Done).await?;
+            Ok(())
+        }
+    }
+
+    /// Run [`FetchProvider`] behind an approval gate and answer its approval
+    /// request with `decision`. Returns the run's events and its final reply.
+    async fn run_fetch(decision: Decision) -> (Vec<AgentEvent>, serde_json::Value) {
+        let approvals = Arc::new(Approvals::default());
+        let gate = ApprovalGate::new(
+            vec!["fetch".into()],
+            Arc::clone(&approvals),
+            "s",
+            Duration::from_secs(5),
+        );
+        let runner = AgentRunner::new().with_approvals(gate);
+        let plugins = Arc::new(RwLock::new(PluginHost::new()));
+        let (tx, mut rx) = mpsc::channel::<AgentEvent>(32);
+        let run = tokio::spawn(async move {
+            runner
+                .run_with_tools(&FetchProvider, vec![], &[], None, &plugins, tx)
+                .await
+        });
+
+        let mut events = Vec::new();
+        let mut decision = Some(decision);
+        while let Some(event) = timeout(Duration::from_secs(5), rx.recv()).await.unwrap() {
+            if let AgentEvent::ApprovalRequest { id, .. } = &event {
+                assert!(approvals.resolve(id, decision.take().unwrap()));
+            }
+            events.push(event);
+        }
+        run.await.unwrap().unwrap();
+        let text = events
+            .iter()
+            .find_map(|e| match e {
+                AgentEvent::Text(text) => serde_json::from_str(text).ok(),
+                _ => None,
+            })
+            .unwrap();
+        (events, text)
+    }
+
+    #[tokio::test]
+    async fn denied_tool_calls_are_reported_to_the_model_as_errors() {
+        let (events, text) = run_fetch(Decision::Deny {
+            reason: Some("not that site".into()),
+        })
+        .await;
+        assert!(matches!(
+            &events[1],
+            AgentEvent::ApprovalRequest { name, timeout_secs: 5, .. } if name == "fetch"
+        ));
+        assert!(matches!(
+            &events[2],
+            AgentEvent::ToolResult { is_error: true, content, .. }
+                if content == "tool call denied by the user: not that site"
+        ));
+        assert_eq!(text[1]["is_error"], true);
+        assert!(matches!(events.last(), Some(AgentEvent::Done)));
+    }
+
+    #[tokio::test]
+    async fn approved_tool_calls_run_with_the_edited_input() {
+        let edited = serde_json::json!({ "url": "https://example.org" });
+        let (_, text) = run_fetch(Decision::Approve {
+            input: Some(edited.clone()),
+        })
+        .await;
+        // The model sees the input the tool actually ran with. No plugin is
+        // loaded, so the call itself fails.
+        assert_eq!(text[0], edited);
+        assert_eq!(text[1]["content"], "unknown tool: fetch");
+    }
+
+    #[tokio::test]
+    async fn client_tool_calls_are_answered_by_the_client() {
+        let calls = Arc::new(ClientCalls::default());
+        let client_tools = ClientTools::new(
+            vec!["fetch".into()],
+            Arc::clone(&calls),
+            Duration::from_secs(5),
+        );
+        let runner = AgentRunner::new().with_client_tools(client_tools);
+        let plugins = Arc::new(RwLock::new(PluginHost::new()));
+        let (tx, mut rx) = mpsc::channel::<AgentEvent>(32);
+        let run = tokio::spawn(async move {
+            runner
+                .run_with_tools(&FetchProvider, vec![], &[], None, &plugins, tx)
+                .await
+        });
+
+        let mut events = Vec::new();
+        while let Some(event) = timeout(Duration::from_secs(5), rx.recv()).await.unwrap() {
+            if let AgentEvent::ClientToolCall { id, .. } = &event {
+                let result = crate::sandbox::ToolCallResult {
+                    content: "<html>".into(),
+                    is_error: false,
+                };
+                assert!(calls.resolve(id, result));
+            }
+            events.push(event);
+        }
+        run.await.unwrap().unwrap();
+        assert!(matches!(
+            &events[1],
+            AgentEvent::ClientToolCall { name, timeout_secs: 5, .. } if name == "fetch"
+        ));
+        assert!(matches!(
+            &events[2],
+            AgentEvent::ToolResult { is_error: false, content, .. } if content == "<html>"
+        ));
+        let AgentEvent::Text(text) = &events[3] else {
+            panic!("expected the model's reply, got {:?}", events[3]);
+        };
+        let text: serde_json::Value = serde_json::from_str(text).unwrap();
+        assert_eq!(text[1]["content"], "<html>");
+    }
 }
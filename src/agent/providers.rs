@@ -377,7 +377,7 @@ impl LlmProvider for OpenAiProvider {
                 "content": system,
             }));
         }
-        all_messages.extend_from_slice(messages);
+        all_messages.extend(messages.iter().map(openai_message));
 
         let mut body = serde_json::json!({
             "model": self.model,
@@ -570,8 +570,67 @@ impl LlmProvider for OpenAiProvider {
     }
 }
 
+/// Records call duration and failures for `/metrics`.
+///
+/// Providers report HTTP errors as `AgentEvent::Error` rather than `Err`, so
+/// events are relayed through here to see them.
+struct Observed {
+    inner: Box<dyn LlmProvider>,
+    provider: String,
+    model: String,
+}
+
+#[async_trait]
+impl LlmProvider for Observed {
+    async fn call_streaming(
+        &self,
+        messages: &[serde_json::Value],
+        tools: &[serde_json::Value],
+        system_prompt: Option<&str>,
+        tx: mpsc::Sender<AgentEvent>,
+    ) -> anyhow::Result<()> {
+        let started = std::time::Instant::now();
+        let (inner_tx, mut inner_rx) = mpsc::channel(32);
+        let call = self
+            .inner
+            .call_streaming(messages, tools, system_prompt, inner_tx);
+        let relay = async {
+            let mut failed = false;
+            while let Some(event) = inner_rx.recv().await {
+                failed |= matches!(event, AgentEvent::Error(_));
+                let _ = tx.send(event).await;
+            }
+            failed
+        };
+        let (result, failed) = tokio::join!(call, relay);
+
+        let labels = [
+            ("provider", self.provider.as_str()),
+            ("model", self.model.as_str()),
+        ];
+        crate::metrics::observe(
+            crate::metrics::PROVIDER_REQUEST_SECONDS,
+            &labels,
+            started.elapsed(),
+        );
+        if failed || result.is_err() {
+            crate::metrics::inc(crate::metrics::PROVIDER_ERRORS, &labels);
+        }
+        result
+    }
+}
+
 /// Create a provider from config.
 pub fn from_config(config: &crate::config::AgentDefConfig) -> anyhow::Result<Box<dyn LlmProvider>> {
+    let inner = build(config)?;
+    Ok(Box::new(Observed {
+        inner,
+        provider: config.provider.clone(),
+        model: config.model.clone(),
+    }))
+}
+
+fn build(config: &crate::config::AgentDefConfig) -> anyhow::Result<Box<dyn LlmProvider>> {
     if config.provider == "mock" {
         return Ok(Box::new(MockProvider));
     }
@@ -646,6 +705,54 @@ pub fn build_openai_tools(schemas: &[serde_json::Value]) -> Vec<serde_json::Valu
         .collect()
 }
 
+/// Convert attachment blocks in a message to OpenAI content parts: images
+/// become `image_url` parts and inline PDFs `file` parts. OpenAI cannot fetch
+/// documents by URL, so the model is given the link as text.
+fn openai_message(message: &serde_json::Value) -> serde_json::Value {
+    let Some(blocks) = message["content"].as_array() else {
+        return message.clone();
+    };
+    let mut message = message.clone();
+    message["content"] = blocks
+        .iter()
+        .map(|block| {
+            let kind = block["type"].as_str();
+            if !matches!(kind, Some("image" | "document")) {
+                return block.clone();
+            }
+            let source = &block["source"];
+            let inline = source["type"] == "base64";
+            let url = if inline {
+                format!(
+                    "data:{};base64,{}",
+                    source["media_type"].as_str().unwrap_or_default(),
+                    source["data"].as_str().unwrap_or_default()
+                )
+            } else {
+                source["url"].as_str().unwrap_or_default().to_string()
+            };
+            match kind {
+                Some("image") => serde_json::json!({
+                    "type": "image_url",
+                    "image_url": { "url": url },
+                }),
+                Some("document") if inline => serde_json::json!({
+                    "type": "file",
+                    "file": {
+                        "filename": block["title"].as_str().unwrap_or("document.pdf"),
+                        "file_data": url,
+                    },
+                }),
+                _ => serde_json::json!({
+                    "type": "text",
+                    "text": format!("[attached document: {url}]"),
+                }),
+            }
+        })
+        .collect();
+    message
+}
+
 /// Build tool schemas in the right format for a given provider.
 pub fn build_tools_for_provider(
     provider: &str,
@@ -660,7 +767,8 @@ pub fn build_tools_for_provider(
 
 #[cfg(test)]
 mod tests {
-    use super::{parse_sse_fields, pop_next_sse_event};
+    use super::{openai_message, parse_sse_fields, pop_next_sse_event};
+    use crate::types::{Attachment, AttachmentSource, Message};
 
     #[test]
     fn pop_next_sse_event_handles_crlf() {
@@ -687,4 +795,47 @@ mod tests {
         assert_eq!(event_type, "message_delta");
         assert_eq!(data, "{\"a\":1}\n{\"b\":2}");
     }
+
+    #[test]
+    fn attachments_become_openai_content_parts() {
+        let png = Attachment {
+            mime_type: "image/png".into(),
+            source: AttachmentSource::Base64 {
+                data: "iVBORw0KGgo=".into(),
+            },
+            name: None,
+        };
+        let pdf = Attachment {
+            mime_type: "application/pdf".into(),
+            source: AttachmentSource::Url {
+                url: "https://example.com/report.pdf".into(),
+            },
+            name: Some("report.pdf".into()),
+        };
+        let message = Message::with_attachments("user", "what's this?", vec![png, pdf])
+            .as_provider_message()
+            .unwrap();
+        assert_eq!(message["content"][0]["type"], "image");
+        assert_eq!(message["content"][1]["type"], "document");
+
+        let converted = openai_message(&message);
+        let parts = converted["content"].as_array().unwrap();
+        assert_eq!(parts[0]["type"], "image_url");
+        assert_eq!(
+            parts[0]["image_url"]["url"],
+            "data:image/png;base64,iVBORw0KGgo="
+        );
+        assert_eq!(
+            parts[1]["text"],
+            "[attached document: https://example.com/report.pdf]"
+        );
+        assert_eq!(
+            parts[2],
+            serde_json::json!({ "type": "text", "text": "what's this?" })
+        );
+
+        // Plain text messages pass through untouched.
+        let text = serde_json::json!({ "role": "user", "content": "hi" });
+        assert_eq!(openai_message(&text), text);
+    }
 }
@@ -0,0 +1,470 @@
+//! Security audit events.
+//!
+//! Every event goes to the `exoclaw::audit` tracing target so it can be
+//! filtered and shipped separately from ordinary logs. With `[audit] path`
+//! set, events are also appended as JSON lines to that file, which is rotated
+//! by size (`audit.jsonl` → `audit.jsonl.1` → ...).
+//!
+//! A line is `{"ts": ..., "kind": ..., <details>}`. Details use the common
+//! keys `remote`, `method`, `request_id` and `session_key` where they apply,
+//! so `exoclaw audit tail` can filter on them.
+
+use chrono::{DateTime, Utc};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::config::AuditConfig;
+
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+/// Open the audit file from config, replacing any previous one. Without a
+/// configured path, events only go to tracing.
+pub fn init(config: &AuditConfig) -> anyhow::Result<()> {
+    let sink = match &config.path {
+        Some(path) => Some(
+            Sink::open(path, config.max_bytes, config.max_files)
+                .map_err(|e| anyhow::anyhow!("failed to open audit log {path}: {e}"))?,
+        ),
+        None => None,
+    };
+    *SINK.lock().unwrap_or_else(|e| e.into_inner()) = sink;
+    Ok(())
+}
+
+/// Record a security-relevant event, e.g. `record("rate_limited", json!({...}))`.
+pub fn record(kind: &str, details: serde_json::Value) {
+    warn!(target: "exoclaw::audit", kind, details = %details, "audit event");
+
+    let mut sink = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(sink) = sink.as_mut() else {
+        return;
+    };
+    let mut line = serde_json::Map::new();
+    line.insert("ts".into(), Utc::now().to_rfc3339().into());
+    line.insert("kind".into(), kind.into());
+    match details {
+        serde_json::Value::Object(details) => line.extend(details),
+        serde_json::Value::Null => {}
+        other => {
+            line.insert("details".into(), other);
+        }
+    }
+    let line = serde_json::Value::Object(line).to_string();
+    if let Err(e) = sink.write(&line) {
+        warn!("failed to write audit log {}: {e}", sink.path.display());
+    }
+}
+
+/// Append-only writer with size-based rotation.
+struct Sink {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl Sink {
+    fn open(path: impl AsRef<Path>, max_bytes: u64, max_files: usize) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_bytes,
+            max_files,
+        })
+    }
+
+    fn write(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(format!("{line}\n").as_bytes())?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Shift `path.N` to `path.N+1`, dropping the oldest, and start a new file.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(rotated(&self.path, self.max_files));
+            for n in (1..self.max_files).rev() {
+                let from = rotated(&self.path, n);
+                if from.exists() {
+                    std::fs::rename(&from, rotated(&self.path, n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated(&self.path, 1))?;
+        }
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> std::io::Result<File> {
+    let file = File::options().create(true).append(true).open(path)?;
+    crate::fs_util::set_secure_file_permissions(path)
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    Ok(file)
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Which events `exoclaw audit tail` shows. Empty fields match everything.
+#[derive(Debug, Default, Clone)]
+pub struct Filter {
+    /// Event kinds, e.g. `auth_failed`; any of them matches.
+    pub kinds: Vec<String>,
+    /// Prefix of the `remote` address.
+    pub remote: Option<String>,
+    pub method: Option<String>,
+    pub session_key: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl Filter {
+    pub fn matches(&self, event: &serde_json::Value) -> bool {
+        let field = |name: &str| event.get(name).and_then(|v| v.as_str());
+        if !self.kinds.is_empty() && !self.kinds.iter().any(|k| field("kind") == Some(k)) {
+            return false;
+        }
+        if let Some(remote) = &self.remote
+            && !field("remote").is_some_and(|r| r.starts_with(remote.as_str()))
+        {
+            return false;
+        }
+        if self.method.is_some() && field("method") != self.method.as_deref() {
+            return false;
+        }
+        if self.session_key.is_some() && field("session_key") != self.session_key.as_deref() {
+            return false;
+        }
+        if let Some(since) = self.since {
+            let at = field("ts").and_then(|ts| DateTime::parse_from_rfc3339(ts).ok());
+            if at.is_none_or(|at| at < since) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn keep(&self, line: &str) -> bool {
+        serde_json::from_str(line).is_ok_and(|event| self.matches(&event))
+    }
+}
+
+/// Parse `--since`: an RFC 3339 time, or an age like `30m`, `2h`, `7d`.
+pub fn parse_since(value: &str) -> anyhow::Result<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let (amount, unit) = value.split_at(value.len().saturating_sub(1));
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid --since '{value}': use e.g. 30m, 2h, 7d"))?;
+    let age = match unit {
+        "s" => chrono::Duration::seconds(amount),
+        "m" => chrono::Duration::minutes(amount),
+        "h" => chrono::Duration::hours(amount),
+        "d" => chrono::Duration::days(amount),
+        _ => anyhow::bail!("invalid --since '{value}': use e.g. 30m, 2h, 7d"),
+    };
+    Ok(Utc::now() - age)
+}
+
+/// The last `count` matching lines, oldest first, across rotated files.
+pub fn tail(path: &Path, filter: &Filter, count: usize) -> anyhow::Result<Vec<String>> {
+    Ok(tail_from(path, filter, count)?.0)
+}
+
+/// [`tail`], plus a [`Follower`] that starts where the tail stopped reading,
+/// so following neither repeats nor skips a line.
+pub fn tail_and_follow(
+    path: &Path,
+    filter: &Filter,
+    count: usize,
+) -> anyhow::Result<(Vec<String>, Follower)> {
+    let (lines, ReadMark { file, offset }) = tail_from(path, filter, count)?;
+    let follower = Follower {
+        path: path.to_path_buf(),
+        file,
+        offset,
+        partial: String::new(),
+    };
+    Ok((lines, follower))
+}
+
+/// Where [`tail`] stopped in the live file: its identity and the offset just
+/// past its last complete line.
+#[derive(Default)]
+struct ReadMark {
+    file: Option<FileId>,
+    offset: u64,
+}
+
+fn tail_from(
+    path: &Path,
+    filter: &Filter,
+    count: usize,
+) -> anyhow::Result<(Vec<String>, ReadMark)> {
+    let mut files: Vec<PathBuf> = (1..)
+        .map(|n| rotated(path, n))
+        .take_while(|p| p.exists())
+        .collect();
+    files.reverse();
+    files.push(path.to_path_buf());
+
+    let mut lines = std::collections::VecDeque::with_capacity(count);
+    let mut mark = ReadMark::default();
+    for name in files {
+        let file = match File::open(&name) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => anyhow::bail!("failed to read {}: {e}", name.display()),
+        };
+        let id = file_id(&file.metadata()?);
+        let mut reader = BufReader::new(file);
+        let mut offset = 0;
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf)?;
+            // A line still being written is left for the follower.
+            if read == 0 || buf.last() != Some(&b'\n') {
+                break;
+            }
+            offset += read as u64;
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_end();
+            if filter.keep(line) {
+                if lines.len() == count {
+                    lines.pop_front();
+                }
+                lines.push_back(line.to_string());
+            }
+        }
+        if name == path {
+            mark = ReadMark { file: id, offset };
+        }
+    }
+    Ok((lines.into(), mark))
+}
+
+/// Device and inode, to tell a rotated-in file from the one being followed.
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn file_id(meta: &std::fs::Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &std::fs::Metadata) -> Option<FileId> {
+    None
+}
+
+/// Reads lines appended to the audit file after it was opened, following
+/// it across rotations.
+pub struct Follower {
+    path: PathBuf,
+    /// The file being read; where identity is unknown, a shrinking file
+    /// is taken as rotated.
+    file: Option<FileId>,
+    offset: u64,
+    partial: String,
+}
+
+impl Follower {
+    /// Start at the current end of the file.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let meta = std::fs::metadata(&path).ok();
+        Self {
+            file: meta.as_ref().and_then(file_id),
+            offset: meta.map(|m| m.len()).unwrap_or(0),
+            path,
+            partial: String::new(),
+        }
+    }
+
+    /// Matching lines written since the last poll.
+    pub fn poll(&mut self, filter: &Filter) -> std::io::Result<Vec<String>> {
+        let Ok(mut file) = File::open(&self.path) else {
+            return Ok(Vec::new());
+        };
+        let meta = file.metadata()?;
+        let id = file_id(&meta);
+        let rotated_out = match (self.file, id) {
+            (Some(old), Some(new)) => old != new,
+            _ => meta.len() < self.offset,
+        };
+
+        let mut lines = Vec::new();
+        if rotated_out {
+            // Finish the old file, now `.1`, then start the new one from scratch.
+            if let Some(old) = self.file
+                && let Ok(mut previous) = File::open(rotated(&self.path, 1))
+                && file_id(&previous.metadata()?) == Some(old)
+            {
+                previous.seek(SeekFrom::Start(self.offset))?;
+                previous.read_to_string(&mut self.partial)?;
+                self.drain(filter, &mut lines);
+            }
+            self.offset = 0;
+            self.partial.clear();
+        }
+        self.file = id;
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut chunk = String::new();
+        self.offset += file.read_to_string(&mut chunk)? as u64;
+        self.partial.push_str(&chunk);
+        self.drain(filter, &mut lines);
+        Ok(lines)
+    }
+
+    /// Move complete lines out of `partial`.
+    fn drain(&mut self, filter: &Filter, lines: &mut Vec<String>) {
+        while let Some(end) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=end).collect();
+            let line = line.trim_end();
+            if filter.keep(line) {
+                lines.push(line.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tmp_path() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("exoclaw-audit-{}", uuid::Uuid::new_v4()));
+        dir.join("audit.jsonl")
+    }
+
+    fn event(kind: &str, remote: &str) -> String {
+        serde_json::json!({ "ts": Utc::now().to_rfc3339(), "kind": kind, "remote": remote })
+            .to_string()
+    }
+
+    #[test]
+    fn rotates_by_size_and_tails_across_files() {
+        let path = tmp_path();
+        let line = event("auth_failed", "10.0.0.1");
+        // Room for two lines per file, keeping two rotated files.
+        let mut sink = Sink::open(&path, 2 * (line.len() as u64 + 1), 2).unwrap();
+        for i in 0..7 {
+            let kind = if i % 2 == 0 { "auth_failed" } else { "auth_ok" };
+            sink.write(&event(kind, &format!("10.0.0.{i}"))).unwrap();
+        }
+        assert!(rotated(&path, 2).exists());
+        assert!(!rotated(&path, 3).exists());
+
+        // Seven lines written; the oldest file (lines 0-1) was dropped.
+        let all = tail(&path, &Filter::default(), 100).unwrap();
+        assert_eq!(all.len(), 5);
+        assert!(all[0].contains("10.0.0.2"));
+
+        let failed = Filter {
+            kinds: vec!["auth_failed".into()],
+            ..Default::default()
+        };
+        let last = tail(&path, &failed, 2).unwrap();
+        assert_eq!(last.len(), 2);
+        assert!(last[0].contains("10.0.0.4") && last[1].contains("10.0.0.6"));
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn follower_sees_appends_and_rotation() {
+        let path = tmp_path();
+        let mut sink = Sink::open(&path, 1 << 20, 1).unwrap();
+        sink.write(&event("auth_ok", "10.0.0.1")).unwrap();
+
+        let mut follower = Follower::new(&path);
+        let filter = Filter {
+            remote: Some("10.0.0.".into()),
+            ..Default::default()
+        };
+        assert!(follower.poll(&filter).unwrap().is_empty());
+
+        sink.write(&event("auth_failed", "10.0.0.2")).unwrap();
+        sink.write(&event("auth_failed", "192.168.0.1")).unwrap();
+        assert_eq!(follower.poll(&filter).unwrap().len(), 1);
+
+        sink.rotate().unwrap();
+        sink.write(&event("rate_limited", "10.0.0.3")).unwrap();
+        let lines = follower.poll(&filter).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("rate_limited"));
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn follower_starts_where_the_tail_stopped() {
+        let path = tmp_path();
+        let mut sink = Sink::open(&path, 1 << 20, 1).unwrap();
+        sink.write(&event("auth_ok", "10.0.0.1")).unwrap();
+
+        let all = Filter::default();
+        let (lines, mut follower) = tail_and_follow(&path, &all, 10).unwrap();
+        assert_eq!(lines.len(), 1);
+        sink.write(&event("auth_failed", "10.0.0.2")).unwrap();
+        let lines = follower.poll(&all).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("10.0.0.2"));
+
+        // Written before a rotation, then the new file grows past the old
+        // offset before the next poll: nothing is lost or repeated.
+        sink.write(&event("auth_failed", "10.0.0.3")).unwrap();
+        sink.rotate().unwrap();
+        for i in 4..8 {
+            sink.write(&event("auth_failed", &format!("10.0.0.{i}")))
+                .unwrap();
+        }
+        let lines = follower.poll(&all).unwrap();
+        assert_eq!(lines.len(), 5, "{lines:?}");
+        for (line, i) in lines.iter().zip(3..) {
+            assert!(line.contains(&format!("10.0.0.{i}")), "{line}");
+        }
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn since_accepts_ages_and_timestamps() {
+        let hour_ago = parse_since("1h").unwrap();
+        assert!((Utc::now() - hour_ago).num_minutes() == 60);
+        assert!(parse_since("2024-01-01T00:00:00Z").is_ok());
+        assert!(parse_since("soon").is_err());
+
+        let filter = Filter {
+            since: Some(hour_ago),
+            ..Default::default()
+        };
+        assert!(filter.keep(&event("auth_ok", "::1")));
+        assert!(!filter.keep(r#"{"ts":"2020-01-01T00:00:00Z","kind":"auth_ok"}"#));
+    }
+}
@@ -1,14 +1,18 @@
 use crate::fs_util::{home_dir, set_secure_dir_permissions, set_secure_file_permissions};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use tracing::info;
 
 /// Top-level configuration loaded from TOML.
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct ExoclawConfig {
     pub gateway: GatewayConfig,
     pub agent: AgentDefConfig,
+    /// Additional named agents that bindings can route to by `agent_id`.
+    #[serde(default)]
+    pub agents: Vec<AgentDefConfig>,
     #[serde(default)]
     pub plugins: Vec<PluginConfig>,
     #[serde(default)]
@@ -17,14 +21,117 @@ pub struct ExoclawConfig {
     pub budgets: BudgetConfig,
     #[serde(default)]
     pub memory: MemoryConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+}
+
+impl ExoclawConfig {
+    /// Look up an agent definition by id in the `[[agents]]` registry or the
+    /// primary `[agent]` table.
+    pub fn find_agent(&self, id: &str) -> Option<&AgentDefConfig> {
+        self.agents
+            .iter()
+            .find(|a| a.id == id)
+            .or_else(|| (self.agent.id == id).then_some(&self.agent))
+    }
+
+    /// Resolve the agent definition for a routed agent id.
+    ///
+    /// Falls back to the primary `[agent]` table for ids that are not in the
+    /// registry (e.g. the router's default route).
+    pub fn agent_def(&self, id: &str) -> &AgentDefConfig {
+        self.find_agent(id).unwrap_or(&self.agent)
+    }
+
+    /// Tools an agent may only call with approval: its own `require_approval`
+    /// list plus every `[[plugins]]` entry marked `require_approval`. Plugins
+    /// loaded with `plugin.load` are added from the plugin host.
+    pub fn approval_tools(&self, agent: &AgentDefConfig) -> Vec<String> {
+        let plugins = self
+            .plugins
+            .iter()
+            .filter(|plugin| plugin.require_approval)
+            .map(|plugin| plugin.name.clone());
+        agent
+            .require_approval
+            .iter()
+            .cloned()
+            .chain(plugins)
+            .collect()
+    }
+
+    /// All configured agent definitions: the primary agent first, then the registry.
+    pub fn all_agents(&self) -> impl Iterator<Item = &AgentDefConfig> {
+        std::iter::once(&self.agent).chain(self.agents.iter())
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GatewayConfig {
     #[serde(default = "default_port")]
     pub port: u16,
     #[serde(default = "default_bind")]
     pub bind: String,
+    /// Named API tokens with scoped access (`[[gateway.tokens]]`).
+    #[serde(default)]
+    pub tokens: Vec<ApiTokenConfig>,
+    /// TOML file with `active`/`next` tokens and `[[tokens]]`, re-read on
+    /// SIGHUP or `auth.reload`.
+    pub token_file: Option<String>,
+    /// How long a connection may stay open after its token is rotated out.
+    #[serde(default = "default_token_grace_secs")]
+    pub token_grace_secs: u64,
+    /// How long shutdown waits for in-flight agent runs before cancelling them.
+    #[serde(default = "default_shutdown_drain_secs")]
+    pub shutdown_drain_secs: u64,
+    /// How long a tool call waits for `tool.approve` before it is denied.
+    #[serde(default = "default_tool_approval_timeout_secs")]
+    pub tool_approval_timeout_secs: u64,
+    /// How long a client tool call waits for `tool.result` before failing.
+    #[serde(default = "default_client_tool_timeout_secs")]
+    pub client_tool_timeout_secs: u64,
+    /// Limits on `chat.send` and webhook calls (`[gateway.rate_limits]`).
+    #[serde(default)]
+    pub rate_limits: RateLimitConfig,
+    /// Largest WebSocket message or HTTP request body accepted.
+    #[serde(default = "default_max_message_bytes")]
+    pub max_message_bytes: usize,
+    /// Longest `content` accepted in a chat message, in characters.
+    #[serde(default = "default_max_content_chars")]
+    pub max_content_chars: usize,
+    /// Attachments allowed on one chat message. 0 turns attachments off.
+    #[serde(default = "default_max_attachments")]
+    pub max_attachments: usize,
+    /// Largest inline attachment, in decoded bytes.
+    #[serde(default = "default_max_attachment_bytes")]
+    pub max_attachment_bytes: usize,
+    /// Open WebSocket connections allowed at once.
+    #[serde(default = "default_max_connections")]
+    pub max_connections: usize,
+    /// Agent runs in flight at once across the gateway.
+    #[serde(default = "default_max_streams")]
+    pub max_streams: usize,
+    /// Agent runs in flight at once on one WebSocket connection.
+    #[serde(default = "default_max_streams_per_connection")]
+    pub max_streams_per_connection: usize,
+    /// Browser origins (`https://app.example.com`) allowed to open `/ws`,
+    /// besides the gateway's own. `"*"` allows any origin.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Serve HTTPS/WSS (`[gateway.tls]`).
+    pub tls: Option<TlsConfig>,
+    /// Per-channel webhook settings (`[gateway.webhooks.<channel>]`).
+    #[serde(default)]
+    pub webhooks: HashMap<String, WebhookConfig>,
+    /// Bearer token for `GET /metrics`. Unset leaves the endpoint open.
+    pub metrics_token: Option<Secret>,
+}
+
+impl GatewayConfig {
+    /// Webhook settings for a channel, or the defaults.
+    pub fn webhook(&self, channel: &str) -> WebhookConfig {
+        self.webhooks.get(channel).cloned().unwrap_or_default()
+    }
 }
 
 impl Default for GatewayConfig {
@@ -32,16 +139,318 @@ impl Default for GatewayConfig {
         Self {
             port: default_port(),
             bind: default_bind(),
+            tokens: Vec::new(),
+            token_file: None,
+            token_grace_secs: default_token_grace_secs(),
+            shutdown_drain_secs: default_shutdown_drain_secs(),
+            tool_approval_timeout_secs: default_tool_approval_timeout_secs(),
+            client_tool_timeout_secs: default_client_tool_timeout_secs(),
+            rate_limits: RateLimitConfig::default(),
+            max_message_bytes: default_max_message_bytes(),
+            max_content_chars: default_max_content_chars(),
+            max_attachments: default_max_attachments(),
+            max_attachment_bytes: default_max_attachment_bytes(),
+            max_connections: default_max_connections(),
+            max_streams: default_max_streams(),
+            max_streams_per_connection: default_max_streams_per_connection(),
+            allowed_origins: Vec::new(),
+            tls: None,
+            webhooks: HashMap::new(),
+            metrics_token: None,
+        }
+    }
+}
+
+/// How `/webhook/<channel>` answers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookMode {
+    /// Hold the request open and return the formatted reply.
+    #[default]
+    Sync,
+    /// Acknowledge with 202, run in the background, and deliver the reply
+    /// through the adapter's `format_outgoing` proxy call.
+    Async,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    #[serde(default)]
+    pub mode: WebhookMode,
+    /// How long a platform message id is remembered to drop redeliveries.
+    #[serde(default = "default_dedup_ttl_secs")]
+    pub dedup_ttl_secs: u64,
+    /// Delivery attempts before a reply is dead-lettered.
+    #[serde(default = "default_delivery_attempts")]
+    pub delivery_attempts: u32,
+    /// Wait before the first retry; doubles on each attempt.
+    #[serde(default = "default_delivery_backoff_ms")]
+    pub delivery_backoff_ms: u64,
+    /// Require an HMAC-SHA256 signature over the request body.
+    #[serde(default)]
+    pub hmac: Option<HmacVerifyConfig>,
+    /// Require a static shared-secret header (e.g. Telegram's
+    /// `X-Telegram-Bot-Api-Secret-Token`).
+    #[serde(default)]
+    pub secret_token: Option<SecretTokenConfig>,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            mode: WebhookMode::default(),
+            dedup_ttl_secs: default_dedup_ttl_secs(),
+            delivery_attempts: default_delivery_attempts(),
+            delivery_backoff_ms: default_delivery_backoff_ms(),
+            hmac: None,
+            secret_token: None,
+        }
+    }
+}
+
+/// HMAC-SHA256 webhook signature (`[gateway.webhooks.<channel>.hmac]`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HmacVerifyConfig {
+    /// Name of the signing secret in the credentials store
+    /// (`exoclaw secret set <name>`).
+    pub secret: String,
+    /// Header carrying the hex digest, e.g. `X-Hub-Signature-256`.
+    pub header: String,
+    /// Text in front of the digest, e.g. `sha256=`.
+    #[serde(default)]
+    pub prefix: String,
+    /// Header carrying the unix timestamp the platform signed. When set,
+    /// requests outside `timestamp_tolerance_secs` are rejected as replays.
+    #[serde(default)]
+    pub timestamp_header: Option<String>,
+    /// What the platform signs; `{timestamp}` and `{body}` are substituted,
+    /// e.g. Slack's `v0:{timestamp}:{body}`.
+    #[serde(default = "default_signed_payload")]
+    pub signed_payload: String,
+    #[serde(default = "default_timestamp_tolerance_secs")]
+    pub timestamp_tolerance_secs: u64,
+}
+
+/// Static shared-secret header (`[gateway.webhooks.<channel>.secret_token]`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SecretTokenConfig {
+    /// Name of the secret in the credentials store.
+    pub secret: String,
+    /// Header that must carry the secret verbatim.
+    pub header: String,
+}
+
+fn default_dedup_ttl_secs() -> u64 {
+    3600
+}
+fn default_delivery_attempts() -> u32 {
+    5
+}
+fn default_delivery_backoff_ms() -> u64 {
+    1000
+}
+fn default_signed_payload() -> String {
+    "{body}".to_string()
+}
+fn default_timestamp_tolerance_secs() -> u64 {
+    300
+}
+
+/// TLS certificate for the gateway. The files are re-read when they change.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TlsConfig {
+    /// PEM certificate chain.
+    pub cert_path: Option<String>,
+    /// PEM private key.
+    pub key_path: Option<String>,
+    /// Generate an ephemeral self-signed certificate instead (local testing).
+    #[serde(default)]
+    pub self_signed: bool,
+}
+
+/// Token-bucket limits, each keyed separately. Unset means unlimited.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct RateLimitConfig {
+    /// Per remote IP address.
+    pub ip: Option<RateLimit>,
+    /// Per session key.
+    pub session: Option<RateLimit>,
+    /// Per channel (`websocket`, `telegram`, ...).
+    pub channel: Option<RateLimit>,
+    /// Per auth token name.
+    pub token: Option<RateLimit>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RateLimit {
+    /// Sustained rate.
+    pub per_minute: u32,
+    /// Requests allowed at once before the rate applies. Defaults to `per_minute`.
+    pub burst: Option<u32>,
+}
+
+impl RateLimit {
+    pub fn capacity(&self) -> f64 {
+        f64::from(self.burst.unwrap_or(self.per_minute))
+    }
+
+    pub fn per_second(&self) -> f64 {
+        f64::from(self.per_minute) / 60.0
+    }
+}
+
+/// A named API token and what it may do.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ApiTokenConfig {
+    pub name: String,
+    pub token: String,
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
+    /// Limit `chat.send` and `session.*` to this channel.
+    pub channel: Option<String>,
+    /// Limit `chat.send` and `session.*` to this account.
+    pub account: Option<String>,
+}
+
+/// Keeps the token itself out of config dumps and error logs.
+impl std::fmt::Debug for ApiTokenConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiTokenConfig")
+            .field("name", &self.name)
+            .field("token", &"<redacted>")
+            .field("scopes", &self.scopes)
+            .field("channel", &self.channel)
+            .field("account", &self.account)
+            .finish()
+    }
+}
+
+/// A secret config value, such as a bearer token. Serialized as the plain
+/// string; debug output shows `<redacted>`.
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/// A permission a token can grant. Methods that need none (`ping`) are open
+/// to every authenticated caller; anything not mapped needs `admin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Scope {
+    #[serde(rename = "chat:send")]
+    ChatSend,
+    #[serde(rename = "session:read")]
+    SessionRead,
+    #[serde(rename = "session:write")]
+    SessionWrite,
+    #[serde(rename = "plugin:admin")]
+    PluginAdmin,
+    #[serde(rename = "usage:read")]
+    UsageRead,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 6] = [
+        Scope::ChatSend,
+        Scope::SessionRead,
+        Scope::SessionWrite,
+        Scope::PluginAdmin,
+        Scope::UsageRead,
+        Scope::Admin,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::ChatSend => "chat:send",
+            Scope::SessionRead => "session:read",
+            Scope::SessionWrite => "session:write",
+            Scope::PluginAdmin => "plugin:admin",
+            Scope::UsageRead => "usage:read",
+            Scope::Admin => "admin",
+        }
+    }
+
+    /// The scope an RPC method needs, or `None` for methods open to any caller.
+    /// Unlisted methods need `admin`, so new methods are denied by default.
+    pub fn for_method(method: &str) -> Option<Scope> {
+        match method {
+            "ping" => None,
+            "chat.send" | "chat.cancel" | "chat.resume" | "tool.approve" | "tool.deny"
+            | "tool.result" => Some(Scope::ChatSend),
+            "session.list"
+            | "session.get"
+            | "session.history"
+            | "session.subscribe"
+            | "session.unsubscribe" => Some(Scope::SessionRead),
+            "session.reset" | "session.delete" => Some(Scope::SessionWrite),
+            "status" => Some(Scope::UsageRead),
+            m if m.starts_with("plugin.") => Some(Scope::PluginAdmin),
+            _ => Some(Scope::Admin),
         }
     }
 }
 
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 fn default_port() -> u16 {
     7200
 }
 fn default_bind() -> String {
     "127.0.0.1".into()
 }
+fn default_token_grace_secs() -> u64 {
+    300
+}
+fn default_shutdown_drain_secs() -> u64 {
+    30
+}
+fn default_tool_approval_timeout_secs() -> u64 {
+    300
+}
+fn default_client_tool_timeout_secs() -> u64 {
+    60
+}
+fn default_max_message_bytes() -> usize {
+    1024 * 1024
+}
+fn default_max_content_chars() -> usize {
+    100_000
+}
+fn default_max_attachments() -> usize {
+    4
+}
+fn default_max_attachment_bytes() -> usize {
+    128 * 1024
+}
+fn default_max_connections() -> usize {
+    1024
+}
+fn default_max_streams() -> usize {
+    256
+}
+fn default_max_streams_per_connection() -> usize {
+    16
+}
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AgentDefConfig {
@@ -58,6 +467,9 @@ pub struct AgentDefConfig {
     pub soul_path: Option<String>,
     #[serde(default)]
     pub tools: Vec<String>,
+    /// Tools this agent may only call once a client approves; `"*"` for all.
+    #[serde(default)]
+    pub require_approval: Vec<String>,
     pub fallback: Option<Box<AgentDefConfig>>,
 }
 
@@ -72,6 +484,7 @@ impl Default for AgentDefConfig {
             system_prompt: None,
             soul_path: None,
             tools: Vec::new(),
+            require_approval: Vec::new(),
             fallback: None,
         }
     }
@@ -96,6 +509,9 @@ pub struct PluginConfig {
     pub path: String,
     #[serde(default)]
     pub capabilities: Vec<String>,
+    /// Hold this plugin's tool calls until a client approves them.
+    #[serde(default)]
+    pub require_approval: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -132,6 +548,36 @@ impl Default for MemoryConfig {
     }
 }
 
+/// Security audit log (`[audit]`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuditConfig {
+    /// JSONL file to append events to. Unset keeps events in tracing only.
+    pub path: Option<String>,
+    /// Rotate once the file would grow past this size.
+    #[serde(default = "default_audit_max_bytes")]
+    pub max_bytes: u64,
+    /// Rotated files kept (`audit.jsonl.1` ... `audit.jsonl.N`).
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_bytes: default_audit_max_bytes(),
+            max_files: default_audit_max_files(),
+        }
+    }
+}
+
+fn default_audit_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+fn default_audit_max_files() -> usize {
+    5
+}
+
 fn default_episodic_window() -> u32 {
     5
 }
@@ -149,14 +595,7 @@ pub fn load() -> anyhow::Result<ExoclawConfig> {
     let path = resolve_path();
 
     if path.exists() {
-        let content = std::fs::read_to_string(&path)
-            .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", path.display()))?;
-        let mut config: ExoclawConfig = toml::from_str(&content)
-            .map_err(|e| anyhow::anyhow!("invalid config at {}: {e}", path.display()))?;
-
-        resolve_api_key(&mut config);
-        validate(&config)?;
-
+        let config = load_from(&path)?;
         info!("loaded config from {}", path.display());
         Ok(config)
     } else {
@@ -167,6 +606,18 @@ pub fn load() -> anyhow::Result<ExoclawConfig> {
     }
 }
 
+/// Read, resolve API keys for and validate the config file at `path`.
+pub fn load_from(path: &Path) -> anyhow::Result<ExoclawConfig> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", path.display()))?;
+    let mut config: ExoclawConfig = toml::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("invalid config at {}: {e}", path.display()))?;
+
+    resolve_api_key(&mut config);
+    validate(&config)?;
+    Ok(config)
+}
+
 /// Resolve config file path based on EXOCLAW_CONFIG or ~/.exoclaw/config.toml.
 pub fn resolve_path() -> PathBuf {
     if let Ok(path) = std::env::var("EXOCLAW_CONFIG") {
@@ -201,10 +652,18 @@ pub fn save_to_path(config: &ExoclawConfig, path: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
-/// Resolve API key from environment variables if not set in config.
+/// Resolve API keys from environment variables for every agent that does not
+/// set one in config.
 fn resolve_api_key(config: &mut ExoclawConfig) {
-    if config.agent.api_key.is_none() {
-        config.agent.api_key = match config.agent.provider.as_str() {
+    resolve_agent_api_key(&mut config.agent);
+    for agent in &mut config.agents {
+        resolve_agent_api_key(agent);
+    }
+}
+
+fn resolve_agent_api_key(agent: &mut AgentDefConfig) {
+    if agent.api_key.is_none() {
+        agent.api_key = match agent.provider.as_str() {
             "anthropic" => std::env::var("ANTHROPIC_API_KEY")
                 .ok()
                 .or_else(|| crate::secrets::load_api_key("anthropic")),
@@ -217,18 +676,122 @@ fn resolve_api_key(config: &mut ExoclawConfig) {
 }
 
 /// Validate the config and return clear error messages.
-fn validate(config: &ExoclawConfig) -> anyhow::Result<()> {
-    let valid_providers = ["anthropic", "openai"];
-    if !valid_providers.contains(&config.agent.provider.as_str()) {
+pub fn validate(config: &ExoclawConfig) -> anyhow::Result<()> {
+    validate_agent("agent", &config.agent)?;
+
+    let mut seen_ids = vec![config.agent.id.as_str()];
+    for (i, agent) in config.agents.iter().enumerate() {
+        let label = format!("agents[{i}]");
+        if agent.id.trim().is_empty() {
+            anyhow::bail!("{label}.id cannot be empty");
+        }
+        if seen_ids.contains(&agent.id.as_str()) {
+            anyhow::bail!("{label}: agent id '{}' is defined more than once", agent.id);
+        }
+        seen_ids.push(agent.id.as_str());
+        validate_agent(&label, agent)?;
+    }
+
+    validate_tokens("gateway.tokens", &config.gateway.tokens)?;
+
+    let limits = &config.gateway.rate_limits;
+    for (name, limit) in [
+        ("ip", &limits.ip),
+        ("session", &limits.session),
+        ("channel", &limits.channel),
+        ("token", &limits.token),
+    ] {
+        let Some(limit) = limit else { continue };
+        if limit.per_minute == 0 || limit.burst == Some(0) {
+            anyhow::bail!("gateway.rate_limits.{name}: per_minute and burst must be > 0");
+        }
+    }
+
+    let gateway = &config.gateway;
+    for (name, value) in [
+        ("max_message_bytes", gateway.max_message_bytes),
+        ("max_content_chars", gateway.max_content_chars),
+        ("max_attachment_bytes", gateway.max_attachment_bytes),
+        ("max_connections", gateway.max_connections),
+        ("max_streams", gateway.max_streams),
+        (
+            "max_streams_per_connection",
+            gateway.max_streams_per_connection,
+        ),
+    ] {
+        if value == 0 {
+            anyhow::bail!("gateway.{name} must be > 0");
+        }
+    }
+
+    // Attachments arrive base64-encoded inside one message, so a message
+    // carrying the most and largest allowed attachments must still fit.
+    let encoded = gateway
+        .max_attachment_bytes
+        .div_ceil(3)
+        .saturating_mul(4)
+        .saturating_mul(gateway.max_attachments);
+    if encoded > gateway.max_message_bytes {
         anyhow::bail!(
-            "invalid provider '{}': must be one of {:?}",
-            config.agent.provider,
-            valid_providers
+            "gateway: {} attachments of max_attachment_bytes ({}) need {encoded} bytes \
+             base64-encoded, more than max_message_bytes ({})",
+            gateway.max_attachments,
+            gateway.max_attachment_bytes,
+            gateway.max_message_bytes
+        );
+    }
+
+    if config.audit.max_bytes == 0 {
+        anyhow::bail!("audit.max_bytes must be > 0");
+    }
+
+    if let Some(tls) = &gateway.tls {
+        let has_files = tls.cert_path.is_some() && tls.key_path.is_some();
+        if tls.self_signed == has_files {
+            anyhow::bail!("gateway.tls: set both cert_path and key_path, or self_signed = true");
+        }
+    }
+
+    for (channel, webhook) in &gateway.webhooks {
+        if webhook.delivery_attempts == 0 {
+            anyhow::bail!("gateway.webhooks.{channel}.delivery_attempts must be > 0");
+        }
+        if let Some(hmac) = &webhook.hmac {
+            if !hmac.signed_payload.contains("{body}") {
+                anyhow::bail!(
+                    "gateway.webhooks.{channel}.hmac.signed_payload must include {{body}}"
+                );
+            }
+            if hmac.signed_payload.contains("{timestamp}") && hmac.timestamp_header.is_none() {
+                anyhow::bail!(
+                    "gateway.webhooks.{channel}.hmac.signed_payload uses {{timestamp}} but timestamp_header is not set"
+                );
+            }
+        }
+        let headers = webhook.hmac.iter().map(|h| ("hmac.header", &h.header));
+        let headers = headers.chain(
+            webhook
+                .secret_token
+                .iter()
+                .map(|t| ("secret_token.header", &t.header)),
         );
+        for (name, header) in headers {
+            if axum::http::HeaderName::from_bytes(header.as_bytes()).is_err() {
+                anyhow::bail!(
+                    "gateway.webhooks.{channel}.{name}: '{header}' is not a valid header name"
+                );
+            }
+        }
     }
 
-    if config.agent.max_tokens == 0 {
-        anyhow::bail!("agent.max_tokens must be > 0");
+    for origin in &gateway.allowed_origins {
+        let valid =
+            origin == "*" || url::Url::parse(origin).is_ok_and(|u| u.has_host() && u.path() == "/");
+        if !valid {
+            anyhow::bail!(
+                "gateway.allowed_origins: '{origin}' must be \"*\" or scheme://host[:port]"
+            );
+        }
     }
 
     for (i, binding) in config.bindings.iter().enumerate() {
@@ -242,6 +805,51 @@ fn validate(config: &ExoclawConfig) -> anyhow::Result<()> {
                 "binding[{i}] must have at least one of: channel, account_id, peer_id, guild_id, team_id"
             );
         }
+        if config.find_agent(&binding.agent_id).is_none() {
+            anyhow::bail!(
+                "binding[{i}] references unknown agent '{}': define it under [[agents]]",
+                binding.agent_id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Check named tokens: non-empty names and secrets, names unique.
+pub fn validate_tokens(label: &str, tokens: &[ApiTokenConfig]) -> anyhow::Result<()> {
+    let mut names: Vec<&str> = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let label = format!("{label}[{i}]");
+        if token.name.trim().is_empty() {
+            anyhow::bail!("{label}.name cannot be empty");
+        }
+        if names.contains(&token.name.as_str()) {
+            anyhow::bail!(
+                "{label}: token name '{}' is defined more than once",
+                token.name
+            );
+        }
+        names.push(token.name.as_str());
+        if token.token.trim().is_empty() {
+            anyhow::bail!("{label}.token cannot be empty");
+        }
+    }
+    Ok(())
+}
+
+fn validate_agent(label: &str, agent: &AgentDefConfig) -> anyhow::Result<()> {
+    let valid_providers = ["anthropic", "openai"];
+    if !valid_providers.contains(&agent.provider.as_str()) {
+        anyhow::bail!(
+            "invalid provider '{}' for {label}: must be one of {:?}",
+            agent.provider,
+            valid_providers
+        );
+    }
+
+    if agent.max_tokens == 0 {
+        anyhow::bail!("{label}.max_tokens must be > 0");
     }
 
     Ok(())
@@ -1,5 +1,12 @@
+use std::net::IpAddr;
+use std::sync::Arc;
 use subtle::ConstantTimeEq;
 
+use super::limits::Slots;
+use crate::agent::client_tools::ClientCalls;
+use crate::config::ApiTokenConfig;
+pub use crate::config::Scope;
+
 /// Verify the initial WebSocket connect message contains a valid token.
 /// Returns true if no token is required (loopback) or if token matches.
 pub fn verify_connect(msg: &str, expected: &Option<String>) -> bool {
@@ -20,9 +27,173 @@ pub fn verify_connect(msg: &str, expected: &Option<String>) -> bool {
     }
 }
 
-fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+/// Verify an HTTP `Authorization: Bearer <token>` header value.
+/// Returns true if no token is required (loopback) or if token matches.
+pub fn verify_bearer(header: Option<&str>, expected: &Option<String>) -> bool {
+    let expected = match expected {
+        Some(t) => t,
+        None => return true, // No auth required (loopback mode)
+    };
+
+    match header.and_then(|h| h.strip_prefix("Bearer ")) {
+        Some(t) => constant_time_eq(t.trim().as_bytes(), expected.as_bytes()),
+        None => false,
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
     if a.len() != b.len() {
         return false;
     }
     a.ct_eq(b).into()
 }
+
+/// Who is on the other end of a connection or request, and what they may do.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    /// Token name from config, `"default"` for the `--token` token, or
+    /// `"loopback"` when auth is off.
+    pub name: String,
+    scopes: Vec<Scope>,
+    /// When set, chat and session access is limited to this channel.
+    pub channel: Option<String>,
+    /// When set, chat and session access is limited to this account.
+    pub account: Option<String>,
+    /// Remote address, for per-IP limits.
+    pub remote: Option<IpAddr>,
+    /// In-flight stream slots for this caller's connection.
+    pub streams: Slots,
+    /// Client tool calls waiting on this caller's WebSocket connection. HTTP
+    /// callers have none and cannot declare client tools.
+    pub client_calls: Option<Arc<ClientCalls>>,
+}
+
+impl Caller {
+    /// A caller holding every scope, with no pinned route.
+    pub fn trusted(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            scopes: Scope::ALL.to_vec(),
+            channel: None,
+            account: None,
+            remote: None,
+            streams: Slots::unlimited(),
+            client_calls: None,
+        }
+    }
+
+    pub fn from_token(token: &ApiTokenConfig) -> Self {
+        Self {
+            name: token.name.clone(),
+            scopes: token.scopes.clone(),
+            channel: token.channel.clone(),
+            account: token.account.clone(),
+            remote: None,
+            streams: Slots::unlimited(),
+            client_calls: None,
+        }
+    }
+
+    pub fn with_remote(mut self, remote: IpAddr) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    pub fn with_streams(mut self, streams: Slots) -> Self {
+        self.streams = streams;
+        self
+    }
+
+    pub fn with_client_calls(mut self, client_calls: Arc<ClientCalls>) -> Self {
+        self.client_calls = Some(client_calls);
+        self
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&Scope::Admin)
+    }
+
+    /// Whether the caller's pins allow a chat on `channel`/`account`.
+    pub fn may_route(&self, channel: &str, account: &str) -> bool {
+        self.channel.as_deref().is_none_or(|c| c == channel)
+            && self.account.as_deref().is_none_or(|a| a == account)
+    }
+
+    /// Whether the caller's pins allow access to a session key
+    /// (`{agent}:{channel}:{account}:{peer}`).
+    pub fn may_access_session(&self, key: &str) -> bool {
+        if self.channel.is_none() && self.account.is_none() {
+            return true;
+        }
+        let mut parts = key.splitn(4, ':').skip(1);
+        match (parts.next(), parts.next()) {
+            (Some(channel), Some(account)) => self.may_route(channel, account),
+            _ => false,
+        }
+    }
+}
+
+/// Resolve a presented token to a caller.
+///
+/// `legacy` is the single `--token`/`EXOCLAW_TOKEN` token, which keeps full
+/// access. Every configured token is compared so timing does not reveal which
+/// one matched.
+pub fn authenticate(
+    presented: &str,
+    legacy: &Option<String>,
+    tokens: &[ApiTokenConfig],
+) -> Option<Caller> {
+    let legacy_match = legacy
+        .as_ref()
+        .is_some_and(|expected| constant_time_eq(presented.as_bytes(), expected.as_bytes()));
+    let mut caller = legacy_match.then(|| Caller::trusted("default"));
+    for token in tokens {
+        let matched = constant_time_eq(presented.as_bytes(), token.token.as_bytes());
+        if matched && caller.is_none() {
+            caller = Some(Caller::from_token(token));
+        }
+    }
+    caller
+}
+
+/// The `token` field of a WebSocket connect message.
+pub fn connect_token(msg: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(msg)
+        .ok()?
+        .get("token")?
+        .as_str()
+        .map(String::from)
+}
+
+/// The token in an `Authorization: Bearer <token>` header value.
+pub fn bearer_token(header: Option<&str>) -> Option<&str> {
+    header?.strip_prefix("Bearer ").map(str::trim)
+}
+
+/// Whether a browser `Origin` may open a WebSocket.
+///
+/// Requests without an `Origin` header come from non-browser clients and are
+/// allowed. Same-origin requests (the embedded UI) are always allowed; other
+/// origins must be listed in `allowed`, or `allowed` must contain `"*"`.
+pub fn origin_allowed(origin: Option<&str>, host: Option<&str>, allowed: &[String]) -> bool {
+    let Some(origin) = origin else {
+        return true;
+    };
+    if allowed.iter().any(|a| a == "*") {
+        return true;
+    }
+    let Ok(parsed) = url::Url::parse(origin) else {
+        return false;
+    };
+    let same_origin = host
+        .and_then(|host| url::Url::parse(&format!("{}://{host}", parsed.scheme())).ok())
+        .is_some_and(|host| {
+            host.host_str() == parsed.host_str()
+                && host.port_or_known_default() == parsed.port_or_known_default()
+        });
+    same_origin
+        || allowed
+            .iter()
+            .filter_map(|a| url::Url::parse(a).ok())
+            .any(|a| a.origin() == parsed.origin())
+}
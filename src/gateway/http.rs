@@ -0,0 +1,239 @@
+use axum::Json;
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, Query, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::warn;
+
+use super::auth::{self, Caller, Scope};
+use super::jsonrpc::RpcError;
+use super::protocol::{ChatSendParams, ChatStream, event_kind, handle_chat_send};
+use super::server::AppState;
+use crate::agent::AgentEvent;
+use crate::types::StreamEvent;
+
+#[derive(Debug, Deserialize)]
+pub struct ChatQuery {
+    /// Stream events as SSE (default) or return one JSON body when false.
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+}
+
+fn default_stream() -> bool {
+    true
+}
+
+/// `POST /v1/chat` — run a chat turn over plain HTTP.
+///
+/// Takes the same body as `chat.send` and goes through `handle_chat_send`, so
+/// routing, budgets, memory and session locking match the WebSocket path.
+/// Streams events as SSE, or collects them into one JSON body with `?stream=false`.
+pub async fn chat_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ChatQuery>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let caller = match authorize(&headers, &state, Scope::ChatSend, remote) {
+        Ok(caller) => caller,
+        Err(denied) => return *denied,
+    };
+
+    let params: ChatSendParams = match serde_json::from_slice(&body) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                format!("invalid chat.send params: {e}"),
+            );
+        }
+    };
+
+    let request_id = format!("http-{}", uuid::Uuid::new_v4());
+    let ChatStream {
+        id,
+        stream,
+        session_key,
+        agent_id,
+        rx,
+    } = match handle_chat_send(request_id, params, &caller, &state).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            let resp = error_response(rpc_status(&e), e.message.clone());
+            return with_retry_after(resp, &e);
+        }
+    };
+
+    if query.stream {
+        let events = ReceiverStream::new(rx).map(move |event| {
+            let kind = event_kind(&event);
+            let mut frame = StreamEvent::from(&event).to_frame(&id);
+            frame["stream"] = serde_json::json!(stream);
+            Ok::<_, Infallible>(Event::default().event(kind).data(frame.to_string()))
+        });
+        return Sse::new(events)
+            .keep_alive(KeepAlive::default())
+            .into_response();
+    }
+
+    let reply = collect_reply(rx).await;
+    Json(serde_json::json!({
+        "id": id,
+        "stream": stream,
+        "session_key": session_key,
+        "agent_id": agent_id,
+        "content": reply.content,
+        "tool_calls": reply.tool_calls,
+        "usage": {
+            "input_tokens": reply.input_tokens,
+            "output_tokens": reply.output_tokens,
+        },
+        "error": (!reply.errors.is_empty()).then(|| reply.errors.join("; ")),
+        "cancelled": reply.cancelled,
+    }))
+    .into_response()
+}
+
+/// Resolve the request's bearer token to a caller holding `scope`.
+/// Returns a 401 response for a missing or unknown token and 403 for a
+/// token without the scope. Both are audited with the `remote` address.
+pub(crate) fn authorize(
+    headers: &HeaderMap,
+    state: &AppState,
+    scope: Scope,
+    remote: SocketAddr,
+) -> Result<Caller, Box<Response>> {
+    let header = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    let Some(caller) = state.authenticate(auth::bearer_token(header)) else {
+        warn!(remote = %remote, "http request rejected: missing or invalid bearer token");
+        crate::audit::record(
+            "auth_failed",
+            serde_json::json!({
+                "method": "http",
+                "remote": remote.to_string(),
+                "token_presented": header.is_some(),
+            }),
+        );
+        let mut resp = error_response(StatusCode::UNAUTHORIZED, "unauthorized");
+        resp.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            "Bearer".parse().expect("static header"),
+        );
+        return Err(Box::new(resp));
+    };
+    if !caller.has_scope(scope) {
+        warn!(caller = %caller.name, scope = %scope, remote = %remote, "http request denied");
+        crate::audit::record(
+            "scope_denied",
+            serde_json::json!({
+                "method": "http",
+                "remote": remote.to_string(),
+                "caller": caller.name,
+                "scope": scope.as_str(),
+            }),
+        );
+        return Err(Box::new(error_response(
+            StatusCode::FORBIDDEN,
+            RpcError::forbidden(scope).message,
+        )));
+    }
+    Ok(caller.with_remote(remote.ip()))
+}
+
+pub(crate) fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    let message: String = message.into();
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Add `Retry-After` to a response for a rate-limit error.
+pub(crate) fn with_retry_after(mut resp: Response, error: &RpcError) -> Response {
+    if let Some(secs) = error.retry_after_secs() {
+        resp.headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    }
+    resp
+}
+
+/// HTTP status for an RPC error from `handle_chat_send`.
+pub(crate) fn rpc_status(error: &RpcError) -> StatusCode {
+    match error.code {
+        RpcError::INVALID_PARAMS => StatusCode::BAD_REQUEST,
+        RpcError::BUDGET_EXCEEDED | RpcError::RATE_LIMITED => StatusCode::TOO_MANY_REQUESTS,
+        RpcError::PROVIDER_ERROR => StatusCode::BAD_GATEWAY,
+        RpcError::NOT_FOUND => StatusCode::NOT_FOUND,
+        RpcError::CONFLICT => StatusCode::CONFLICT,
+        RpcError::FORBIDDEN => StatusCode::FORBIDDEN,
+        RpcError::OVERLOADED => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Everything a run produced, collected for a non-streaming reply.
+#[derive(Debug, Default)]
+pub(crate) struct CollectedReply {
+    pub content: String,
+    pub tool_calls: Vec<serde_json::Value>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub errors: Vec<String>,
+    pub cancelled: bool,
+}
+
+/// Drain a run's events until it finishes.
+pub(crate) async fn collect_reply(mut rx: mpsc::Receiver<AgentEvent>) -> CollectedReply {
+    let mut reply = CollectedReply::default();
+
+    while let Some(event) = rx.recv().await {
+        match event {
+            AgentEvent::Text(text) => reply.content.push_str(&text),
+            AgentEvent::ToolUse {
+                id: call_id,
+                name,
+                input,
+            } => reply.tool_calls.push(serde_json::json!({
+                "id": call_id,
+                "name": name,
+                "input": input,
+            })),
+            AgentEvent::ToolResult {
+                tool_use_id,
+                content: result,
+                is_error,
+            } => {
+                let call = reply.tool_calls.iter_mut().find(|c| c["id"] == tool_use_id);
+                if let Some(call) = call {
+                    call["result"] = serde_json::json!(result);
+                    call["is_error"] = serde_json::json!(is_error);
+                }
+            }
+            AgentEvent::Usage {
+                input_tokens,
+                output_tokens,
+            } => {
+                reply.input_tokens += u64::from(input_tokens);
+                reply.output_tokens += u64::from(output_tokens);
+            }
+            AgentEvent::Error(e) => reply.errors.push(e),
+            // Answered over the socket or left to time out.
+            AgentEvent::ApprovalRequest { .. } | AgentEvent::ClientToolCall { .. } => {}
+            AgentEvent::Done => break,
+            AgentEvent::Cancelled => {
+                reply.cancelled = true;
+                break;
+            }
+        }
+    }
+
+    reply
+}
@@ -0,0 +1,97 @@
+//! Live session subscriptions.
+//!
+//! `session.subscribe` lets a connection watch a session whatever its traffic
+//! comes from: another socket, the HTTP endpoints or a webhook. Every user
+//! message and agent event on a session is published here and fanned out to
+//! the session's subscribers. Sessions nobody watches cost nothing.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Frames buffered per session for its slowest subscriber. A subscriber that
+/// falls further behind skips ahead and is told how many it missed.
+pub const HUB_CAPACITY: usize = 256;
+
+/// Per-session broadcast channels of serialized frames.
+#[derive(Default)]
+pub struct SessionHub {
+    sessions: Mutex<HashMap<String, broadcast::Sender<Arc<str>>>>,
+}
+
+impl SessionHub {
+    /// Receive every frame published on `session_key` from now on.
+    pub fn subscribe(&self, session_key: &str) -> broadcast::Receiver<Arc<str>> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, tx| tx.receiver_count() > 0);
+        sessions
+            .entry(session_key.to_string())
+            .or_insert_with(|| broadcast::channel(HUB_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Send a frame to the session's subscribers, tagged with its
+    /// `session_key`.
+    pub fn publish(&self, session_key: &str, mut frame: serde_json::Value) {
+        let sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(tx) = sessions.get(session_key) else {
+            return;
+        };
+        if tx.receiver_count() == 0 {
+            return;
+        }
+        frame["session_key"] = serde_json::json!(session_key);
+        let _ = tx.send(Arc::from(frame.to_string()));
+    }
+
+    /// Open subscriptions on a session.
+    pub fn subscribers(&self, session_key: &str) -> usize {
+        let sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions
+            .get(session_key)
+            .map_or(0, broadcast::Sender::receiver_count)
+    }
+}
+
+/// Frame announcing a user message on a session. `id` is the run the message
+/// started, so subscribers can match it with the agent frames that follow.
+pub fn user_message(
+    request_id: &str,
+    channel: &str,
+    account: &str,
+    content: &str,
+) -> serde_json::Value {
+    serde_json::json!({
+        "id": request_id,
+        "event": "user_message",
+        "data": {
+            "channel": channel,
+            "account": account,
+            "content": content,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_reach_only_the_sessions_subscribers() {
+        let hub = SessionHub::default();
+        hub.publish("a", serde_json::json!({ "event": "text" }));
+        let mut a = hub.subscribe("a");
+        let mut b = hub.subscribe("b");
+        assert_eq!(hub.subscribers("a"), 1);
+
+        hub.publish("a", serde_json::json!({ "event": "done" }));
+        let frame: serde_json::Value = serde_json::from_str(&a.try_recv().unwrap()).unwrap();
+        assert_eq!(frame["event"], "done");
+        assert_eq!(frame["session_key"], "a");
+        assert!(a.try_recv().is_err());
+        assert!(b.try_recv().is_err());
+
+        drop(a);
+        assert_eq!(hub.subscribers("a"), 0);
+    }
+}
@@ -0,0 +1,304 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use super::auth::Scope;
+use super::ratelimit::RateLimited;
+
+/// A JSON-RPC 2.0 error object.
+///
+/// Standard codes follow the spec; exoclaw-specific codes live in the
+/// implementation-defined server error range (-32000 to -32099).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const INTERNAL_ERROR: i64 = -32603;
+
+    /// Token budget for the session, day or month is used up.
+    pub const BUDGET_EXCEEDED: i64 = -32001;
+    /// The LLM provider could not be created or called.
+    pub const PROVIDER_ERROR: i64 = -32002;
+    /// The session, run or stream named in the params does not exist.
+    pub const NOT_FOUND: i64 = -32003;
+    /// The request conflicts with in-flight work (e.g. a reused request id).
+    pub const CONFLICT: i64 = -32004;
+    /// The caller's token lacks the scope or route the request needs.
+    pub const FORBIDDEN: i64 = -32005;
+    /// A rate limit was hit; `data.retry_after_secs` says when to retry.
+    pub const RATE_LIMITED: i64 = -32006;
+    /// Too many streams in flight on the gateway or this connection.
+    pub const OVERLOADED: i64 = -32007;
+
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// `invalid <method> params: <reason>`
+    pub fn invalid_params(method: &str, reason: impl std::fmt::Display) -> Self {
+        Self::new(
+            Self::INVALID_PARAMS,
+            format!("invalid {method} params: {reason}"),
+        )
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(Self::NOT_FOUND, message)
+    }
+
+    pub fn rate_limited(limited: &RateLimited) -> Self {
+        Self::new(Self::RATE_LIMITED, limited.to_string()).with_data(serde_json::json!({
+            "dimension": limited.dimension.as_str(),
+            "retry_after_secs": limited.retry_after_secs(),
+        }))
+    }
+
+    /// Too many streams in flight; `data.limit` names the config key hit.
+    pub fn overloaded(limit: &str, max: usize) -> Self {
+        Self::new(
+            Self::OVERLOADED,
+            format!("too many streams in flight ({limit} = {max})"),
+        )
+        .with_data(serde_json::json!({ "limit": limit, "max": max }))
+    }
+
+    /// The gateway is shutting down and takes no new runs.
+    pub fn shutting_down() -> Self {
+        Self::new(Self::OVERLOADED, "server is shutting down")
+            .with_data(serde_json::json!({ "shutdown": true }))
+    }
+
+    /// Short name of the error code, e.g. for metric labels.
+    pub fn kind(&self) -> &'static str {
+        match self.code {
+            Self::PARSE_ERROR => "parse_error",
+            Self::INVALID_REQUEST => "invalid_request",
+            Self::METHOD_NOT_FOUND => "method_not_found",
+            Self::INVALID_PARAMS => "invalid_params",
+            Self::INTERNAL_ERROR => "internal_error",
+            Self::BUDGET_EXCEEDED => "budget_exceeded",
+            Self::PROVIDER_ERROR => "provider_error",
+            Self::NOT_FOUND => "not_found",
+            Self::CONFLICT => "conflict",
+            Self::FORBIDDEN => "forbidden",
+            Self::RATE_LIMITED => "rate_limited",
+            Self::OVERLOADED => "overloaded",
+            _ => "error",
+        }
+    }
+
+    /// Seconds to wait before retrying, for rate-limit errors.
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        self.data.as_ref()?.get("retry_after_secs")?.as_u64()
+    }
+
+    /// `missing scope: <scope>`, with the scope in `data.scope`.
+    pub fn forbidden(scope: Scope) -> Self {
+        Self::new(Self::FORBIDDEN, format!("missing scope: {scope}"))
+            .with_data(serde_json::json!({ "scope": scope.as_str() }))
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Which envelope a request used, and therefore which one its reply gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// `{"jsonrpc":"2.0", ...}` requests: structured errors, ids echoed as sent.
+    JsonRpc2,
+    /// The original exoclaw frames: no `jsonrpc` field,
+    /// string ids, and `error` as a bare message.
+    Legacy,
+}
+
+/// Addressing for the reply to one request.
+#[derive(Debug, Clone)]
+pub struct Reply {
+    dialect: Dialect,
+    /// `None` marks a JSON-RPC 2.0 notification, which gets no reply.
+    id: Option<Value>,
+}
+
+impl Reply {
+    pub fn new(dialect: Dialect, id: Option<Value>) -> Self {
+        Self { dialect, id }
+    }
+
+    /// Reply for a request whose id could not be determined.
+    pub fn unknown() -> Self {
+        Self::unknown_in(Dialect::JsonRpc2)
+    }
+
+    /// [`Reply::unknown`] in a given dialect. Legacy replies use id `"0"`, as
+    /// the original frames did.
+    pub fn unknown_in(dialect: Dialect) -> Self {
+        let id = match dialect {
+            Dialect::JsonRpc2 => Value::Null,
+            Dialect::Legacy => Value::String("0".into()),
+        };
+        Self::new(dialect, Some(id))
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+
+    /// The id as a string, for run registries and stream frames.
+    /// Notifications and null ids get a generated one.
+    pub fn request_id(&self) -> String {
+        match &self.id {
+            Some(Value::String(id)) => id.clone(),
+            Some(Value::Number(id)) => id.to_string(),
+            _ => format!("rpc-{}", uuid::Uuid::new_v4()),
+        }
+    }
+
+    /// Serialize a reply, or `None` for notifications.
+    pub fn finish(&self, result: Result<Value, RpcError>) -> Option<String> {
+        let id = self.id.as_ref()?;
+        let body = match self.dialect {
+            Dialect::JsonRpc2 => match result {
+                Ok(value) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": value }),
+                Err(error) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+            },
+            Dialect::Legacy => {
+                let id = self.request_id();
+                match result {
+                    Ok(value) => serde_json::json!({ "id": id, "result": value }),
+                    Err(error) => serde_json::json!({ "id": id, "error": error.message }),
+                }
+            }
+        };
+        Some(body.to_string())
+    }
+}
+
+/// A request that passed envelope validation.
+#[derive(Debug)]
+pub struct Call {
+    pub reply: Reply,
+    pub method: String,
+    pub params: Value,
+}
+
+/// Validate one request object. Batch members must be JSON-RPC 2.0.
+pub fn parse_call(value: Value, in_batch: bool) -> Result<Call, (Reply, RpcError)> {
+    let Value::Object(mut obj) = value else {
+        return Err((
+            Reply::unknown(),
+            RpcError::new(RpcError::INVALID_REQUEST, "request must be an object"),
+        ));
+    };
+
+    let dialect = match obj.get("jsonrpc") {
+        Some(version) if version == "2.0" => Dialect::JsonRpc2,
+        None if !in_batch => Dialect::Legacy,
+        _ => {
+            return Err((
+                Reply::unknown(),
+                RpcError::new(RpcError::INVALID_REQUEST, "jsonrpc must be \"2.0\""),
+            ));
+        }
+    };
+
+    let id = obj.remove("id");
+    let id_ok = matches!(
+        (&id, dialect),
+        (None | Some(Value::Null), Dialect::JsonRpc2)
+            | (Some(Value::String(_) | Value::Number(_)), _)
+    );
+    if !id_ok {
+        return Err((
+            Reply::unknown(),
+            RpcError::new(RpcError::INVALID_REQUEST, "id must be a string or number"),
+        ));
+    }
+    let reply = Reply::new(dialect, id);
+
+    let method = match obj.remove("method") {
+        Some(Value::String(method)) => method,
+        _ => {
+            return Err((
+                reply,
+                RpcError::new(RpcError::INVALID_REQUEST, "method must be a string"),
+            ));
+        }
+    };
+
+    Ok(Call {
+        reply,
+        method,
+        params: obj.remove("params").unwrap_or(Value::Null),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_reply_stringifies_id_and_flattens_error() {
+        let reply = Reply::new(Dialect::Legacy, Some(serde_json::json!(7)));
+        let out = reply
+            .finish(Err(RpcError::not_found("session not found: x")))
+            .unwrap();
+        let parsed: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!({"id":"7","error":"session not found: x"})
+        );
+    }
+
+    #[test]
+    fn strict_reply_echoes_id_and_structures_error() {
+        let reply = Reply::new(Dialect::JsonRpc2, Some(serde_json::json!(7)));
+        let out = reply
+            .finish(Err(RpcError::new(
+                RpcError::METHOD_NOT_FOUND,
+                "unknown method: x",
+            )))
+            .unwrap();
+        let parsed: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(parsed["jsonrpc"], "2.0");
+        assert_eq!(parsed["id"], 7);
+        assert_eq!(parsed["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn notifications_get_no_reply() {
+        let call = parse_call(serde_json::json!({"jsonrpc":"2.0","method":"ping"}), false).unwrap();
+        assert!(call.reply.is_notification());
+        assert!(call.reply.finish(Ok(Value::Null)).is_none());
+    }
+
+    #[test]
+    fn batch_members_must_declare_version() {
+        let (_, err) = parse_call(serde_json::json!({"id":"1","method":"ping"}), true).unwrap_err();
+        assert_eq!(err.code, RpcError::INVALID_REQUEST);
+    }
+}
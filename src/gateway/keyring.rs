@@ -0,0 +1,258 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tokio::sync::watch;
+use tracing::info;
+
+use super::auth::{self, Caller};
+use crate::config::{ApiTokenConfig, GatewayConfig};
+
+/// Tokens the gateway currently accepts.
+///
+/// During a rotation both `active` and `next` are accepted; once every client
+/// has moved over, `next` is promoted to `active` and the old one dropped.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Keys {
+    pub active: Option<String>,
+    pub next: Option<String>,
+    /// Named scoped tokens, as in `[[gateway.tokens]]`.
+    pub tokens: Vec<ApiTokenConfig>,
+}
+
+impl Keys {
+    /// Whether any token would be accepted.
+    fn accepts_any(&self) -> bool {
+        self.active.is_some() || self.next.is_some() || !self.tokens.is_empty()
+    }
+}
+
+/// The gateway's accepted tokens, reloadable at runtime from a token file.
+///
+/// Connections subscribe to changes and re-check their own token, so a token
+/// that is rotated out stops working without restarting the gateway.
+pub struct Keyring {
+    keys: RwLock<Keys>,
+    /// `[[gateway.tokens]]` from config; always kept across reloads.
+    config_tokens: Vec<ApiTokenConfig>,
+    /// `--token`/`EXOCLAW_TOKEN`; used as `active` when the file has none.
+    cli_token: Option<String>,
+    file: Option<PathBuf>,
+    changes: watch::Sender<u64>,
+}
+
+impl Default for Keyring {
+    fn default() -> Self {
+        Self::new(Keys::default())
+    }
+}
+
+impl Keyring {
+    /// A fixed keyring with no token file.
+    pub fn new(keys: Keys) -> Self {
+        Self {
+            config_tokens: keys.tokens.clone(),
+            cli_token: keys.active.clone(),
+            keys: RwLock::new(keys),
+            file: None,
+            changes: watch::channel(0).0,
+        }
+    }
+
+    /// Build the keyring from the CLI token, `[[gateway.tokens]]`, and
+    /// `gateway.token_file` if set.
+    pub fn from_config(token: Option<String>, gateway: &GatewayConfig) -> anyhow::Result<Self> {
+        let keyring = Self {
+            keys: RwLock::new(Keys::default()),
+            config_tokens: gateway.tokens.clone(),
+            cli_token: token,
+            file: gateway.token_file.as_ref().map(PathBuf::from),
+            changes: watch::channel(0).0,
+        };
+        keyring.reload()?;
+        Ok(keyring)
+    }
+
+    /// Re-read the token file and swap in its tokens. On error the current
+    /// tokens stay in place. A reload never turns auth off: a file that
+    /// leaves no token to accept is rejected while auth is on.
+    pub fn reload(&self) -> anyhow::Result<KeyringSummary> {
+        let mut keys = match &self.file {
+            Some(path) => read_token_file(path)?,
+            None => Keys::default(),
+        };
+        if keys.active.is_none() {
+            keys.active = self.cli_token.clone();
+        }
+        let mut tokens = self.config_tokens.clone();
+        tokens.append(&mut keys.tokens);
+        crate::config::validate_tokens("token file", &tokens)?;
+        keys.tokens = tokens;
+        if !keys.accepts_any() && self.auth_required() {
+            anyhow::bail!(
+                "token file {} leaves no tokens; keeping the current ones",
+                self.file
+                    .as_deref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default()
+            );
+        }
+
+        self.replace(keys);
+        let summary = self.summary();
+        info!(
+            active = summary.active,
+            next = summary.next,
+            tokens = summary.tokens,
+            "auth tokens loaded"
+        );
+        Ok(summary)
+    }
+
+    /// Swap in a new set of keys and notify connections.
+    pub fn replace(&self, keys: Keys) {
+        *self.keys.write().unwrap_or_else(|e| e.into_inner()) = keys;
+        self.changes.send_modify(|generation| *generation += 1);
+    }
+
+    pub fn has_file(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Whether callers must present a token.
+    pub fn auth_required(&self) -> bool {
+        self.keys
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .accepts_any()
+    }
+
+    /// Resolve a presented token to a caller. `active` and `next` both have
+    /// full access; named tokens carry their scopes.
+    pub fn authenticate(&self, presented: &str) -> Option<Caller> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        let caller = auth::authenticate(presented, &keys.active, &keys.tokens);
+        let next = keys
+            .next
+            .as_ref()
+            .is_some_and(|next| auth::constant_time_eq(presented.as_bytes(), next.as_bytes()));
+        caller.or_else(|| next.then(|| Caller::trusted("next")))
+    }
+
+    /// Fires whenever the accepted tokens change.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.changes.subscribe()
+    }
+
+    pub fn summary(&self) -> KeyringSummary {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        KeyringSummary {
+            active: keys.active.is_some(),
+            next: keys.next.is_some(),
+            tokens: keys.tokens.len(),
+        }
+    }
+}
+
+/// What a keyring holds, without the secrets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyringSummary {
+    pub active: bool,
+    pub next: bool,
+    pub tokens: usize,
+}
+
+fn read_token_file(path: &Path) -> anyhow::Result<Keys> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", path.display()))?;
+    let keys: Keys = toml::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("invalid token file {}: {e}", path.display()))?;
+    let blank = |t: &Option<String>| t.as_deref().is_some_and(|t| t.trim().is_empty());
+    if blank(&keys.active) || blank(&keys.next) {
+        anyhow::bail!("token file {}: tokens cannot be empty", path.display());
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(active: Option<&str>, next: Option<&str>) -> Keys {
+        Keys {
+            active: active.map(String::from),
+            next: next.map(String::from),
+            tokens: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn active_and_next_are_both_accepted() {
+        let keyring = Keyring::new(keys(Some("old"), Some("new")));
+        assert_eq!(keyring.authenticate("old").unwrap().name, "default");
+        assert_eq!(keyring.authenticate("new").unwrap().name, "next");
+        assert!(keyring.authenticate("other").is_none());
+    }
+
+    #[test]
+    fn replace_notifies_subscribers() {
+        let keyring = Keyring::new(keys(Some("old"), None));
+        let changes = keyring.subscribe();
+        keyring.replace(keys(Some("new"), None));
+        assert!(changes.has_changed().unwrap());
+        assert!(keyring.authenticate("old").is_none());
+    }
+
+    #[test]
+    fn bad_token_file_keeps_current_tokens() {
+        let dir = std::env::temp_dir().join(format!("exoclaw-keyring-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tokens.toml");
+        std::fs::write(&path, "active = \"one\"\n").unwrap();
+
+        let gateway = GatewayConfig {
+            token_file: Some(path.display().to_string()),
+            ..GatewayConfig::default()
+        };
+        let keyring = Keyring::from_config(Some("cli".into()), &gateway).unwrap();
+        assert!(keyring.authenticate("one").is_some());
+        // The file's active token wins over --token.
+        assert!(keyring.authenticate("cli").is_none());
+
+        std::fs::write(&path, "active = [not toml").unwrap();
+        assert!(keyring.reload().is_err());
+        assert!(keyring.authenticate("one").is_some());
+
+        std::fs::write(&path, "next = \"two\"\n").unwrap();
+        let summary = keyring.reload().unwrap();
+        assert!(summary.active && summary.next);
+        assert!(keyring.authenticate("cli").is_some());
+        assert!(keyring.authenticate("two").is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn empty_token_file_does_not_turn_auth_off() {
+        let dir = std::env::temp_dir().join(format!("exoclaw-keyring-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tokens.toml");
+        std::fs::write(&path, "active = \"one\"\n").unwrap();
+
+        let gateway = GatewayConfig {
+            token_file: Some(path.display().to_string()),
+            ..GatewayConfig::default()
+        };
+        let keyring = Keyring::from_config(None, &gateway).unwrap();
+        let changes = keyring.subscribe();
+
+        std::fs::write(&path, "").unwrap();
+        let err = keyring.reload().expect_err("empty file must be rejected");
+        assert!(err.to_string().contains("leaves no tokens"));
+        assert!(keyring.auth_required());
+        assert!(keyring.authenticate("one").is_some());
+        assert!(!changes.has_changed().unwrap());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
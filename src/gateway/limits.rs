@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Close code for a WebSocket message over `gateway.max_message_bytes`.
+pub const CLOSE_MESSAGE_TOO_BIG: u16 = 1009;
+
+/// Close code sent to connections still open when the gateway shuts down.
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+
+/// Close code sent when `gateway.max_connections` is reached.
+pub const CLOSE_TRY_AGAIN_LATER: u16 = 1013;
+
+/// A fixed number of slots for connections or in-flight streams.
+///
+/// Clones share the same slots. A taken slot is released when its [`Slot`]
+/// is dropped.
+#[derive(Debug, Clone)]
+pub struct Slots {
+    semaphore: Arc<Semaphore>,
+    limit: usize,
+}
+
+impl Slots {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit,
+        }
+    }
+
+    /// Slots that never run out.
+    pub fn unlimited() -> Self {
+        Self::new(Semaphore::MAX_PERMITS)
+    }
+
+    /// Take a slot, or `None` if all are in use.
+    pub fn try_take(&self) -> Option<Slot> {
+        Arc::clone(&self.semaphore)
+            .try_acquire_owned()
+            .ok()
+            .map(|permit| Slot { _permit: permit })
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn in_use(&self) -> usize {
+        self.limit - self.semaphore.available_permits()
+    }
+}
+
+impl Default for Slots {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+/// A taken slot; released on drop.
+#[derive(Debug)]
+pub struct Slot {
+    _permit: OwnedSemaphorePermit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_are_released_on_drop() {
+        let slots = Slots::new(1);
+        let taken = slots.try_take().unwrap();
+        assert!(slots.try_take().is_none());
+        assert_eq!(slots.in_use(), 1);
+        drop(taken);
+        assert!(slots.clone().try_take().is_some());
+    }
+}
@@ -1,5 +1,20 @@
 pub mod auth;
+pub mod http;
+pub mod hub;
+pub mod jsonrpc;
+pub mod keyring;
+pub mod limits;
+pub mod openai;
+pub mod plugins;
 pub mod protocol;
+pub mod ratelimit;
+pub mod reload;
+pub mod replay;
+pub mod runs;
 pub mod server;
+pub mod shutdown;
+pub mod signature;
+pub mod tls;
+pub mod webhook;
 
 pub use server::run;
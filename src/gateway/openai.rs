@@ -0,0 +1,390 @@
+use axum::Json;
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, warn};
+
+use super::auth::Scope;
+use super::http::{authorize, collect_reply, rpc_status, with_retry_after};
+use super::jsonrpc::RpcError;
+use super::protocol::{ChatSendParams, handle_chat_send};
+use super::server::AppState;
+use crate::agent::AgentEvent;
+use crate::agent::providers::build_openai_tools;
+
+/// Channel name used for sessions opened through the OpenAI-compatible API.
+const OPENAI_CHANNEL: &str = "openai";
+
+/// OpenAI chat-completions request. Fields exoclaw does not use are ignored.
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionRequest {
+    /// Exoclaw agent id to run.
+    pub model: String,
+    pub messages: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub stream_options: Option<StreamOptions>,
+    /// End-user id; becomes the session account so each user keeps their own memory.
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// `POST /v1/chat/completions` — OpenAI-compatible facade over exoclaw agents.
+///
+/// `model` selects the agent. Exoclaw sessions are stateful, so a request
+/// carries only the new `user` message; earlier turns come from the session's
+/// memory, keyed by the request's `user` field or else by the token. Tool calls run server-side in the WASM
+/// sandbox and are not surfaced as OpenAI `tool_calls`.
+pub async fn chat_completions_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let caller = match authorize(&headers, &state, Scope::ChatSend, remote) {
+        Ok(caller) => caller,
+        Err(denied) => return openai_denied(*denied),
+    };
+
+    let req: ChatCompletionRequest = match serde_json::from_slice(&body) {
+        Ok(r) => r,
+        Err(e) => {
+            return openai_error(
+                StatusCode::BAD_REQUEST,
+                format!("invalid request body: {e}"),
+                "invalid_request_error",
+                None,
+            );
+        }
+    };
+
+    if state.config.get().find_agent(&req.model).is_none() {
+        return openai_error(
+            StatusCode::NOT_FOUND,
+            format!("The model '{}' does not exist", req.model),
+            "invalid_request_error",
+            Some("model_not_found"),
+        );
+    }
+
+    let content = match user_content(&req.messages) {
+        Ok(content) => content,
+        Err(message) => {
+            return openai_error(
+                StatusCode::BAD_REQUEST,
+                message,
+                "invalid_request_error",
+                None,
+            );
+        }
+    };
+
+    let params = ChatSendParams {
+        channel: OPENAI_CHANNEL.to_string(),
+        account: req
+            .user
+            .clone()
+            .or_else(|| caller.account.clone())
+            // One session per token, so callers without `user` don't share one.
+            .unwrap_or_else(|| caller.name.clone()),
+        peer: "main".to_string(),
+        content,
+        attachments: Vec::new(),
+        guild: None,
+        team: None,
+        agent: Some(req.model.clone()),
+        client_tools: Vec::new(),
+    };
+
+    let completion_id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+    let rx = match handle_chat_send(completion_id.clone(), params, &caller, &state).await {
+        Ok(stream) => stream.rx,
+        Err(e) => {
+            let status = rpc_status(&e);
+            let kind = if status.is_server_error() {
+                "api_error"
+            } else {
+                "invalid_request_error"
+            };
+            let code = (e.code == RpcError::RATE_LIMITED).then_some("rate_limit_exceeded");
+            let resp = openai_error(status, e.message.clone(), kind, code);
+            return with_retry_after(resp, &e);
+        }
+    };
+
+    let created = chrono::Utc::now().timestamp();
+    if req.stream {
+        let include_usage = req.stream_options.unwrap_or_default().include_usage;
+        let (event_tx, event_rx) = mpsc::channel::<Result<Event, Infallible>>(32);
+        tokio::spawn(stream_chunks(
+            ChunkMeta {
+                id: completion_id,
+                model: req.model,
+                created,
+            },
+            include_usage,
+            rx,
+            event_tx,
+        ));
+        return Sse::new(ReceiverStream::new(event_rx))
+            .keep_alive(KeepAlive::default())
+            .into_response();
+    }
+
+    let reply = collect_reply(rx).await;
+    if reply.content.is_empty() && !reply.errors.is_empty() {
+        return openai_error(
+            StatusCode::BAD_GATEWAY,
+            reply.errors.join("; "),
+            "api_error",
+            None,
+        );
+    }
+
+    Json(serde_json::json!({
+        "id": completion_id,
+        "object": "chat.completion",
+        "created": created,
+        "model": req.model,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": reply.content },
+            "finish_reason": finish_reason(reply.cancelled, !reply.errors.is_empty()),
+        }],
+        "usage": usage_json(reply.input_tokens, reply.output_tokens),
+    }))
+    .into_response()
+}
+
+/// `GET /v1/models` — list agents as OpenAI models, with their tools in OpenAI format.
+///
+/// Needs `chat:send` like completions: listing models is only useful for chatting.
+pub async fn models_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(denied) = authorize(&headers, &state, Scope::ChatSend, remote) {
+        return openai_denied(*denied);
+    }
+
+    let mut data = Vec::new();
+    let config = state.config.get();
+    for agent in config.all_agents() {
+        let tools = build_openai_tools(&state.tool_schemas_for(agent).await);
+        data.push(serde_json::json!({
+            "id": agent.id,
+            "object": "model",
+            "created": 0,
+            "owned_by": "exoclaw",
+            "tools": tools,
+        }));
+    }
+
+    Json(serde_json::json!({ "object": "list", "data": data })).into_response()
+}
+
+struct ChunkMeta {
+    id: String,
+    model: String,
+    created: i64,
+}
+
+impl ChunkMeta {
+    fn chunk(&self, delta: serde_json::Value, finish_reason: Option<&str>) -> Event {
+        let chunk = serde_json::json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason,
+            }],
+        });
+        Event::default().data(chunk.to_string())
+    }
+}
+
+/// Translate agent events into `chat.completion.chunk` SSE events, ending with `[DONE]`.
+async fn stream_chunks(
+    meta: ChunkMeta,
+    include_usage: bool,
+    mut rx: mpsc::Receiver<AgentEvent>,
+    tx: mpsc::Sender<Result<Event, Infallible>>,
+) {
+    let mut input_tokens: u64 = 0;
+    let mut output_tokens: u64 = 0;
+    let mut cancelled = false;
+    let mut failed = false;
+
+    let mut events = vec![meta.chunk(serde_json::json!({ "role": "assistant" }), None)];
+    while let Some(event) = rx.recv().await {
+        let finished = match event {
+            AgentEvent::Text(text) => {
+                events.push(meta.chunk(serde_json::json!({ "content": text }), None));
+                false
+            }
+            AgentEvent::Usage {
+                input_tokens: input,
+                output_tokens: output,
+            } => {
+                input_tokens += u64::from(input);
+                output_tokens += u64::from(output);
+                false
+            }
+            AgentEvent::Error(message) => {
+                warn!(completion = %meta.id, "agent error during completion: {message}");
+                let error = serde_json::json!({
+                    "error": { "message": message, "type": "api_error" },
+                });
+                events.push(Event::default().data(error.to_string()));
+                failed = true;
+                false
+            }
+            AgentEvent::ToolUse { .. }
+            | AgentEvent::ToolResult { .. }
+            | AgentEvent::ApprovalRequest { .. }
+            | AgentEvent::ClientToolCall { .. } => false,
+            AgentEvent::Done => true,
+            AgentEvent::Cancelled => {
+                cancelled = true;
+                true
+            }
+        };
+
+        for event in events.drain(..) {
+            if tx.send(Ok(event)).await.is_err() {
+                debug!(completion = %meta.id, "completion client disconnected");
+                return;
+            }
+        }
+        if finished {
+            break;
+        }
+    }
+
+    events.push(meta.chunk(
+        serde_json::json!({}),
+        Some(finish_reason(cancelled, failed)),
+    ));
+    if include_usage {
+        let usage = serde_json::json!({
+            "id": meta.id,
+            "object": "chat.completion.chunk",
+            "created": meta.created,
+            "model": meta.model,
+            "choices": [],
+            "usage": usage_json(input_tokens, output_tokens),
+        });
+        events.push(Event::default().data(usage.to_string()));
+    }
+    events.push(Event::default().data("[DONE]"));
+    for event in events {
+        if tx.send(Ok(event)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// `stop` for a reply that ran to completion. A cancelled or failed run is
+/// reported as `length`, which OpenAI clients treat as a truncated answer.
+fn finish_reason(cancelled: bool, failed: bool) -> &'static str {
+    if cancelled || failed {
+        "length"
+    } else {
+        "stop"
+    }
+}
+
+/// Text of the last `user` message. Accepts string content or an array of parts.
+/// Text of the request's one user message. Sessions keep their own history
+/// and system prompt, so earlier turns and system messages are refused rather
+/// than dropped.
+fn user_content(messages: &[serde_json::Value]) -> Result<String, &'static str> {
+    let [message] = messages else {
+        return Err("messages must hold only the new user message; \
+                    earlier turns come from the session, keyed by `user`");
+    };
+    if message.get("role").and_then(|r| r.as_str()) != Some("user") {
+        return Err("messages must hold one user message");
+    }
+    let text = match message.get("content") {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(serde_json::Value::Array(parts)) => parts
+            .iter()
+            .filter(|p| p.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+    if text.is_empty() {
+        return Err("the user message must have text content");
+    }
+    Ok(text)
+}
+
+fn usage_json(input_tokens: u64, output_tokens: u64) -> serde_json::Value {
+    serde_json::json!({
+        "prompt_tokens": input_tokens,
+        "completion_tokens": output_tokens,
+        "total_tokens": input_tokens + output_tokens,
+    })
+}
+
+/// Restate a rejection from [`authorize`] in the OpenAI error shape, keeping
+/// its status and `WWW-Authenticate` challenge.
+fn openai_denied(denied: Response) -> Response {
+    let (code, kind, message) = match denied.status() {
+        StatusCode::UNAUTHORIZED => (
+            Some("invalid_api_key"),
+            "invalid_request_error",
+            "invalid or missing API key".to_string(),
+        ),
+        _ => (
+            None,
+            "permission_error",
+            RpcError::forbidden(Scope::ChatSend).message,
+        ),
+    };
+    let mut resp = openai_error(denied.status(), message, kind, code);
+    if let Some(challenge) = denied.headers().get(header::WWW_AUTHENTICATE) {
+        resp.headers_mut()
+            .insert(header::WWW_AUTHENTICATE, challenge.clone());
+    }
+    resp
+}
+
+/// Error body in the shape OpenAI SDKs expect.
+fn openai_error(
+    status: StatusCode,
+    message: impl Into<String>,
+    kind: &str,
+    code: Option<&str>,
+) -> Response {
+    let message: String = message.into();
+    (
+        status,
+        Json(serde_json::json!({
+            "error": { "message": message, "type": kind, "param": null, "code": code },
+        })),
+    )
+        .into_response()
+}
@@ -0,0 +1,193 @@
+//! Runtime plugin administration: `plugin.load`, `plugin.unload`,
+//! `plugin.reload` and `plugin.inspect`.
+//!
+//! Changes take the write lock on [`AppState::plugins`]. Tool calls and
+//! webhook adapters hold the read lock while they run, and tokio's lock is
+//! fair, so the change waits for calls already in flight and holds back new
+//! ones until it is done. The agent reads tool schemas under the same lock at
+//! the start of every run, so the next `chat.send` sees the change.
+
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLockWriteGuard;
+
+use super::auth::Caller;
+use super::jsonrpc::RpcError;
+use super::server::AppState;
+use crate::sandbox::capabilities;
+use crate::sandbox::{PluginHost, PreparedPlugin};
+
+/// How long a change waits for in-flight plugin calls to finish. Matches
+/// the per-call sandbox timeout.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Parameters for `plugin.load`.
+#[derive(Debug, Deserialize)]
+pub struct LoadParams {
+    pub name: String,
+    pub path: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Like `require_approval` on a `[[plugins]]` entry.
+    #[serde(default)]
+    pub require_approval: bool,
+}
+
+/// Parameters for `plugin.unload`, `plugin.reload` and `plugin.inspect`.
+#[derive(Debug, Deserialize)]
+pub struct NameParams {
+    pub name: String,
+}
+
+pub async fn load(
+    params: LoadParams,
+    caller: &Caller,
+    state: &Arc<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    if params.name.trim().is_empty() {
+        return Err(RpcError::invalid_params("plugin.load", "name is empty"));
+    }
+    if state.plugins.read().await.has_plugin(&params.name) {
+        return Err(already_loaded(&params.name));
+    }
+    let caps = capabilities::parse_all(&params.capabilities)
+        .map_err(|e| RpcError::invalid_params("plugin.load", e))?;
+    let plugin = prepare(&params.name, &params.path, caps)
+        .await?
+        .require_approval(params.require_approval);
+
+    let mut host = drain(state).await?;
+    // Checked again: another load may have won while this one compiled.
+    if host.has_plugin(&params.name) {
+        return Err(already_loaded(&params.name));
+    }
+    host.install(plugin);
+    let details = host.inspect(&params.name);
+    drop(host);
+
+    crate::audit::record(
+        "plugin_loaded",
+        serde_json::json!({
+            "method": "plugin.load",
+            "caller": caller.name,
+            "plugin": params.name,
+            "path": params.path,
+            "capabilities": params.capabilities,
+            "require_approval": params.require_approval,
+        }),
+    );
+    Ok(serde_json::json!(details))
+}
+
+pub async fn unload(
+    params: NameParams,
+    caller: &Caller,
+    state: &Arc<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    let mut host = drain(state).await?;
+    if !host.unregister(&params.name) {
+        return Err(plugin_not_found(&params.name));
+    }
+    drop(host);
+
+    crate::audit::record(
+        "plugin_unloaded",
+        serde_json::json!({
+            "method": "plugin.unload",
+            "caller": caller.name,
+            "plugin": params.name,
+        }),
+    );
+    Ok(serde_json::json!({ "unloaded": params.name }))
+}
+
+/// Re-read a plugin's `.wasm` from the path it was loaded from. The old
+/// module keeps serving until the new one has compiled; if it fails to
+/// compile, nothing changes.
+pub async fn reload(
+    params: NameParams,
+    caller: &Caller,
+    state: &Arc<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    let (source, require_approval) = {
+        let host = state.plugins.read().await;
+        (
+            host.source(&params.name),
+            host.requires_approval(&params.name),
+        )
+    };
+    let (path, caps) = source.ok_or_else(|| plugin_not_found(&params.name))?;
+    let plugin = prepare(&params.name, &path, caps)
+        .await?
+        .require_approval(require_approval);
+
+    let mut host = drain(state).await?;
+    if !host.has_plugin(&params.name) {
+        // Unloaded while the new module compiled.
+        return Err(plugin_not_found(&params.name));
+    }
+    host.install(plugin);
+    let details = host.inspect(&params.name);
+    drop(host);
+
+    crate::audit::record(
+        "plugin_reloaded",
+        serde_json::json!({
+            "method": "plugin.reload",
+            "caller": caller.name,
+            "plugin": params.name,
+            "path": path,
+        }),
+    );
+    Ok(serde_json::json!(details))
+}
+
+pub async fn inspect(
+    params: NameParams,
+    state: &Arc<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    let details = state.plugins.read().await.inspect(&params.name);
+    details
+        .map(|details| serde_json::json!(details))
+        .ok_or_else(|| plugin_not_found(&params.name))
+}
+
+/// Compile and validate off the async runtime; large modules take a while.
+async fn prepare(
+    name: &str,
+    path: &str,
+    caps: Vec<capabilities::Capability>,
+) -> Result<PreparedPlugin, RpcError> {
+    let (name, path) = (name.to_string(), path.to_string());
+    tokio::task::spawn_blocking(move || PluginHost::prepare(&name, &path, caps))
+        .await
+        .map_err(|e| RpcError::new(RpcError::INTERNAL_ERROR, e.to_string()))?
+        .map_err(|e| RpcError::new(RpcError::INVALID_PARAMS, format!("{e:#}")))
+}
+
+/// Take the write lock once in-flight plugin calls have finished.
+async fn drain(state: &AppState) -> Result<RwLockWriteGuard<'_, PluginHost>, RpcError> {
+    tokio::time::timeout(DRAIN_TIMEOUT, state.plugins.write())
+        .await
+        .map_err(|_| {
+            RpcError::new(
+                RpcError::CONFLICT,
+                format!(
+                    "plugin calls still in flight after {}s; try again",
+                    DRAIN_TIMEOUT.as_secs()
+                ),
+            )
+        })
+}
+
+fn already_loaded(name: &str) -> RpcError {
+    RpcError::new(
+        RpcError::CONFLICT,
+        format!("plugin already loaded: {name}; use plugin.reload"),
+    )
+}
+
+fn plugin_not_found(name: &str) -> RpcError {
+    RpcError::not_found(format!("plugin not found: {name}"))
+}
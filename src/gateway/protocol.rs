@@ -1,36 +1,21 @@
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 
+use super::auth::{Caller, Scope};
+use super::jsonrpc::{Call, Dialect, Reply, RpcError, parse_call};
+use super::ratelimit::Dimension;
 use super::server::AppState;
 use crate::agent::AgentEvent;
+use crate::agent::approval::Decision;
+use crate::agent::client_tools::ClientTools;
 use crate::agent::metering;
-use crate::types::Message as AgentMessage;
-
-#[derive(Deserialize)]
-struct RpcRequest {
-    id: RpcId,
-    method: String,
-    #[serde(default)]
-    params: serde_json::Value,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum RpcId {
-    String(String),
-    Number(serde_json::Number),
-}
-
-impl RpcId {
-    fn into_string(self) -> String {
-        match self {
-            RpcId::String(value) => value,
-            RpcId::Number(value) => value.to_string(),
-        }
-    }
-}
+use crate::router::SessionRouter;
+use crate::types::{Attachment, Message as AgentMessage, StreamEvent};
 
 /// Parameters for the `chat.send` RPC method.
 #[derive(Debug, Deserialize)]
@@ -40,244 +25,1060 @@ pub struct ChatSendParams {
     #[serde(default = "default_peer")]
     pub peer: String,
     pub content: String,
+    /// Images or documents sent with `content`.
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
     pub guild: Option<String>,
     pub team: Option<String>,
+    /// Run on this agent instead of resolving bindings.
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// Tools the client runs itself, offered to the model next to the
+    /// agent's plugins. WebSocket only.
+    #[serde(default)]
+    pub client_tools: Vec<ClientToolSchema>,
+}
+
+/// A client tool declared in `chat.send`, in the plugin schema format.
+#[derive(Debug, Clone, Deserialize, serde::Serialize)]
+pub struct ClientToolSchema {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_input_schema")]
+    pub input_schema: serde_json::Value,
+}
+
+fn default_input_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
 }
 
 fn default_peer() -> String {
     "main".into()
 }
 
-#[derive(Serialize)]
-struct RpcResponse {
-    id: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    result: Option<serde_json::Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+/// Parameters for the `chat.cancel` RPC method.
+#[derive(Debug, Deserialize)]
+pub struct ChatCancelParams {
+    /// Stream id of the run to cancel, from the `chat.send` acknowledgement
+    /// or its frames.
+    pub stream: String,
 }
 
+/// Parameters for the `chat.resume` RPC method.
+#[derive(Debug, Deserialize)]
+pub struct ChatResumeParams {
+    /// Stream id of the run to follow.
+    pub stream: String,
+    /// Last `seq` the client received; frames after it are replayed.
+    #[serde(default)]
+    pub after_seq: u64,
+}
+
+/// Parameters for `tool.approve` and `tool.deny`.
+#[derive(Debug, Deserialize)]
+pub struct ToolDecisionParams {
+    /// Id of the tool call, from the `tool_approval_request` frame.
+    pub id: String,
+    /// `tool.approve`: run the tool with this input instead of the model's.
+    #[serde(default)]
+    pub input: Option<serde_json::Value>,
+    /// `tool.deny`: passed on to the model.
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Parameters for `tool.result`.
+#[derive(Debug, Deserialize)]
+pub struct ToolResultParams {
+    /// Id of the tool call, from the `client_tool_call` frame.
+    pub id: String,
+    /// A string is passed to the model as is; anything else as JSON text.
+    pub content: serde_json::Value,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+/// Parameters for `session.get`, `session.reset` and `session.delete`.
+#[derive(Debug, Deserialize)]
+pub struct SessionParams {
+    pub key: String,
+}
+
+/// Parameters for `session.history` (and `session.get`, which accepts the same paging).
+#[derive(Debug, Deserialize)]
+pub struct SessionHistoryParams {
+    pub key: String,
+    /// Index of the first message to return, counted from the oldest.
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "default_history_limit")]
+    pub limit: usize,
+}
+
+/// Parameters for `session.subscribe` and `session.unsubscribe`.
+#[derive(Debug, Deserialize)]
+pub struct SubscribeParams {
+    pub session_key: String,
+}
+
+fn default_history_limit() -> usize {
+    50
+}
+
+/// Upper bound on messages returned by a single `session.history` page.
+const MAX_HISTORY_LIMIT: usize = 200;
+
 /// Result of handling an RPC request.
-/// Either a single JSON response or a stream of events.
+/// Either a single JSON response, a stream of events, a request to follow an
+/// existing stream from its replay buffer, or the results of a batch.
 pub enum RpcResult {
     Response(String),
     Stream {
+        /// JSON-RPC 2.0 acknowledgement to send before the frames.
+        /// Legacy clients get no acknowledgement.
+        ack: Option<String>,
         id: String,
+        /// Server-assigned stream id the run is registered and buffered under.
+        stream: String,
         session_key: String,
         agent_id: String,
-        user_content: String,
         rx: mpsc::Receiver<AgentEvent>,
     },
+    Resume {
+        /// Acknowledgement to send before the replayed frames.
+        response: Option<String>,
+        stream: String,
+        after_seq: u64,
+    },
+    /// Start or stop forwarding a session's live frames to the connection.
+    Subscription {
+        /// Acknowledgement, sent once the subscription is in place.
+        response: Option<String>,
+        session_key: String,
+        subscribe: bool,
+    },
+    /// One result per batch member, in request order.
+    Batch(Vec<RpcResult>),
+    /// The request was a notification; nothing is sent back.
+    NoResponse,
+}
+
+/// A started `chat.send` run.
+pub struct ChatStream {
+    /// Request id of the `chat.send` call, echoed in every frame.
+    pub id: String,
+    /// Server-assigned id for `chat.cancel` and `chat.resume`.
+    pub stream: String,
+    pub session_key: String,
+    pub agent_id: String,
+    pub rx: mpsc::Receiver<AgentEvent>,
 }
 
-fn event_kind(event: &AgentEvent) -> &'static str {
+/// Every method [`handle_rpc`] answers, for bounding metric labels.
+pub const METHODS: &[&str] = &[
+    "ping",
+    "status",
+    "chat.send",
+    "chat.cancel",
+    "chat.resume",
+    "tool.approve",
+    "tool.deny",
+    "tool.result",
+    "session.list",
+    "session.get",
+    "session.history",
+    "session.reset",
+    "session.delete",
+    "session.subscribe",
+    "session.unsubscribe",
+    "auth.reload",
+    "plugin.list",
+    "plugin.load",
+    "plugin.unload",
+    "plugin.reload",
+    "plugin.inspect",
+    "webhook.dead_letters",
+];
+
+/// Wire name of an event, as used in frame `event` fields.
+pub(crate) fn event_kind(event: &AgentEvent) -> &'static str {
     match event {
         AgentEvent::Text(_) => "text",
         AgentEvent::ToolUse { .. } => "tool_use",
         AgentEvent::ToolResult { .. } => "tool_result",
+        AgentEvent::ApprovalRequest { .. } => "tool_approval_request",
+        AgentEvent::ClientToolCall { .. } => "client_tool_call",
         AgentEvent::Usage { .. } => "usage",
         AgentEvent::Done => "done",
         AgentEvent::Error(_) => "error",
+        AgentEvent::Cancelled => "cancelled",
     }
 }
 
-/// Handle an incoming JSON-RPC-style message.
-pub async fn handle_rpc(msg: &str, state: &Arc<AppState>) -> RpcResult {
-    let req: RpcRequest = match serde_json::from_str(msg) {
-        Ok(r) => r,
+/// Handle an incoming message: a JSON-RPC 2.0 request, a batch, or a legacy
+/// exoclaw frame (no `jsonrpc` field), which gets a legacy-shaped reply.
+pub async fn handle_rpc(msg: &str, caller: &Caller, state: &Arc<AppState>) -> RpcResult {
+    handle_rpc_as(msg, &mut Dialect::JsonRpc2, caller, state).await
+}
+
+/// [`handle_rpc`] for a connection that last spoke `dialect`. Malformed JSON
+/// has no envelope to go by, so it is answered in that dialect; every
+/// well-formed message updates it.
+pub async fn handle_rpc_as(
+    msg: &str,
+    dialect: &mut Dialect,
+    caller: &Caller,
+    state: &Arc<AppState>,
+) -> RpcResult {
+    let value: serde_json::Value = match serde_json::from_str(msg) {
+        Ok(v) => v,
         Err(e) => {
             warn!("malformed rpc: {e}");
-            let resp = serde_json::to_string(&RpcResponse {
-                id: "0".into(),
-                result: None,
-                error: Some(format!("parse error: {e}")),
-            })
-            .unwrap_or_default();
-            return RpcResult::Response(resp);
+            let error = RpcError::new(RpcError::PARSE_ERROR, format!("parse error: {e}"));
+            return respond(&Reply::unknown_in(*dialect), "", Err(error));
         }
     };
+    *dialect = match value.get("jsonrpc") {
+        None if value.is_object() => Dialect::Legacy,
+        _ => Dialect::JsonRpc2,
+    };
 
-    let request_id = req.id.into_string();
-
-    match req.method.as_str() {
-        "ping" => {
-            let resp = RpcResponse {
-                id: request_id,
-                result: Some(serde_json::json!("pong")),
-                error: None,
-            };
-            RpcResult::Response(serde_json::to_string(&resp).unwrap_or_default())
+    match value {
+        serde_json::Value::Array(calls) => {
+            if calls.is_empty() {
+                let error = RpcError::new(RpcError::INVALID_REQUEST, "empty batch");
+                return respond(&Reply::unknown(), "", Err(error));
+            }
+            let mut results = Vec::with_capacity(calls.len());
+            for call in calls {
+                results.push(handle_call(call, true, caller, state).await);
+            }
+            RpcResult::Batch(results)
         }
+        call => handle_call(call, false, caller, state).await,
+    }
+}
+
+async fn handle_call(
+    value: serde_json::Value,
+    in_batch: bool,
+    caller: &Caller,
+    state: &Arc<AppState>,
+) -> RpcResult {
+    let Call {
+        reply,
+        method,
+        params,
+    } = match parse_call(value, in_batch) {
+        Ok(call) => call,
+        Err((reply, error)) => return respond(&reply, "", Err(error)),
+    };
+
+    let missing = Scope::for_method(&method).filter(|scope| !caller.has_scope(*scope));
+    if let Some(scope) = missing {
+        warn!(caller = %caller.name, method = %method, scope = %scope, "rpc denied");
+        crate::audit::record(
+            "scope_denied",
+            serde_json::json!({
+                "method": method,
+                "request_id": reply.request_id(),
+                "remote": caller.remote.map(|ip| ip.to_string()),
+                "caller": caller.name,
+                "scope": scope.as_str(),
+            }),
+        );
+        return respond(&reply, &method, Err(RpcError::forbidden(scope)));
+    }
+
+    let request_id = reply.request_id();
+
+    match method.as_str() {
+        "ping" => respond(&reply, &method, Ok(serde_json::json!("pong"))),
 
         "status" => {
-            let router = state.router.read().await;
-            let resp = RpcResponse {
-                id: request_id,
-                result: Some(serde_json::json!({
-                    "version": env!("CARGO_PKG_VERSION"),
-                    "plugins": state.plugins.read().await.count(),
-                    "sessions": router.session_count(),
-                })),
-                error: None,
-            };
-            RpcResult::Response(serde_json::to_string(&resp).unwrap_or_default())
+            let sessions = state.router.read().await.session_count();
+            let plugins = state.plugins.read().await.count();
+            let status = serde_json::json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "plugins": plugins,
+                "sessions": sessions,
+            });
+            respond(&reply, &method, Ok(status))
         }
 
         "chat.send" => {
-            let params: ChatSendParams = match serde_json::from_value(req.params) {
+            let params: ChatSendParams = match parse_params(&method, params) {
                 Ok(p) => p,
-                Err(e) => {
-                    let resp = RpcResponse {
-                        id: request_id,
-                        result: None,
-                        error: Some(format!("invalid chat.send params: {e}")),
+                Err(e) => return respond(&reply, &method, Err(e)),
+            };
+
+            match handle_chat_send(request_id, params, caller, state).await {
+                Err(e) => respond(&reply, &method, Err(e)),
+                // Fire-and-forget: the run still completes and is saved.
+                Ok(_) if reply.is_notification() => {
+                    record_rpc(&method, "ok");
+                    RpcResult::NoResponse
+                }
+                Ok(stream) => {
+                    record_rpc(&method, "ok");
+                    let ack = match reply.dialect() {
+                        Dialect::JsonRpc2 => reply.finish(Ok(serde_json::json!({
+                            "stream": stream.stream,
+                            "session_key": stream.session_key,
+                            "agent_id": stream.agent_id,
+                        }))),
+                        Dialect::Legacy => None,
                     };
-                    return RpcResult::Response(serde_json::to_string(&resp).unwrap_or_default());
+                    RpcResult::Stream {
+                        ack,
+                        id: stream.id,
+                        stream: stream.stream,
+                        session_key: stream.session_key,
+                        agent_id: stream.agent_id,
+                        rx: stream.rx,
+                    }
                 }
+            }
+        }
+
+        "chat.cancel" => {
+            let params: ChatCancelParams = match parse_params(&method, params) {
+                Ok(p) => p,
+                Err(e) => return respond(&reply, &method, Err(e)),
             };
 
-            handle_chat_send(request_id, params, state).await
+            // Runs outside the caller's pinned route look the same as missing ones.
+            let visible = state
+                .runs
+                .session_key(&params.stream)
+                .is_some_and(|key| caller.may_access_session(&key));
+            let cancelled = if visible {
+                state.runs.cancel(&params.stream)
+            } else {
+                None
+            };
+            let result = match cancelled {
+                Some(session_key) => {
+                    info!(
+                        request_id = %request_id,
+                        run = %params.stream,
+                        session = %session_key,
+                        "chat.cancel accepted"
+                    );
+                    Ok(serde_json::json!({ "cancelled": params.stream }))
+                }
+                None => Err(RpcError::not_found(format!(
+                    "no in-flight run with id: {}",
+                    params.stream
+                ))),
+            };
+            respond(&reply, &method, result)
+        }
+
+        "chat.resume" => {
+            let params: ChatResumeParams = match parse_params(&method, params) {
+                Ok(p) => p,
+                Err(e) => return respond(&reply, &method, Err(e)),
+            };
+
+            // Streams outside the caller's pinned route look the same as missing ones.
+            let visible = state
+                .replay
+                .session_key(&params.stream)
+                .is_some_and(|key| caller.may_access_session(&key));
+            if !visible {
+                let error =
+                    RpcError::not_found(format!("no resumable stream with id: {}", params.stream));
+                return respond(&reply, &method, Err(error));
+            }
+
+            // Fail early if the stream is gone or has been trimmed past after_seq.
+            if let Err(e) = state.replay.frames_after(&params.stream, params.after_seq) {
+                return respond(&reply, &method, Err(RpcError::not_found(e)));
+            }
+
+            info!(
+                request_id = %request_id,
+                run = %params.stream,
+                after_seq = params.after_seq,
+                "chat.resume accepted"
+            );
+            record_rpc(&method, "ok");
+            RpcResult::Resume {
+                response: reply.finish(Ok(serde_json::json!({
+                    "resumed": params.stream,
+                    "after_seq": params.after_seq,
+                }))),
+                stream: params.stream,
+                after_seq: params.after_seq,
+            }
+        }
+
+        "tool.approve" | "tool.deny" => {
+            let params: ToolDecisionParams = match parse_params(&method, params) {
+                Ok(p) => p,
+                Err(e) => return respond(&reply, &method, Err(e)),
+            };
+            respond(
+                &reply,
+                &method,
+                decide_tool_call(&method, params, caller, state),
+            )
+        }
+
+        "tool.result" => {
+            let params: ToolResultParams = match parse_params(&method, params) {
+                Ok(p) => p,
+                Err(e) => return respond(&reply, &method, Err(e)),
+            };
+            respond(&reply, &method, return_tool_result(params, caller))
+        }
+
+        "session.list" => {
+            let store = state.store.read().await;
+            let sessions: Vec<serde_json::Value> = store
+                .list()
+                .into_iter()
+                .filter(|session| caller.may_access_session(&session.key))
+                .map(|session| {
+                    serde_json::json!({
+                        "key": session.key,
+                        "agent_id": session.agent_id,
+                        "created_at": session.created_at,
+                        "message_count": session.message_count,
+                    })
+                })
+                .collect();
+            respond(
+                &reply,
+                &method,
+                Ok(serde_json::json!({ "sessions": sessions })),
+            )
+        }
+
+        "session.get" | "session.history" => {
+            let result = match parse_params::<SessionHistoryParams>(&method, params) {
+                Ok(params) if !caller.may_access_session(&params.key) => {
+                    Err(session_not_found(&params.key))
+                }
+                Ok(params) => session_history(params, state).await,
+                Err(e) => Err(e),
+            };
+            respond(&reply, &method, result)
+        }
+
+        "session.reset" | "session.delete" => {
+            let result = match parse_params::<SessionParams>(&method, params) {
+                Ok(params) if !caller.may_access_session(&params.key) => {
+                    Err(session_not_found(&params.key))
+                }
+                Ok(params) => clear_session(&params.key, method == "session.delete", state).await,
+                Err(e) => Err(e),
+            };
+            respond(&reply, &method, result)
+        }
+
+        "session.subscribe" | "session.unsubscribe" => {
+            let params: SubscribeParams = match parse_params(&method, params) {
+                Ok(p) => p,
+                Err(e) => return respond(&reply, &method, Err(e)),
+            };
+            // Sessions need not exist yet: a client may watch for the first
+            // message on a route.
+            let subscribe = method == "session.subscribe";
+            if subscribe && !caller.may_access_session(&params.session_key) {
+                warn!(
+                    caller = %caller.name,
+                    session = %params.session_key,
+                    "session.subscribe outside pinned route"
+                );
+                let error = RpcError::new(
+                    RpcError::FORBIDDEN,
+                    format!(
+                        "token '{}' may not read session {}",
+                        caller.name, params.session_key
+                    ),
+                );
+                return respond(&reply, &method, Err(error));
+            }
+
+            record_rpc(&method, "ok");
+            let action = if subscribe {
+                "subscribed"
+            } else {
+                "unsubscribed"
+            };
+            RpcResult::Subscription {
+                response: reply.finish(Ok(serde_json::json!({ action: params.session_key }))),
+                session_key: params.session_key,
+                subscribe,
+            }
+        }
+
+        "auth.reload" => {
+            let result = if state.keyring.has_file() {
+                state
+                    .keyring
+                    .reload()
+                    .map(|summary| {
+                        info!(caller = %caller.name, "auth tokens reloaded via rpc");
+                        serde_json::json!({
+                            "active": summary.active,
+                            "next": summary.next,
+                            "tokens": summary.tokens,
+                        })
+                    })
+                    .map_err(|e| {
+                        RpcError::new(
+                            RpcError::INTERNAL_ERROR,
+                            format!("token reload failed: {e}"),
+                        )
+                    })
+            } else {
+                Err(RpcError::new(
+                    RpcError::INTERNAL_ERROR,
+                    "no token file configured",
+                ))
+            };
+            respond(&reply, &method, result)
         }
 
         "plugin.list" => {
             let plugins = state.plugins.read().await;
-            let resp = RpcResponse {
-                id: request_id,
-                result: Some(serde_json::json!(plugins.list())),
-                error: None,
+            respond(&reply, &method, Ok(serde_json::json!(plugins.list())))
+        }
+
+        "plugin.load" => {
+            let result = match parse_params(&method, params) {
+                Ok(params) => super::plugins::load(params, caller, state).await,
+                Err(e) => Err(e),
             };
-            RpcResult::Response(serde_json::to_string(&resp).unwrap_or_default())
+            respond(&reply, &method, result)
         }
 
-        _ => {
-            let resp = RpcResponse {
-                id: request_id,
-                result: None,
-                error: Some(format!("unknown method: {}", req.method)),
+        "plugin.unload" | "plugin.reload" | "plugin.inspect" => {
+            let result = match parse_params(&method, params) {
+                Ok(params) => match method.as_str() {
+                    "plugin.unload" => super::plugins::unload(params, caller, state).await,
+                    "plugin.reload" => super::plugins::reload(params, caller, state).await,
+                    _ => super::plugins::inspect(params, state).await,
+                },
+                Err(e) => Err(e),
             };
-            RpcResult::Response(serde_json::to_string(&resp).unwrap_or_default())
+            respond(&reply, &method, result)
+        }
+
+        "webhook.dead_letters" => respond(
+            &reply,
+            &method,
+            Ok(serde_json::json!({ "dead_letters": state.webhooks.dead_letters() })),
+        ),
+
+        _ => respond(
+            &reply,
+            &method,
+            Err(RpcError::new(
+                RpcError::METHOD_NOT_FOUND,
+                format!("unknown method: {method}"),
+            )),
+        ),
+    }
+}
+
+/// Count a call for `/metrics`. Methods outside [`METHODS`] share one label
+/// so arbitrary client input cannot grow the series without bound.
+fn record_rpc(method: &str, outcome: &str) {
+    let method = if METHODS.contains(&method) {
+        method
+    } else {
+        "unknown"
+    };
+    crate::metrics::inc(
+        crate::metrics::RPC_REQUESTS,
+        &[("method", method), ("outcome", outcome)],
+    );
+}
+
+fn parse_params<T: DeserializeOwned>(
+    method: &str,
+    params: serde_json::Value,
+) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::invalid_params(method, e))
+}
+
+fn respond(reply: &Reply, method: &str, result: Result<serde_json::Value, RpcError>) -> RpcResult {
+    let outcome = match &result {
+        Ok(_) => "ok",
+        Err(e) => e.kind(),
+    };
+    record_rpc(method, outcome);
+    match reply.finish(result) {
+        Some(resp) => RpcResult::Response(resp),
+        None => RpcResult::NoResponse,
+    }
+}
+
+/// Answer a run's `tool_approval_request`.
+fn decide_tool_call(
+    method: &str,
+    params: ToolDecisionParams,
+    caller: &Caller,
+    state: &AppState,
+) -> Result<serde_json::Value, RpcError> {
+    // Requests outside the caller's pinned route look the same as missing ones.
+    let session_key = state
+        .approvals
+        .session_key(&params.id)
+        .filter(|key| caller.may_access_session(key))
+        .ok_or_else(|| {
+            RpcError::not_found(format!("no pending approval for tool call: {}", params.id))
+        })?;
+    let approve = method == "tool.approve";
+    let edited = params.input.is_some();
+    let decision = if approve {
+        Decision::Approve {
+            input: params.input,
+        }
+    } else {
+        Decision::Deny {
+            reason: params.reason.clone(),
         }
+    };
+    if !state.approvals.resolve(&params.id, decision) {
+        return Err(RpcError::not_found(format!(
+            "no pending approval for tool call: {}",
+            params.id
+        )));
+    }
+
+    let (action, event) = if approve {
+        ("approved", "tool_approved")
+    } else {
+        ("denied", "tool_denied")
+    };
+    info!(caller = %caller.name, tool_use_id = %params.id, session = %session_key, "tool call {action}");
+    crate::audit::record(
+        event,
+        serde_json::json!({
+            "method": method,
+            "caller": caller.name,
+            "tool_use_id": params.id,
+            "session_key": session_key,
+            "edited_input": edited,
+            "reason": params.reason,
+        }),
+    );
+    Ok(serde_json::json!({ action: params.id }))
+}
+
+/// Answer a `client_tool_call` sent to the caller's connection.
+fn return_tool_result(
+    params: ToolResultParams,
+    caller: &Caller,
+) -> Result<serde_json::Value, RpcError> {
+    let content = match params.content {
+        serde_json::Value::String(text) => text,
+        other => other.to_string(),
+    };
+    let result = crate::sandbox::ToolCallResult {
+        content,
+        is_error: params.is_error,
+    };
+    let resolved = caller
+        .client_calls
+        .as_ref()
+        .is_some_and(|calls| calls.resolve(&params.id, result));
+    if !resolved {
+        return Err(RpcError::not_found(format!(
+            "no pending client tool call: {}",
+            params.id
+        )));
     }
+    info!(caller = %caller.name, tool_use_id = %params.id, is_error = params.is_error, "client tool result");
+    Ok(serde_json::json!({ "returned": params.id }))
+}
+
+/// Return one page of a session's stored history, oldest first.
+async fn session_history(
+    params: SessionHistoryParams,
+    state: &Arc<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    let store = state.store.read().await;
+    let session = store
+        .get(&params.key)
+        .ok_or_else(|| session_not_found(&params.key))?;
+
+    let limit = params.limit.min(MAX_HISTORY_LIMIT);
+    let total = session.messages.len();
+    let messages: Vec<&serde_json::Value> = session
+        .messages
+        .iter()
+        .skip(params.offset)
+        .take(limit)
+        .collect();
+    let next_offset = params.offset + messages.len();
+
+    Ok(serde_json::json!({
+        "key": session.key,
+        "agent_id": session.agent_id,
+        "created_at": session.created_at,
+        "total": total,
+        "offset": params.offset,
+        "limit": limit,
+        "messages": messages,
+        "next_offset": (next_offset < total).then_some(next_offset),
+    }))
+}
+
+/// Reset or delete a session.
+///
+/// Clears the stored history, the episodic memory window and the router's
+/// session state so the next message starts fresh. Reset keeps the (now empty)
+/// session in the store. Fails with CONFLICT while a run holds the session:
+/// this is called from a connection's read loop, and waiting there would
+/// block the `tool.approve` or `tool.result` that run may be waiting for.
+async fn clear_session(
+    key: &str,
+    delete: bool,
+    state: &Arc<AppState>,
+) -> Result<serde_json::Value, RpcError> {
+    let lock = state.session_lock(key).await;
+    let Ok(_guard) = lock.try_lock() else {
+        return Err(RpcError::new(
+            RpcError::CONFLICT,
+            format!("session has a run in flight: {key}; cancel it or retry when it ends"),
+        ));
+    };
+
+    {
+        let mut store = state.store.write().await;
+        let found = if delete {
+            store.remove(key).is_some()
+        } else {
+            store.reset(key)
+        };
+        if !found {
+            return Err(session_not_found(key));
+        }
+    }
+    state.memory.write().await.clear_session(key);
+    state.router.write().await.remove_session(key);
+
+    info!(session = %key, delete, "session cleared");
+    let action = if delete { "deleted" } else { "reset" };
+    Ok(serde_json::json!({ action: key }))
 }
 
 /// Handle chat.send: resolve route, get/create session, run agent, return stream.
-async fn handle_chat_send(
+///
+/// Shared by the WebSocket RPC and the HTTP chat endpoints.
+pub async fn handle_chat_send(
     request_id: String,
     params: ChatSendParams,
+    caller: &Caller,
     state: &Arc<AppState>,
-) -> RpcResult {
-    // 1. Route to agent
-    let route = {
-        let mut router = state.router.write().await;
-        router.resolve(
+) -> Result<ChatStream, RpcError> {
+    if !caller.may_route(&params.channel, &params.account) {
+        warn!(
+            caller = %caller.name,
+            channel = %params.channel,
+            account = %params.account,
+            "chat.send outside pinned route"
+        );
+        return Err(RpcError::new(
+            RpcError::FORBIDDEN,
+            format!(
+                "token '{}' may not send on {}:{}",
+                caller.name, params.channel, params.account
+            ),
+        ));
+    }
+
+    if state.shutdown.is_draining() {
+        return Err(RpcError::shutting_down());
+    }
+
+    let config = state.config.get();
+    let max_chars = config.gateway.max_content_chars;
+    let chars = params.content.chars().count();
+    if chars > max_chars {
+        return Err(RpcError::invalid_params(
+            "chat.send",
+            format!("content is {chars} characters; the limit is {max_chars}"),
+        ));
+    }
+    Attachment::validate_all(
+        &params.attachments,
+        config.gateway.max_attachments,
+        config.gateway.max_attachment_bytes,
+    )
+    .map_err(|reason| RpcError::invalid_params("chat.send", reason))?;
+
+    if !params.client_tools.is_empty() {
+        check_client_tools(&params.client_tools, caller, state).await?;
+    }
+
+    let unknown_agent = params
+        .agent
+        .as_deref()
+        .filter(|id| config.find_agent(id).is_none());
+    if let Some(agent_id) = unknown_agent {
+        return Err(RpcError::new(
+            RpcError::INVALID_PARAMS,
+            format!("unknown agent: {agent_id}"),
+        ));
+    }
+
+    // 1. Route to agent. Nothing is recorded for the session until the call
+    //    has passed every admission check below.
+    let route = match params.agent.as_deref() {
+        Some(agent_id) => SessionRouter::route_agent(
+            agent_id,
+            &params.channel,
+            &params.account,
+            Some(&params.peer),
+        ),
+        None => state.router.read().await.route(
             &params.channel,
             &params.account,
             Some(&params.peer),
             params.guild.as_deref(),
             params.team.as_deref(),
-        )
+        ),
     };
-    info!(
-        request_id = %request_id,
-        session = %route.session_key,
-        agent = %route.agent_id,
-        provider = %state.config.agent.provider,
-        message_chars = params.content.chars().count(),
-        "chat.send accepted"
-    );
-
-    // 2. Get/create session and append user message
-    {
-        let mut store = state.store.write().await;
-        let session = store.get_or_create(&route.session_key, &route.agent_id);
-        session.messages.push(serde_json::json!({
-            "role": "user",
-            "content": params.content.clone(),
-        }));
-        session.message_count += 1;
+    let remote = caller.remote.map(|ip| ip.to_string());
+    let mut limits = vec![
+        (Dimension::Session, route.session_key.as_str()),
+        (Dimension::Channel, params.channel.as_str()),
+        (Dimension::Token, caller.name.as_str()),
+    ];
+    if let Some(ip) = &remote {
+        limits.push((Dimension::Ip, ip.as_str()));
     }
+    if let Err(limited) = state.rate_limit("chat.send", &limits) {
+        return Err(RpcError::rate_limited(&limited));
+    }
+
+    let agent = config.agent_def(&route.agent_id);
 
-    // 3. Build message history from memory context + current user message.
-    let user_message = AgentMessage::text("user", params.content.clone());
+    // 2. Build message history from memory context + current user message.
+    let transcript_entry = user_entry(&params.content, &params.attachments);
+    let user_message =
+        AgentMessage::with_attachments("user", params.content.clone(), params.attachments);
     let messages = {
         let mut memory = state.memory.write().await;
         let mut context =
             memory.assemble_context(&route.session_key, &route.agent_id, &params.content);
-        context.push(user_message);
+        context.push(user_message.clone());
         context
             .into_iter()
             .filter_map(|m| m.as_provider_message())
             .collect::<Vec<_>>()
     };
 
-    // 4. Budget check before LLM call (T033)
+    // 3. Budget check before LLM call (T033)
+    let estimated_input = metering::estimate_input_tokens(&messages);
     {
-        let counter_mutex = metering::get_or_init_global(&state.config.budgets);
-        let estimated = metering::estimate_input_tokens(&messages);
+        let counter_mutex = metering::get_or_init_global(&config.budgets);
+        let estimated = estimated_input;
         let mut counter = counter_mutex.lock().unwrap_or_else(|e| e.into_inner());
         if let Err(exceeded) = counter.check_budget(&route.session_key, estimated) {
-            let resp = RpcResponse {
-                id: request_id,
-                result: None,
-                error: Some(exceeded.to_string()),
+            let scope = match exceeded.scope {
+                metering::BudgetScope::Session(_) => "session",
+                metering::BudgetScope::Daily => "daily",
+                metering::BudgetScope::Monthly => "monthly",
             };
-            return RpcResult::Response(serde_json::to_string(&resp).unwrap_or_default());
+            crate::metrics::inc(crate::metrics::BUDGET_REJECTIONS, &[("scope", scope)]);
+            crate::audit::record(
+                "budget_rejected",
+                serde_json::json!({
+                    "method": "chat.send",
+                    "request_id": request_id,
+                    "session_key": route.session_key,
+                    "remote": caller.remote.map(|ip| ip.to_string()),
+                    "scope": scope,
+                    "used": exceeded.used,
+                    "limit": exceeded.limit,
+                }),
+            );
+            return Err(RpcError::new(
+                RpcError::BUDGET_EXCEEDED,
+                exceeded.to_string(),
+            ));
         }
     }
 
-    // 5. Create provider from config
-    let provider = match crate::agent::providers::from_config(&state.config.agent) {
+    // 4. Create provider from the routed agent's definition
+    let provider = match crate::agent::providers::from_config(agent) {
         Ok(p) => p,
         Err(e) => {
-            let resp = RpcResponse {
-                id: request_id,
-                result: None,
-                error: Some(format!("provider error: {e}")),
-            };
-            return RpcResult::Response(serde_json::to_string(&resp).unwrap_or_default());
+            return Err(RpcError::new(
+                RpcError::PROVIDER_ERROR,
+                format!("provider error: {e}"),
+            ));
         }
     };
 
-    // 6. Build tool schemas from loaded plugins
-    let tool_schemas = {
-        let plugin_host = state.plugins.read().await;
-        let raw_schemas = plugin_host.tool_schemas();
-        crate::agent::providers::build_tools_for_provider(
-            &state.config.agent.provider,
-            &raw_schemas,
-        )
+    // 5. Hold a stream slot on the gateway and the caller's connection until
+    //    the run ends.
+    let slots = (state.streams.try_take(), caller.streams.try_take());
+    let (Some(gateway_slot), Some(connection_slot)) = slots else {
+        let (limit, max) = match slots.0 {
+            None => ("max_streams", state.streams.limit()),
+            Some(_) => ("max_streams_per_connection", caller.streams.limit()),
+        };
+        warn!(request_id = %request_id, limit, max, "chat.send rejected: too many streams");
+        return Err(RpcError::overloaded(limit, max));
     };
 
-    // 7. Spawn agent task and return stream
+    // 6. Build tool schemas from the plugins this agent may use and the
+    //    caller's own tools
+    let mut tool_schemas = state.tool_schemas_for(agent).await;
+    let client_schemas: Vec<serde_json::Value> = params
+        .client_tools
+        .iter()
+        .filter_map(|tool| serde_json::to_value(tool).ok())
+        .collect();
+    tool_schemas.extend(crate::agent::providers::build_tools_for_provider(
+        &agent.provider,
+        &client_schemas,
+    ));
+
+    // 7. Register the run under a fresh stream id so chat.cancel and
+    //    chat.resume can find it
+    let stream_id = uuid::Uuid::new_v4().to_string();
+    let Some(mut cancel_rx) = state.runs.register(&stream_id, &route.session_key) else {
+        return Err(RpcError::new(
+            RpcError::INTERNAL_ERROR,
+            format!("stream id already in use: {stream_id}"),
+        ));
+    };
+
+    state
+        .replay
+        .start(&stream_id, &request_id, &route.session_key);
+
+    // 8. The run is admitted: open the session, append the user message and
+    //    tell subscribers about it
+    info!(
+        request_id = %request_id,
+        stream = %stream_id,
+        session = %route.session_key,
+        agent = %route.agent_id,
+        provider = %agent.provider,
+        message_chars = params.content.chars().count(),
+        "chat.send accepted"
+    );
+    state.router.write().await.open(&route);
+    {
+        let mut store = state.store.write().await;
+        let session = store.get_or_create(&route.session_key, &route.agent_id);
+        session.messages.push(transcript_entry);
+        session.message_count += 1;
+    }
+    state.hub.publish(
+        &route.session_key,
+        super::hub::user_message(
+            &request_id,
+            &params.channel,
+            &params.account,
+            &params.content,
+        ),
+    );
+
+    // 9. Spawn agent task and return stream
     let (tx, rx) = mpsc::channel::<AgentEvent>(32);
     let (meter_tx, mut meter_rx) = mpsc::channel::<AgentEvent>(32);
     let session_key = route.session_key.clone();
     let state_clone = Arc::clone(state);
-    let system_prompt = state.config.agent.system_prompt.clone();
-    let agent_provider = state.config.agent.provider.clone();
-    let agent_model = state.config.agent.model.clone();
+    let system_prompt = agent.system_prompt.clone();
+    let agent_provider = agent.provider.clone();
+    let agent_model = agent.model.clone();
     let agent_id = route.agent_id.clone();
     let meter_session_key = route.session_key.clone();
     let plugins = Arc::clone(&state.plugins);
-    let budget_config = state.config.budgets.clone();
+    let budget_config = config.budgets.clone();
     let session_lock = state.session_lock(&route.session_key).await;
     let relay_request_id = request_id.clone();
+    let relay_stream_id = stream_id.clone();
+    let runner_stream_id = stream_id.clone();
     let runner_request_id = request_id.clone();
+    let relay_state = Arc::clone(state);
+    let relay_guard = state.shutdown.track();
+    // Set once the run holds the session and starts calling the provider.
+    let dispatched = Arc::new(AtomicBool::new(false));
+    let relay_dispatched = Arc::clone(&dispatched);
+    let mut runner = state.runner_for(&config, agent, &route.session_key).await;
+    if let Some(calls) = caller
+        .client_calls
+        .as_ref()
+        .filter(|_| !params.client_tools.is_empty())
+    {
+        runner = runner.with_client_tools(ClientTools::new(
+            params
+                .client_tools
+                .iter()
+                .map(|tool| tool.name.clone())
+                .collect(),
+            Arc::clone(calls),
+            Duration::from_secs(config.gateway.client_tool_timeout_secs),
+        ));
+    }
 
-    // Metering relay: intercepts events to record usage, then forwards to client.
+    // Relay: meters usage, buffers frames for chat.resume, persists the reply,
+    // publishes frames to session subscribers, and forwards events to the
+    // caller. It keeps draining the run even if the
+    // caller goes away so the transcript does not depend on the socket.
     tokio::spawn(async move {
+        let _relay_guard = relay_guard;
+        // Providers only report usage once a call completes. Track output
+        // streamed since the last report so a cancelled call is still metered.
+        // A run cancelled before its first call reached the provider costs
+        // nothing.
+        let mut awaiting_usage = false;
+        let mut first_call = true;
+        let mut unmetered_chars: usize = 0;
+        let mut assistant_text = String::new();
+        let mut caller_gone = false;
         while let Some(event) = meter_rx.recv().await {
             debug!(
                 request_id = %relay_request_id,
                 event = event_kind(&event),
                 "relaying agent event"
             );
-            // Record usage when we see a Usage event (T031/T033)
-            if let AgentEvent::Usage {
-                input_tokens,
-                output_tokens,
-            } = &event
-            {
+            let usage = match &event {
+                // Record usage when we see a Usage event (T031/T033)
+                AgentEvent::Usage {
+                    input_tokens,
+                    output_tokens,
+                } => {
+                    awaiting_usage = false;
+                    first_call = false;
+                    unmetered_chars = 0;
+                    Some((*input_tokens, *output_tokens))
+                }
+                AgentEvent::Text(text) => {
+                    unmetered_chars += text.len();
+                    None
+                }
+                // A tool result means the next provider call is about to start.
+                AgentEvent::ToolResult { .. } => {
+                    awaiting_usage = true;
+                    None
+                }
+                AgentEvent::Cancelled
+                    if awaiting_usage
+                        || (first_call && relay_dispatched.load(Ordering::Acquire)) =>
+                {
+                    Some((
+                        estimated_input.min(u32::MAX as u64) as u32,
+                        (unmetered_chars / 4) as u32,
+                    ))
+                }
+                _ => None,
+            };
+            if let Some((input_tokens, output_tokens)) = usage {
                 let counter_mutex = metering::get_or_init_global(&budget_config);
                 let mut counter = counter_mutex.lock().unwrap_or_else(|e| e.into_inner());
                 counter.record_usage(
@@ -285,60 +1086,104 @@ async fn handle_chat_send(
                     &agent_id,
                     &agent_provider,
                     &agent_model,
-                    *input_tokens,
-                    *output_tokens,
+                    input_tokens,
+                    output_tokens,
                 );
             }
-            if tx.send(event).await.is_err() {
+            if let AgentEvent::Text(text) = &event {
+                assistant_text.push_str(text);
+            }
+            let mut frame = StreamEvent::from(&event).to_frame(&relay_request_id);
+            frame["stream"] = serde_json::json!(relay_stream_id);
+            if let Some(seq) = relay_state.replay.push(&relay_stream_id, frame.clone()) {
+                frame["seq"] = serde_json::json!(seq);
+            }
+            relay_state.hub.publish(&meter_session_key, frame);
+
+            if matches!(event, AgentEvent::Done | AgentEvent::Cancelled) {
+                persist_reply(
+                    &relay_state,
+                    &meter_session_key,
+                    &user_message,
+                    &assistant_text,
+                )
+                .await;
+                relay_state.replay.finish(&relay_stream_id);
+            }
+
+            if !caller_gone && tx.send(event).await.is_err() {
                 debug!(request_id = %relay_request_id, "client receiver dropped");
-                break;
+                caller_gone = true;
             }
         }
+        relay_state.replay.finish(&relay_stream_id);
         debug!(request_id = %relay_request_id, "agent relay channel closed");
     });
 
     tokio::spawn(async move {
-        // Serialize all processing for this session across connections.
-        debug!(
-            request_id = %runner_request_id,
-            session = %session_key,
-            "waiting for session lock"
-        );
-        let _session_guard = session_lock.lock().await;
-        debug!(
-            request_id = %runner_request_id,
-            session = %session_key,
-            "acquired session lock"
-        );
-
-        let runner = crate::agent::AgentRunner::new();
-        let result = runner
-            .run_with_tools(
-                provider.as_ref(),
-                messages,
-                &tool_schemas,
-                system_prompt.as_deref(),
-                &plugins,
-                meter_tx.clone(),
-            )
-            .await;
-
-        if let Err(e) = result {
-            warn!(
+        let run = async {
+            // Serialize all processing for this session across connections.
+            debug!(
                 request_id = %runner_request_id,
                 session = %session_key,
-                "provider run failed: {e}"
+                "waiting for session lock"
             );
-            let _ = meter_tx
-                .send(AgentEvent::Error(format!("provider error: {e}")))
-                .await;
-            let _ = meter_tx.send(AgentEvent::Done).await;
-        } else {
+            let _session_guard = session_lock.lock().await;
             debug!(
                 request_id = %runner_request_id,
                 session = %session_key,
-                "provider run completed"
+                "acquired session lock"
             );
+            dispatched.store(true, Ordering::Release);
+
+            runner
+                .run_with_tools(
+                    provider.as_ref(),
+                    messages,
+                    &tool_schemas,
+                    system_prompt.as_deref(),
+                    &plugins,
+                    meter_tx.clone(),
+                )
+                .await
+        };
+
+        // Dropping the run future on cancel aborts the provider stream, skips
+        // pending tool calls, and releases the session lock.
+        let outcome = tokio::select! {
+            result = run => Some(result),
+            Ok(()) = &mut cancel_rx => None,
+        };
+        state_clone.runs.finish(&runner_stream_id);
+        drop((gateway_slot, connection_slot));
+
+        match outcome {
+            Some(Ok(())) => {
+                debug!(
+                    request_id = %runner_request_id,
+                    session = %session_key,
+                    "provider run completed"
+                );
+            }
+            Some(Err(e)) => {
+                warn!(
+                    request_id = %runner_request_id,
+                    session = %session_key,
+                    "provider run failed: {e}"
+                );
+                let _ = meter_tx
+                    .send(AgentEvent::Error(format!("provider error: {e}")))
+                    .await;
+                let _ = meter_tx.send(AgentEvent::Done).await;
+            }
+            None => {
+                info!(
+                    request_id = %runner_request_id,
+                    session = %session_key,
+                    "agent run cancelled"
+                );
+                let _ = meter_tx.send(AgentEvent::Cancelled).await;
+            }
         }
 
         // Collect assistant response text and append to session
@@ -350,11 +1195,93 @@ async fn handle_chat_send(
         }
     });
 
-    RpcResult::Stream {
+    Ok(ChatStream {
         id: request_id,
+        stream: stream_id,
         session_key: route.session_key,
         agent_id: route.agent_id,
-        user_content: params.content,
         rx,
+    })
+}
+
+fn session_not_found(key: &str) -> RpcError {
+    RpcError::not_found(format!("session not found: {key}"))
+}
+
+/// Client tools need a connection to answer them, and must not shadow a
+/// plugin or each other.
+async fn check_client_tools(
+    tools: &[ClientToolSchema],
+    caller: &Caller,
+    state: &AppState,
+) -> Result<(), RpcError> {
+    if caller.client_calls.is_none() {
+        return Err(RpcError::invalid_params(
+            "chat.send",
+            "client_tools need a WebSocket connection",
+        ));
+    }
+    let plugin_host = state.plugins.read().await;
+    for (i, tool) in tools.iter().enumerate() {
+        let problem = if tool.name.is_empty() {
+            Some("has an empty name")
+        } else if plugin_host.has_plugin(&tool.name) {
+            Some("has the name of a loaded plugin")
+        } else if tools[..i].iter().any(|other| other.name == tool.name) {
+            Some("is declared twice")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            return Err(RpcError::invalid_params(
+                "chat.send",
+                format!("client tool '{}' {problem}", tool.name),
+            ));
+        }
     }
+    Ok(())
+}
+
+/// A user message as kept in the session transcript. Inline attachments are
+/// stored as placeholders, so the transcript does not hold their bytes.
+pub(crate) fn user_entry(content: &str, attachments: &[Attachment]) -> serde_json::Value {
+    let mut entry = serde_json::json!({
+        "role": "user",
+        "content": content,
+    });
+    if !attachments.is_empty() {
+        entry["attachments"] = serde_json::json!(
+            attachments
+                .iter()
+                .map(Attachment::placeholder)
+                .collect::<Vec<_>>()
+        );
+    }
+    entry
+}
+
+/// Append a finished reply to the session transcript and memory.
+async fn persist_reply(
+    state: &Arc<AppState>,
+    session_key: &str,
+    user_message: &AgentMessage,
+    assistant_text: &str,
+) {
+    if assistant_text.is_empty() {
+        return;
+    }
+
+    {
+        let mut store = state.store.write().await;
+        if let Some(session) = store.get_mut(session_key) {
+            session.messages.push(serde_json::json!({
+                "role": "assistant",
+                "content": assistant_text,
+            }));
+        }
+    }
+
+    let mut memory = state.memory.write().await;
+    let assistant_message = AgentMessage::text("assistant", assistant_text);
+    memory.process_response(session_key, user_message, &assistant_message);
 }
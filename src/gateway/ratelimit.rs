@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::{RateLimit, RateLimitConfig};
+
+/// Past this many buckets, idle full buckets are swept on the next check.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// What a limit is keyed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dimension {
+    Ip,
+    Session,
+    Channel,
+    Token,
+}
+
+impl Dimension {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Dimension::Ip => "ip",
+            Dimension::Session => "session",
+            Dimension::Channel => "channel",
+            Dimension::Token => "token",
+        }
+    }
+}
+
+/// A request that hit a limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimited {
+    pub dimension: Dimension,
+    pub key: String,
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    /// Whole seconds to wait, rounded up so clients never retry too early.
+    pub fn retry_after_secs(&self) -> u64 {
+        self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0)
+    }
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "rate limit exceeded for {} (retry after {}s)",
+            self.dimension.as_str(),
+            self.retry_after_secs()
+        )
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token-bucket limiter with independent buckets per dimension and key.
+///
+/// A request passes only if every bucket it touches has a token; tokens are
+/// taken from all of them or none, so one exhausted limit does not drain the
+/// others.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<(Dimension, String), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn limit(&self, dimension: Dimension) -> Option<&RateLimit> {
+        match dimension {
+            Dimension::Ip => self.config.ip.as_ref(),
+            Dimension::Session => self.config.session.as_ref(),
+            Dimension::Channel => self.config.channel.as_ref(),
+            Dimension::Token => self.config.token.as_ref(),
+        }
+    }
+
+    /// Take one token from each configured bucket in `keys`, or report the
+    /// limit with the longest wait.
+    pub fn check(&self, keys: &[(Dimension, &str)]) -> Result<(), RateLimited> {
+        self.check_at(keys, Instant::now())
+    }
+
+    fn check_at(&self, keys: &[(Dimension, &str)], now: Instant) -> Result<(), RateLimited> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() > SWEEP_THRESHOLD {
+            buckets.retain(|(dimension, _), bucket| {
+                self.limit(*dimension)
+                    .is_some_and(|limit| refilled(bucket, limit, now) < limit.capacity())
+            });
+        }
+
+        let mut worst: Option<RateLimited> = None;
+        for (dimension, key) in keys {
+            let Some(limit) = self.limit(*dimension) else {
+                continue;
+            };
+            let bucket = buckets
+                .entry((*dimension, key.to_string()))
+                .or_insert_with(|| Bucket {
+                    tokens: limit.capacity(),
+                    updated: now,
+                });
+            bucket.tokens = refilled(bucket, limit, now);
+            bucket.updated = now;
+            if bucket.tokens < 1.0 {
+                let wait = Duration::from_secs_f64((1.0 - bucket.tokens) / limit.per_second());
+                if worst.as_ref().is_none_or(|w| wait > w.retry_after) {
+                    worst = Some(RateLimited {
+                        dimension: *dimension,
+                        key: key.to_string(),
+                        retry_after: wait,
+                    });
+                }
+            }
+        }
+        if let Some(limited) = worst {
+            return Err(limited);
+        }
+
+        for (dimension, key) in keys {
+            if let Some(bucket) = buckets.get_mut(&(*dimension, key.to_string())) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn refilled(bucket: &Bucket, limit: &RateLimit, now: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    (bucket.tokens + elapsed * limit.per_second()).min(limit.capacity())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_minute: u32, burst: u32) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            session: Some(RateLimit {
+                per_minute,
+                burst: Some(burst),
+            }),
+            ..RateLimitConfig::default()
+        })
+    }
+
+    #[test]
+    fn burst_then_refill() {
+        let limiter = limiter(60, 2);
+        let now = Instant::now();
+        let keys = [(Dimension::Session, "s1")];
+        assert!(limiter.check_at(&keys, now).is_ok());
+        assert!(limiter.check_at(&keys, now).is_ok());
+
+        let limited = limiter.check_at(&keys, now).unwrap_err();
+        assert_eq!(limited.dimension, Dimension::Session);
+        assert_eq!(limited.retry_after_secs(), 1);
+
+        // One token per second at 60/minute.
+        assert!(
+            limiter
+                .check_at(&keys, now + Duration::from_secs(1))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn keys_and_unconfigured_dimensions_are_independent() {
+        let limiter = limiter(60, 1);
+        let now = Instant::now();
+        assert!(limiter.check_at(&[(Dimension::Session, "a")], now).is_ok());
+        assert!(limiter.check_at(&[(Dimension::Session, "b")], now).is_ok());
+        // No ip limit configured, so only the session bucket counts.
+        let keys = [(Dimension::Ip, "1.2.3.4"), (Dimension::Session, "a")];
+        assert!(limiter.check_at(&keys, now).is_err());
+    }
+
+    #[test]
+    fn rejection_takes_no_tokens() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            ip: Some(RateLimit {
+                per_minute: 60,
+                burst: Some(5),
+            }),
+            session: Some(RateLimit {
+                per_minute: 60,
+                burst: Some(1),
+            }),
+            ..RateLimitConfig::default()
+        });
+        let now = Instant::now();
+        let keys = [(Dimension::Ip, "ip"), (Dimension::Session, "s")];
+        assert!(limiter.check_at(&keys, now).is_ok());
+        for _ in 0..10 {
+            assert!(limiter.check_at(&keys, now).is_err());
+        }
+        // The ip bucket still has its remaining four tokens.
+        for _ in 0..4 {
+            assert!(limiter.check_at(&[(Dimension::Ip, "ip")], now).is_ok());
+        }
+        assert!(limiter.check_at(&[(Dimension::Ip, "ip")], now).is_err());
+    }
+}
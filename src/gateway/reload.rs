@@ -0,0 +1,350 @@
+//! Config hot-reload.
+//!
+//! The gateway re-reads the config file when it changes on disk or the
+//! process gets SIGHUP. A new config is validated and fully prepared (plugins
+//! compiled, soul files read) before anything changes; it is then applied
+//! with the plugin host, router and memory locked together, so requests see
+//! either the old config or the new one. If any step fails, the gateway keeps
+//! running on the old config and logs why.
+//!
+//! `[gateway]` and `[memory]` are bound to listeners and engines at startup
+//! and are kept from the running config; changes there need a restart.
+
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use super::server::AppState;
+use crate::agent::metering;
+use crate::config::ExoclawConfig;
+use crate::memory::soul::Soul;
+use crate::router::Binding;
+use crate::sandbox::capabilities;
+use crate::sandbox::{PluginHost, PreparedPlugin};
+
+/// How often the config file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a reload waits for in-flight plugin calls to finish.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sections that are only read at startup.
+const RESTART_SECTIONS: [&str; 2] = ["gateway", "memory"];
+
+/// The running config, swapped whole on reload.
+///
+/// Readers take a snapshot with [`LiveConfig::get`] and keep using it for
+/// the rest of the request, so one request never mixes two configs.
+pub struct LiveConfig(RwLock<Arc<ExoclawConfig>>);
+
+impl LiveConfig {
+    pub fn get(&self) -> Arc<ExoclawConfig> {
+        Arc::clone(&self.0.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn replace(&self, config: ExoclawConfig) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+    }
+}
+
+impl From<ExoclawConfig> for LiveConfig {
+    fn from(config: ExoclawConfig) -> Self {
+        Self(RwLock::new(Arc::new(config)))
+    }
+}
+
+/// What a reload changed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReloadSummary {
+    pub plugins_loaded: Vec<String>,
+    pub plugins_unloaded: Vec<String>,
+    pub bindings: usize,
+    pub souls: usize,
+}
+
+/// Router bindings for `config`.
+pub(crate) fn router_bindings(config: &ExoclawConfig) -> Vec<Binding> {
+    config
+        .bindings
+        .iter()
+        .map(|binding| Binding {
+            agent_id: binding.agent_id.clone(),
+            channel: binding.channel.clone(),
+            account_id: binding.account_id.clone(),
+            peer_id: binding.peer_id.clone(),
+            guild_id: binding.guild_id.clone(),
+            team_id: binding.team_id.clone(),
+        })
+        .collect()
+}
+
+/// Apply a validated config to the running gateway.
+///
+/// Plugins are compared with the running config's `[[plugins]]`: removed
+/// entries are unloaded, new or changed ones (path or capabilities) are
+/// loaded, and unchanged ones keep running. Plugins loaded with `plugin.load`
+/// are left alone unless the new config uses the same name.
+pub async fn apply(state: &AppState, mut config: ExoclawConfig) -> anyhow::Result<ReloadSummary> {
+    let current = state.config.get();
+    config.gateway = current.gateway.clone();
+    config.memory = current.memory.clone();
+
+    // Prepare everything that can fail before taking any lock.
+    let mut summary = ReloadSummary::default();
+    let mut prepared = Vec::new();
+    {
+        let host = state.plugins.read().await;
+        for plugin in &config.plugins {
+            let caps = capabilities::parse_all(&plugin.capabilities)
+                .map_err(|e| anyhow::anyhow!("plugin '{}': {e}", plugin.name))?;
+            let unchanged = current.plugins.iter().any(|old| {
+                old.name == plugin.name
+                    && old.path == plugin.path
+                    && old.capabilities == plugin.capabilities
+            });
+            if !unchanged || !host.has_plugin(&plugin.name) {
+                prepared.push((plugin.name.clone(), plugin.path.clone(), caps));
+            }
+        }
+    }
+    let prepared = prepare_plugins(prepared).await?;
+    let removed: Vec<String> = current
+        .plugins
+        .iter()
+        .filter(|old| !config.plugins.iter().any(|p| p.name == old.name))
+        .map(|old| old.name.clone())
+        .collect();
+
+    let mut souls = Vec::new();
+    for agent in config.all_agents() {
+        if let Some(path) = agent.soul_path.as_deref() {
+            souls.push(
+                Soul::read(&agent.id, path)
+                    .map_err(|e| anyhow::anyhow!("agent '{}': {e}", agent.id))?,
+            );
+        }
+    }
+    let bindings = router_bindings(&config);
+
+    // Lock in the same order as the rest of the gateway: plugins, router,
+    // memory. Waiting for the plugin host drains in-flight tool calls.
+    let mut host = tokio::time::timeout(DRAIN_TIMEOUT, state.plugins.write())
+        .await
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "plugin calls still in flight after {}s",
+                DRAIN_TIMEOUT.as_secs()
+            )
+        })?;
+    let mut router = state.router.write().await;
+    let mut memory = state.memory.write().await;
+
+    for name in removed {
+        if host.unregister(&name) {
+            summary.plugins_unloaded.push(name);
+        }
+    }
+    for plugin in prepared {
+        summary.plugins_loaded.push(plugin.name().to_string());
+        host.install(plugin);
+    }
+    summary.bindings = bindings.len();
+    router.set_bindings(&config.agent.id, bindings);
+    summary.souls = souls.len();
+    memory.soul.replace_all(souls);
+    metering::get_or_init_global(&config.budgets)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .set_limits(&config.budgets);
+    state.config.replace(config);
+
+    Ok(summary)
+}
+
+/// Compile plugins off the async runtime.
+async fn prepare_plugins(
+    plugins: Vec<(String, String, Vec<capabilities::Capability>)>,
+) -> anyhow::Result<Vec<PreparedPlugin>> {
+    tokio::task::spawn_blocking(move || {
+        plugins
+            .into_iter()
+            .map(|(name, path, caps)| {
+                PluginHost::prepare(&name, &path, caps)
+                    .map_err(|e| anyhow::anyhow!("plugin '{name}': {e:#}"))
+            })
+            .collect()
+    })
+    .await?
+}
+
+/// Reload the config file when it changes, and the config and token file on
+/// SIGHUP.
+pub async fn watch(state: Arc<AppState>, path: PathBuf) {
+    let mut watcher = Watcher::new(path);
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut hangups = Hangups::new();
+
+    loop {
+        let hangup = tokio::select! {
+            _ = interval.tick() => false,
+            () = hangups.recv() => true,
+        };
+        if hangup {
+            info!("SIGHUP received, reloading config");
+            reload_tokens(&state);
+        } else if !watcher.changed() {
+            continue;
+        } else {
+            info!(path = %watcher.path.display(), "config file changed, reloading");
+        }
+        watcher.reload(&state).await;
+    }
+}
+
+fn reload_tokens(state: &AppState) {
+    if !state.keyring.has_file() {
+        debug!("no token file is configured");
+        return;
+    }
+    if let Err(e) = state.keyring.reload() {
+        warn!("token reload failed, keeping current tokens: {e}");
+    }
+}
+
+/// Tracks the config file between reloads.
+struct Watcher {
+    path: PathBuf,
+    /// File contents at the last check. Compared whole because edits within
+    /// the same second can leave the modification time unchanged.
+    contents: Option<Vec<u8>>,
+    /// The file as last read, to tell which restart-only sections changed.
+    /// The running config may differ from it through CLI flags.
+    last_read: Option<ExoclawConfig>,
+}
+
+impl Watcher {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            contents: std::fs::read(&path).ok(),
+            last_read: crate::config::load_from(&path).ok(),
+            path,
+        }
+    }
+
+    /// Whether the file was modified since it was last checked.
+    fn changed(&mut self) -> bool {
+        let contents = std::fs::read(&self.path).ok();
+        if contents == self.contents {
+            return false;
+        }
+        self.contents = contents;
+        self.contents.is_some()
+    }
+
+    async fn reload(&mut self, state: &AppState) {
+        if !self.path.exists() {
+            debug!(path = %self.path.display(), "no config file to reload");
+            return;
+        }
+        let config = match crate::config::load_from(&self.path) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("config reload failed, keeping the running config: {e:#}");
+                return;
+            }
+        };
+        let previous = self.last_read.replace(config.clone());
+        for section in restart_sections_changed(previous.as_ref(), &config, &state.config.get()) {
+            warn!("[{section}] changed; restart the gateway to apply it");
+        }
+        match apply(state, config).await {
+            Ok(summary) => info!(
+                plugins_loaded = ?summary.plugins_loaded,
+                plugins_unloaded = ?summary.plugins_unloaded,
+                bindings = summary.bindings,
+                souls = summary.souls,
+                "config reloaded"
+            ),
+            Err(e) => warn!("config reload failed, keeping the running config: {e:#}"),
+        }
+    }
+}
+
+/// Restart-only sections that differ from the file as last read, or from
+/// the running config when the file was not readable at startup.
+fn restart_sections_changed(
+    previous: Option<&ExoclawConfig>,
+    new: &ExoclawConfig,
+    running: &ExoclawConfig,
+) -> Vec<&'static str> {
+    let section = |config: &ExoclawConfig, name: &str| {
+        let value = serde_json::to_value(config).unwrap_or_default();
+        value.get(name).cloned().unwrap_or_default()
+    };
+    let baseline = previous.unwrap_or(running);
+    RESTART_SECTIONS
+        .into_iter()
+        .filter(|name| section(baseline, name) != section(new, name))
+        .collect()
+}
+
+/// SIGHUP as a stream; never fires where signals are unavailable.
+struct Hangups {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl Hangups {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            let signal = signal(SignalKind::hangup())
+                .inspect_err(|e| warn!("cannot listen for SIGHUP: {e}"))
+                .ok();
+            Self { signal }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = self.signal.as_mut()
+            && signal.recv().await.is_some()
+        {
+            return;
+        }
+        std::future::pending::<()>().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restart_sections_compare_against_the_file() {
+        let mut file = ExoclawConfig::default();
+        let mut running = file.clone();
+        // A CLI flag moved the port; the file did not change it.
+        running.gateway.port = 9000;
+        let mut new = file.clone();
+        new.budgets.daily = Some(10);
+        assert!(restart_sections_changed(Some(&file), &new, &running).is_empty());
+
+        new.memory.episodic_window += 1;
+        assert_eq!(
+            restart_sections_changed(Some(&file), &new, &running),
+            vec!["memory"]
+        );
+
+        file.gateway.port = 9000;
+        assert_eq!(
+            restart_sections_changed(None, &file, &running),
+            Vec::<&str>::new()
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Frames kept per stream for `chat.resume`. Older frames are dropped first.
+pub const REPLAY_CAPACITY: usize = 2048;
+
+/// How long a finished stream stays resumable.
+pub const REPLAY_RETENTION: Duration = Duration::from_secs(300);
+
+/// Per-stream replay buffers for stream frames, keyed by the server-assigned
+/// stream id so client request ids never collide across connections.
+///
+/// Every frame a run emits is stamped with a sequence number (starting at 1)
+/// and kept in a bounded buffer, independent of any socket. Followers read
+/// frames after the last `seq` they saw and wait on a watch channel for more,
+/// so a client that reconnects can pick a stream up where it left off.
+pub struct ReplayBuffers {
+    streams: Mutex<HashMap<String, ReplayStream>>,
+    capacity: usize,
+    retention: Duration,
+}
+
+struct ReplayStream {
+    /// Id of the `chat.send` call that started the stream, as echoed in its frames.
+    request_id: String,
+    /// Session the run belongs to, for access checks on `chat.resume`.
+    session_key: String,
+    frames: VecDeque<serde_json::Value>,
+    last_seq: u64,
+    finished_at: Option<Instant>,
+    changed: watch::Sender<u64>,
+}
+
+/// Frames returned by [`ReplayBuffers::frames_after`].
+#[derive(Debug)]
+pub struct ReplaySlice {
+    pub frames: Vec<serde_json::Value>,
+    /// The stream has ended; `frames` holds everything that remains.
+    pub finished: bool,
+}
+
+impl Default for ReplayBuffers {
+    fn default() -> Self {
+        Self::new(REPLAY_CAPACITY, REPLAY_RETENTION)
+    }
+}
+
+impl ReplayBuffers {
+    pub fn new(capacity: usize, retention: Duration) -> Self {
+        Self {
+            streams: Mutex::new(HashMap::new()),
+            capacity: capacity.max(1),
+            retention,
+        }
+    }
+
+    /// Open a buffer for a new run.
+    pub fn start(&self, stream_id: &str, request_id: &str, session_key: &str) {
+        let mut streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        let retention = self.retention;
+        streams.retain(|_, s| s.finished_at.is_none_or(|at| at.elapsed() < retention));
+        let (changed, _) = watch::channel(0);
+        streams.insert(
+            stream_id.to_string(),
+            ReplayStream {
+                request_id: request_id.to_string(),
+                session_key: session_key.to_string(),
+                frames: VecDeque::new(),
+                last_seq: 0,
+                finished_at: None,
+                changed,
+            },
+        );
+    }
+
+    /// Stamp a frame with the next sequence number and buffer it.
+    /// Returns the assigned `seq`, or `None` if the stream is unknown.
+    pub fn push(&self, stream_id: &str, mut frame: serde_json::Value) -> Option<u64> {
+        let mut streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        let stream = streams.get_mut(stream_id)?;
+        stream.last_seq += 1;
+        let seq = stream.last_seq;
+        frame["seq"] = serde_json::json!(seq);
+        if stream.frames.len() == self.capacity {
+            stream.frames.pop_front();
+        }
+        stream.frames.push_back(frame);
+        stream.changed.send_replace(seq);
+        Some(seq)
+    }
+
+    /// Mark a stream as ended. It stays resumable for the retention period.
+    pub fn finish(&self, stream_id: &str) {
+        let mut streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(stream) = streams.get_mut(stream_id) {
+            stream.finished_at.get_or_insert_with(Instant::now);
+            stream.changed.send_replace(stream.last_seq);
+        }
+    }
+
+    /// Buffered frames with `seq > after_seq`.
+    ///
+    /// Fails if the stream is unknown or if frames the caller has not seen
+    /// were already evicted from the buffer.
+    pub fn frames_after(&self, stream_id: &str, after_seq: u64) -> Result<ReplaySlice, String> {
+        let streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        let stream = streams
+            .get(stream_id)
+            .ok_or_else(|| format!("no resumable stream with id: {stream_id}"))?;
+
+        let first_seq = stream.last_seq + 1 - stream.frames.len() as u64;
+        if after_seq + 1 < first_seq {
+            return Err(format!(
+                "frames after seq {after_seq} are no longer buffered (oldest is {first_seq})"
+            ));
+        }
+
+        let skip = after_seq.saturating_sub(first_seq - 1) as usize;
+        Ok(ReplaySlice {
+            frames: stream.frames.iter().skip(skip).cloned().collect(),
+            finished: stream.finished_at.is_some(),
+        })
+    }
+
+    /// Subscribe to new-frame notifications for a stream.
+    pub fn watch(&self, stream_id: &str) -> Option<watch::Receiver<u64>> {
+        let streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        streams.get(stream_id).map(|s| s.changed.subscribe())
+    }
+
+    /// Request id the stream's frames carry.
+    pub fn request_id(&self, stream_id: &str) -> Option<String> {
+        let streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        streams.get(stream_id).map(|s| s.request_id.clone())
+    }
+
+    /// Session key of the run that fills the stream.
+    pub fn session_key(&self, stream_id: &str) -> Option<String> {
+        let streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        streams.get(stream_id).map(|s| s.session_key.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReplayBuffers;
+    use std::time::Duration;
+
+    #[test]
+    fn frames_are_stamped_and_replayed_after_seq() {
+        let replay = ReplayBuffers::default();
+        replay.start("r1", "req-1", "agent:ws:me:main");
+        for text in ["a", "b", "c"] {
+            replay.push("r1", serde_json::json!({"id": "r1", "data": text}));
+        }
+
+        let slice = replay.frames_after("r1", 1).unwrap();
+        assert_eq!(slice.frames.len(), 2);
+        assert_eq!(slice.frames[0]["seq"], 2);
+        assert_eq!(slice.frames[0]["data"], "b");
+        assert!(!slice.finished);
+
+        replay.finish("r1");
+        let slice = replay.frames_after("r1", 3).unwrap();
+        assert!(slice.frames.is_empty());
+        assert!(slice.finished);
+    }
+
+    #[test]
+    fn evicted_frames_are_reported() {
+        let replay = ReplayBuffers::new(2, Duration::from_secs(60));
+        replay.start("r1", "req-1", "agent:ws:me:main");
+        for _ in 0..4 {
+            replay.push("r1", serde_json::json!({}));
+        }
+
+        assert!(replay.frames_after("r1", 1).is_err());
+        let slice = replay.frames_after("r1", 2).unwrap();
+        assert_eq!(slice.frames.len(), 2);
+        assert_eq!(slice.frames[0]["seq"], 3);
+        assert!(replay.frames_after("missing", 0).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+/// Registry of in-flight agent runs, keyed by the server-assigned stream id.
+///
+/// Each run registers a cancel handle when it is spawned and removes itself
+/// when it finishes. `chat.cancel` looks the run up here and fires the handle.
+#[derive(Default)]
+pub struct RunRegistry {
+    runs: Mutex<HashMap<String, RunHandle>>,
+}
+
+struct RunHandle {
+    session_key: String,
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+impl RunRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new run. Returns the receiver the run should watch for
+    /// cancellation, or `None` if a run with this id is already in flight.
+    pub fn register(&self, stream_id: &str, session_key: &str) -> Option<oneshot::Receiver<()>> {
+        let mut runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        if runs.contains_key(stream_id) {
+            return None;
+        }
+        let (tx, rx) = oneshot::channel();
+        runs.insert(
+            stream_id.to_string(),
+            RunHandle {
+                session_key: session_key.to_string(),
+                cancel: Some(tx),
+            },
+        );
+        Some(rx)
+    }
+
+    /// Signal cancellation for a run. Returns the run's session key if the
+    /// run was in flight and had not already been cancelled.
+    pub fn cancel(&self, stream_id: &str) -> Option<String> {
+        let mut runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        let handle = runs.get_mut(stream_id)?;
+        let tx = handle.cancel.take()?;
+        // The receiver is only gone if the run already finished.
+        tx.send(()).ok()?;
+        Some(handle.session_key.clone())
+    }
+
+    /// Signal cancellation for every run in flight. Returns how many were
+    /// signalled.
+    pub fn cancel_all(&self) -> usize {
+        let mut runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        runs.values_mut()
+            .filter_map(|handle| handle.cancel.take())
+            .filter_map(|tx| tx.send(()).ok())
+            .count()
+    }
+
+    /// Remove a finished run.
+    pub fn finish(&self, stream_id: &str) {
+        let mut runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        runs.remove(stream_id);
+    }
+
+    /// Session key of an in-flight run.
+    pub fn session_key(&self, stream_id: &str) -> Option<String> {
+        let runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        runs.get(stream_id).map(|handle| handle.session_key.clone())
+    }
+
+    /// Number of in-flight runs.
+    pub fn count(&self) -> usize {
+        let runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        runs.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RunRegistry;
+
+    #[test]
+    fn register_rejects_duplicate_ids() {
+        let runs = RunRegistry::new();
+        assert!(runs.register("r1", "s").is_some());
+        assert!(runs.register("r1", "s").is_none());
+        runs.finish("r1");
+        assert!(runs.register("r1", "s").is_some());
+    }
+
+    #[tokio::test]
+    async fn cancel_fires_receiver_once() {
+        let runs = RunRegistry::new();
+        let rx = runs.register("r1", "agent:ws:me:main").unwrap();
+
+        assert_eq!(runs.cancel("r1").as_deref(), Some("agent:ws:me:main"));
+        assert!(rx.await.is_ok());
+        assert!(runs.cancel("r1").is_none());
+        assert!(runs.cancel("missing").is_none());
+    }
+}
@@ -1,42 +1,84 @@
-use axum::body::Bytes;
 use axum::{
     Router,
-    extract::ws::{Message, WebSocket, WebSocketUpgrade},
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+    extract::{ConnectInfo, DefaultBodyLimit, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
     routing::{get, post},
 };
-use futures::SinkExt;
+use futures::{SinkExt, StreamExt};
 use rust_embed::Embed;
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock, broadcast, mpsc};
+use tokio::time::Sleep;
 use tracing::{debug, info, warn};
 
+/// Outbound frames buffered per connection before stream tasks wait on the writer.
+const OUTBOUND_QUEUE: usize = 256;
+
+/// Close code sent when a connection's token was rotated out and its grace
+/// period ran out.
+const CLOSE_TOKEN_RETIRED: u16 = 4001;
+
 #[derive(Embed)]
 #[folder = "ui/dist/"]
 struct UiAssets;
 
 use super::auth;
+use super::hub::SessionHub;
+use super::jsonrpc::Dialect;
+use super::keyring::Keyring;
+use super::limits::{CLOSE_GOING_AWAY, CLOSE_MESSAGE_TOO_BIG, CLOSE_TRY_AGAIN_LATER, Slots};
 use super::protocol::RpcResult;
-use crate::agent::AgentEvent;
-use crate::config::ExoclawConfig;
+use super::ratelimit::{Dimension, RateLimited, RateLimiter};
+use super::reload::LiveConfig;
+use super::replay::ReplayBuffers;
+use super::runs::RunRegistry;
+use super::shutdown::{Phase, Shutdown};
+use super::webhook::WebhookState;
+use crate::agent::AgentRunner;
+use crate::agent::approval::{ApprovalGate, Approvals};
+use crate::agent::client_tools::{ClientCalls, DisconnectGuard};
+use crate::config::{AgentDefConfig, ExoclawConfig};
 use crate::memory::MemoryEngine;
 use crate::router::SessionRouter;
 use crate::sandbox::PluginHost;
 use crate::store::SessionStore;
-use crate::types::Message as AgentMessage;
+use crate::types::StreamEvent;
 
 pub struct AppState {
-    pub token: Option<String>,
+    /// Accepted auth tokens; reloadable at runtime.
+    pub keyring: Keyring,
     pub router: RwLock<SessionRouter>,
     pub plugins: Arc<RwLock<PluginHost>>,
     pub store: RwLock<SessionStore>,
     pub memory: Arc<RwLock<MemoryEngine>>,
-    pub config: ExoclawConfig,
+    /// Current config; replaced on reload.
+    pub config: LiveConfig,
     /// Per-session locks for message serialization (FR-006).
     pub session_locks: RwLock<HashMap<String, Arc<Mutex<()>>>>,
+    /// In-flight agent runs, for `chat.cancel`.
+    pub runs: RunRegistry,
+    /// Sequenced stream frames, for `chat.resume`.
+    pub replay: ReplayBuffers,
+    /// Request limits for `chat.send` and webhooks.
+    pub limiter: RateLimiter,
+    /// Open WebSocket connections (`gateway.max_connections`).
+    pub connections: Slots,
+    /// Agent runs in flight (`gateway.max_streams`).
+    pub streams: Slots,
+    /// Async webhook redelivery tracking and dead letters.
+    pub webhooks: WebhookState,
+    /// Graceful shutdown phase and the runs it waits for.
+    pub shutdown: Shutdown,
+    /// Live session subscribers, for `session.subscribe`.
+    pub hub: SessionHub,
+    /// Tool calls waiting for `tool.approve` or `tool.deny`.
+    pub approvals: Arc<Approvals>,
 }
 
 impl AppState {
@@ -55,32 +97,89 @@ impl AppState {
                 .or_insert_with(|| Arc::new(Mutex::new(()))),
         )
     }
+
+    /// Whether callers must present a token: true once any token is set.
+    pub fn auth_required(&self) -> bool {
+        self.keyring.auth_required()
+    }
+
+    /// Resolve a presented token to a caller. With auth off, everyone is a
+    /// trusted loopback caller.
+    pub fn authenticate(&self, presented: Option<&str>) -> Option<auth::Caller> {
+        if !self.auth_required() {
+            return Some(auth::Caller::trusted("loopback"));
+        }
+        self.keyring.authenticate(presented?)
+    }
+
+    /// Apply rate limits to a request from `source` (`chat.send`, `webhook`),
+    /// recording an audit event when one is hit.
+    pub fn rate_limit(&self, source: &str, keys: &[(Dimension, &str)]) -> Result<(), RateLimited> {
+        self.limiter.check(keys).inspect_err(|limited| {
+            crate::audit::record(
+                "rate_limited",
+                serde_json::json!({
+                    "source": source,
+                    "dimension": limited.dimension.as_str(),
+                    "key": limited.key,
+                    "retry_after_secs": limited.retry_after_secs(),
+                }),
+            );
+        })
+    }
+
+    /// A runner for `agent` on `session_key` that holds back the tools it may
+    /// only call with approval, including plugins loaded with
+    /// `plugin.load` and `require_approval`.
+    pub async fn runner_for(
+        &self,
+        config: &ExoclawConfig,
+        agent: &AgentDefConfig,
+        session_key: &str,
+    ) -> AgentRunner {
+        let runner = AgentRunner::new();
+        let mut tools = config.approval_tools(agent);
+        tools.extend(self.plugins.read().await.approval_tools());
+        if tools.is_empty() {
+            return runner;
+        }
+        runner.with_approvals(ApprovalGate::new(
+            tools,
+            Arc::clone(&self.approvals),
+            session_key,
+            Duration::from_secs(config.gateway.tool_approval_timeout_secs),
+        ))
+    }
+
+    /// Provider-formatted tool schemas for the plugins an agent is allowed to call.
+    pub async fn tool_schemas_for(&self, agent: &AgentDefConfig) -> Vec<serde_json::Value> {
+        let plugin_host = self.plugins.read().await;
+        let raw_schemas = plugin_host.tool_schemas_for(&agent.tools);
+        crate::agent::providers::build_tools_for_provider(&agent.provider, &raw_schemas)
+    }
 }
 
 pub async fn run(config: ExoclawConfig, token: Option<String>) -> anyhow::Result<()> {
     let is_loopback = config.gateway.bind == "127.0.0.1" || config.gateway.bind == "::1";
 
-    if !is_loopback && token.is_none() {
+    let keyring = Keyring::from_config(token, &config.gateway)?;
+    if !is_loopback && !keyring.auth_required() {
         anyhow::bail!(
             "Auth token required when binding to non-loopback address. \
-             Set --token or EXOCLAW_TOKEN env var."
+             Set --token, EXOCLAW_TOKEN env var, gateway.token_file, or [[gateway.tokens]]."
         );
     }
 
+    let tls = match &config.gateway.tls {
+        Some(tls) => Some(super::tls::load(tls, &config.gateway.bind).await?),
+        None => None,
+    };
+
     crate::agent::metering::init_global(&config.budgets);
 
-    // Populate router with bindings from config
+    // Populate router with bindings from config; unbound traffic goes to [agent].
     let mut router = SessionRouter::new();
-    for binding in &config.bindings {
-        router.add_binding(crate::router::Binding {
-            agent_id: binding.agent_id.clone(),
-            channel: binding.channel.clone(),
-            account_id: binding.account_id.clone(),
-            peer_id: binding.peer_id.clone(),
-            guild_id: binding.guild_id.clone(),
-            team_id: binding.team_id.clone(),
-        });
-    }
+    router.set_bindings(&config.agent.id, super::reload::router_bindings(&config));
     info!(bindings = config.bindings.len(), "router configured");
 
     // Load plugins from config (skip missing files with warning)
@@ -104,9 +203,11 @@ pub async fn run(config: ExoclawConfig, token: Option<String>) -> anyhow::Result
         config.memory.episodic_window as usize,
         config.memory.semantic_enabled,
     );
-    if let Some(path) = config.agent.soul_path.as_deref() {
-        if let Err(e) = memory.soul.load(&config.agent.id, path) {
-            warn!(agent = %config.agent.id, path, "failed to load soul: {e}");
+    for agent in config.all_agents() {
+        if let Some(path) = agent.soul_path.as_deref() {
+            if let Err(e) = memory.soul.load(&agent.id, path) {
+                warn!(agent = %agent.id, path, "failed to load soul: {e}");
+            }
         }
     }
 
@@ -115,36 +216,122 @@ pub async fn run(config: ExoclawConfig, token: Option<String>) -> anyhow::Result
             "no API key configured — run 'exoclaw onboard' or set ANTHROPIC_API_KEY/OPENAI_API_KEY"
         );
     }
+    for agent in &config.agents {
+        if agent.api_key.is_none() {
+            warn!(agent = %agent.id, provider = %agent.provider, "no API key configured for agent");
+        }
+    }
+
+    crate::audit::init(&config.audit)?;
 
     let addr = format!("{}:{}", config.gateway.bind, config.gateway.port);
 
+    let limiter = RateLimiter::new(config.gateway.rate_limits.clone());
+    let connections = Slots::new(config.gateway.max_connections);
+    let streams = Slots::new(config.gateway.max_streams);
+    let max_body = config.gateway.max_message_bytes;
+    let metrics_open = config.gateway.metrics_token.is_none();
+    let drain_timeout = Duration::from_secs(config.gateway.shutdown_drain_secs);
+    let webhooks = WebhookState::new(&config.gateway, crate::secrets::load_secret)?;
     let state = Arc::new(AppState {
-        token,
+        keyring,
         router: RwLock::new(router),
         plugins: Arc::new(RwLock::new(plugin_host)),
         store: RwLock::new(SessionStore::new()),
         memory: Arc::new(RwLock::new(memory)),
-        config,
+        config: config.into(),
         session_locks: RwLock::new(HashMap::new()),
+        runs: RunRegistry::new(),
+        replay: ReplayBuffers::default(),
+        limiter,
+        connections,
+        streams,
+        webhooks,
+        shutdown: Shutdown::default(),
+        hub: SessionHub::default(),
+        approvals: Arc::default(),
     });
 
+    tokio::spawn(super::reload::watch(
+        Arc::clone(&state),
+        crate::config::resolve_path(),
+    ));
+
     let app = Router::new()
         .route("/ws", get(ws_handler))
         .route("/health", get(health))
-        .route("/webhook/{channel}", post(webhook_handler))
+        .route("/metrics", get(metrics))
+        .route("/webhook/{channel}", post(super::webhook::webhook_handler))
+        .route("/v1/chat", post(super::http::chat_handler))
+        .route(
+            "/v1/chat/completions",
+            post(super::openai::chat_completions_handler),
+        )
+        .route("/v1/models", get(super::openai::models_handler))
         .fallback(get(ui_handler))
-        .with_state(state);
+        .layer(DefaultBodyLimit::max(max_body))
+        .with_state(Arc::clone(&state));
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
 
-    info!("exoclaw gateway listening on {addr}");
+    info!(tls = tls.is_some(), "exoclaw gateway listening on {addr}");
     if is_loopback {
         info!("bound to loopback — local access only");
     } else {
         warn!("bound to {addr} — ensure auth token is set");
+        if metrics_open {
+            warn!("/metrics is readable without a token; set gateway.metrics_token");
+        }
+        if tls.is_none() {
+            warn!("serving plain HTTP on a non-loopback address; configure [gateway.tls]");
+        }
     }
 
-    axum::serve(listener, app).await?;
+    tokio::spawn(super::shutdown::on_signal(Arc::clone(&state)));
+
+    // Stop accepting once shutdown begins; connections already open are
+    // drained below.
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let stopping = {
+        let state = Arc::clone(&state);
+        async move { state.shutdown.begun().await }
+    };
+    let server: Pin<Box<dyn Future<Output = std::io::Result<()>> + Send>> = match tls {
+        Some(tls) => {
+            let handle = axum_server::Handle::new();
+            tokio::spawn({
+                let handle = handle.clone();
+                async move {
+                    stopping.await;
+                    handle.graceful_shutdown(None);
+                }
+            });
+            Box::pin(
+                axum_server::from_tcp_rustls(listener.into_std()?, tls)
+                    .handle(handle)
+                    .serve(service),
+            )
+        }
+        None => Box::pin(
+            axum::serve(listener, service)
+                .with_graceful_shutdown(stopping)
+                .into_future(),
+        ),
+    };
+    let drained = async {
+        state.shutdown.begun().await;
+        super::shutdown::drain(&state, drain_timeout).await;
+    };
+
+    tokio::pin!(drained);
+    tokio::select! {
+        result = server => {
+            result?;
+            drained.await;
+        }
+        () = &mut drained => {}
+    }
+    info!("exoclaw gateway stopped");
     Ok(())
 }
 
@@ -152,6 +339,93 @@ async fn health() -> &'static str {
     "ok"
 }
 
+/// Prometheus scrape endpoint. Needs `gateway.metrics_token` as a bearer
+/// token when one is set; API tokens are not accepted here.
+async fn metrics(headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    let header = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    let expected = state
+        .config
+        .get()
+        .gateway
+        .metrics_token
+        .as_ref()
+        .map(|t| t.expose().to_string());
+    if !auth::verify_bearer(header, &expected) {
+        return (StatusCode::UNAUTHORIZED, "unauthorized").into_response();
+    }
+
+    use crate::metrics::Gauge;
+    // One lock at a time; a config reload takes them in another order.
+    let sessions_routed = state.router.read().await.session_count();
+    let sessions_stored = state.store.read().await.count();
+    let plugins_loaded = state.plugins.read().await.count();
+    let mut gauges = vec![
+        Gauge::new(
+            "exoclaw_connections_active",
+            "Open WebSocket connections.",
+            state.connections.in_use() as f64,
+        ),
+        Gauge::new(
+            "exoclaw_streams_active",
+            "Agent runs in flight.",
+            state.streams.in_use() as f64,
+        ),
+        Gauge::new(
+            "exoclaw_sessions_routed",
+            "Sessions known to the router.",
+            sessions_routed as f64,
+        ),
+        Gauge::new(
+            "exoclaw_sessions_stored",
+            "Sessions in the session store.",
+            sessions_stored as f64,
+        ),
+        Gauge::new(
+            "exoclaw_plugins_loaded",
+            "Loaded WASM plugins.",
+            plugins_loaded as f64,
+        ),
+    ];
+    {
+        use crate::agent::metering::{self, BudgetScope};
+        let counter = metering::get_or_init_global(&state.config.get().budgets);
+        let counter = counter.lock().unwrap_or_else(|e| e.into_inner());
+        for (period, scope) in [
+            ("daily", BudgetScope::Daily),
+            ("monthly", BudgetScope::Monthly),
+        ] {
+            let usage = counter.get_usage(&scope);
+            gauges.push(
+                Gauge::new(
+                    "exoclaw_budget_used_tokens",
+                    "Tokens counted against the daily and monthly budgets.",
+                    usage.total_tokens as f64,
+                )
+                .with_label("period", period),
+            );
+            gauges.push(
+                Gauge::new(
+                    "exoclaw_budget_used_cost_usd",
+                    "Estimated spend in the current budget period.",
+                    usage.cost_estimate_usd,
+                )
+                .with_label("period", period),
+            );
+        }
+    }
+
+    (
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        crate::metrics::render(&gauges),
+    )
+        .into_response()
+}
+
 async fn ui_handler(uri: axum::http::Uri) -> impl IntoResponse {
     let path = uri.path().trim_start_matches('/');
     // Serve index.html for root path or unknown paths (SPA routing)
@@ -182,19 +456,76 @@ async fn ui_handler(uri: axum::http::Uri) -> impl IntoResponse {
     }
 }
 
-async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_connection(socket, state))
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    if state.shutdown.is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "server is shutting down").into_response();
+    }
+    let origin = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok());
+    let host = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+    if !auth::origin_allowed(origin, host, &state.config.get().gateway.allowed_origins) {
+        warn!(%remote, origin, "rejecting websocket from disallowed origin");
+        crate::audit::record(
+            "origin_rejected",
+            serde_json::json!({ "origin": origin, "remote": remote.to_string() }),
+        );
+        return (StatusCode::FORBIDDEN, "origin not allowed").into_response();
+    }
+
+    let max = state.config.get().gateway.max_message_bytes;
+    // Take the slot before upgrading so concurrent handshakes cannot overshoot.
+    let slot = state.connections.try_take();
+    ws.max_message_size(max)
+        .max_frame_size(max)
+        .on_upgrade(move |socket| async move {
+            match slot {
+                Some(_slot) => handle_connection(socket, remote, state).await,
+                None => reject_connection(socket, remote, &state).await,
+            }
+        })
+        .into_response()
 }
 
-async fn handle_connection(mut socket: WebSocket, state: Arc<AppState>) {
-    if state.token.is_some() {
+/// Close a connection over `gateway.max_connections` with 1013 (try again later).
+async fn reject_connection(mut socket: WebSocket, remote: SocketAddr, state: &AppState) {
+    warn!(
+        %remote,
+        max_connections = state.connections.limit(),
+        "rejecting connection: too many connections"
+    );
+    let _ = socket
+        .send(Message::Close(Some(CloseFrame {
+            code: CLOSE_TRY_AGAIN_LATER,
+            reason: "too many connections".into(),
+        })))
+        .await;
+}
+
+async fn handle_connection(mut socket: WebSocket, remote: SocketAddr, state: Arc<AppState>) {
+    // Subscribe before authenticating so a rotation racing the handshake is seen.
+    let mut key_changes = state.keyring.subscribe();
+    let mut shutdown = state.shutdown.subscribe();
+    let mut presented = None;
+    let mut caller = if state.auth_required() {
         // First message must be auth when token auth is enabled.
-        let authed = match socket.recv().await {
-            Some(Ok(Message::Text(msg))) => auth::verify_connect(&msg, &state.token),
-            _ => false,
+        presented = match socket.recv().await {
+            Some(Ok(Message::Text(msg))) => auth::connect_token(&msg),
+            _ => None,
         };
 
-        if !authed {
+        let Some(caller) = state.authenticate(presented.as_deref()) else {
+            crate::audit::record(
+                "auth_failed",
+                serde_json::json!({
+                    "method": "connect",
+                    "remote": remote.to_string(),
+                    "token_presented": presented.is_some(),
+                }),
+            );
             let _ = socket
                 .send(Message::Text(
                     r#"{"error":"auth_failed","code":4001}"#.into(),
@@ -202,454 +533,391 @@ async fn handle_connection(mut socket: WebSocket, state: Arc<AppState>) {
                 .await;
             let _ = socket.close().await;
             return;
-        }
+        };
+        crate::audit::record(
+            "auth_ok",
+            serde_json::json!({
+                "method": "connect",
+                "remote": remote.to_string(),
+                "caller": caller.name,
+            }),
+        );
+        caller
+    } else {
+        auth::Caller::trusted("loopback")
     }
+    .with_remote(remote.ip());
+    let streams = Slots::new(state.config.get().gateway.max_streams_per_connection);
+    // Client tool calls waiting on this connection fail once it closes.
+    let client_calls = Arc::new(ClientCalls::default());
+    let _client_calls = DisconnectGuard(Arc::clone(&client_calls));
+    caller = caller
+        .with_streams(streams.clone())
+        .with_client_calls(Arc::clone(&client_calls));
 
     let _ = socket
         .send(Message::Text(r#"{"ok":true,"version":"0.1.0"}"#.into()))
         .await;
 
-    info!("client connected");
+    info!(caller = %caller.name, "client connected");
+
+    // Streams run as their own tasks and write frames into a shared outbound
+    // queue; a single writer task owns the socket sink. This lets the read loop
+    // keep dispatching RPCs (ping, status, more chat.send calls) while earlier
+    // streams are still in flight.
+    let (mut sink, mut inbound) = socket.split();
+    let (outbound, mut outbound_rx) = mpsc::channel::<Message>(OUTBOUND_QUEUE);
+    let writer = tokio::spawn(async move {
+        while let Some(msg) = outbound_rx.recv().await {
+            if sink.send(msg).await.is_err() {
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    });
 
-    // Message loop
-    while let Some(Ok(msg)) = socket.recv().await {
-        match msg {
-            Message::Text(text) => {
-                let result = super::protocol::handle_rpc(&text, &state).await;
-                match result {
-                    RpcResult::Response(resp) => {
-                        let _ = socket.send(Message::Text(resp.into())).await;
+    // Sessions this connection watches with `session.subscribe`.
+    let mut subscriptions = Subscriptions::default();
+    // Envelope of the last well-formed request, for framing parse errors.
+    let mut dialect = Dialect::JsonRpc2;
+
+    // Set once this connection's token has been rotated out.
+    let grace = Duration::from_secs(state.config.get().gateway.token_grace_secs);
+    let mut retired: Option<Pin<Box<Sleep>>> = None;
+
+    loop {
+        tokio::select! {
+            msg = inbound.next() => {
+                let msg = match msg {
+                    Some(Ok(msg)) => msg,
+                    Some(Err(e)) if is_too_big(&e) => {
+                        warn!(caller = %caller.name, "closing connection: message too large");
+                        let reason = format!(
+                            "message exceeds {} bytes",
+                            state.config.get().gateway.max_message_bytes
+                        );
+                        close(outbound, writer, CLOSE_MESSAGE_TOO_BIG, reason).await;
+                        return;
+                    }
+                    _ => break,
+                };
+                let open = match msg {
+                    Message::Text(text) => {
+                        handle_text(&text, &mut dialect, &caller, &state, &outbound, &mut subscriptions)
+                            .await
+                    }
+                    Message::Close(_) => false,
+                    _ => true,
+                };
+                if !open {
+                    break;
+                }
+            }
+            Ok(()) = key_changes.changed() => {
+                match state.authenticate(presented.as_deref()) {
+                    Some(current) => {
+                        if retired.take().is_some() {
+                            info!(caller = %current.name, "token accepted again; grace cancelled");
+                        }
+                        caller = current
+                            .with_remote(remote.ip())
+                            .with_streams(streams.clone())
+                            .with_client_calls(Arc::clone(&client_calls));
+                        subscriptions.retain_readable(&caller);
                     }
-                    RpcResult::Stream {
-                        id,
-                        session_key,
-                        agent_id: _agent_id,
-                        user_content,
-                        mut rx,
-                    } => {
+                    None if retired.is_none() => {
                         info!(
-                            request_id = %id,
-                            session = %session_key,
-                            "starting websocket event stream"
+                            caller = %caller.name,
+                            grace_secs = grace.as_secs(),
+                            "connection token retired; closing after grace period"
                         );
-                        // Stream AgentEvents as JSON frames to the client
-                        let mut assistant_text = String::new();
-                        let mut saw_done = false;
-                        let mut sent_frames: usize = 0;
-                        while let Some(event) = rx.recv().await {
-                            let event_kind = match &event {
-                                AgentEvent::Text(_) => "text",
-                                AgentEvent::ToolUse { .. } => "tool_use",
-                                AgentEvent::ToolResult { .. } => "tool_result",
-                                AgentEvent::Usage { .. } => "usage",
-                                AgentEvent::Done => "done",
-                                AgentEvent::Error(_) => "error",
-                            };
-                            let frame = match &event {
-                                AgentEvent::Text(text) => {
-                                    assistant_text.push_str(text);
-                                    serde_json::json!({
-                                        "id": id,
-                                        "event": "text",
-                                        "data": text,
-                                    })
-                                }
-                                AgentEvent::ToolUse {
-                                    id: call_id,
-                                    name,
-                                    input,
-                                } => {
-                                    serde_json::json!({
-                                        "id": id,
-                                        "event": "tool_use",
-                                        "data": {
-                                            "id": call_id,
-                                            "name": name,
-                                            "input": input,
-                                        },
-                                    })
-                                }
-                                AgentEvent::ToolResult {
-                                    tool_use_id,
-                                    content,
-                                    is_error,
-                                } => {
-                                    serde_json::json!({
-                                        "id": id,
-                                        "event": "tool_result",
-                                        "data": {
-                                            "tool_use_id": tool_use_id,
-                                            "content": content,
-                                            "is_error": is_error,
-                                        },
-                                    })
-                                }
-                                AgentEvent::Usage {
-                                    input_tokens,
-                                    output_tokens,
-                                } => {
-                                    serde_json::json!({
-                                        "id": id,
-                                        "event": "usage",
-                                        "data": {
-                                            "input_tokens": input_tokens,
-                                            "output_tokens": output_tokens,
-                                        },
-                                    })
-                                }
-                                AgentEvent::Done => {
-                                    serde_json::json!({
-                                        "id": id,
-                                        "event": "done",
-                                    })
-                                }
-                                AgentEvent::Error(err) => {
-                                    serde_json::json!({
-                                        "id": id,
-                                        "event": "error",
-                                        "data": err,
-                                    })
-                                }
-                            };
-
-                            let is_done = matches!(event, AgentEvent::Done);
-                            let frame_str = serde_json::to_string(&frame).unwrap_or_default();
-                            if socket.send(Message::Text(frame_str.into())).await.is_err() {
-                                // Client disconnected mid-stream
-                                debug!(
-                                    request_id = %id,
-                                    session = %session_key,
-                                    "client disconnected during stream send"
-                                );
-                                break;
-                            }
-                            sent_frames += 1;
-                            debug!(
-                                request_id = %id,
-                                session = %session_key,
-                                sent_frames,
-                                event = event_kind,
-                                "sent stream frame"
-                            );
-
-                            if is_done {
-                                saw_done = true;
-                                // Append collected assistant text to session
-                                if !assistant_text.is_empty() {
-                                    let mut store = state.store.write().await;
-                                    if let Some(session) = store.get_mut(&session_key) {
-                                        session.messages.push(serde_json::json!({
-                                            "role": "assistant",
-                                            "content": assistant_text.clone(),
-                                        }));
-                                    }
-
-                                    let mut memory = state.memory.write().await;
-                                    let user_message =
-                                        AgentMessage::text("user", user_content.clone());
-                                    let assistant_message =
-                                        AgentMessage::text("assistant", assistant_text.clone());
-                                    memory.process_response(
-                                        &session_key,
-                                        &user_message,
-                                        &assistant_message,
-                                    );
-                                }
-                                break;
-                            }
-                        }
-                        if !saw_done {
-                            warn!(
-                                request_id = %id,
-                                session = %session_key,
-                                sent_frames,
-                                "stream ended without done"
-                            );
-                        }
+                        let notice = serde_json::json!({
+                            "event": "token_retired",
+                            "data": { "grace_secs": grace.as_secs() },
+                        });
+                        let _ = outbound.send(Message::Text(notice.to_string().into())).await;
+                        retired = Some(Box::pin(tokio::time::sleep(grace)));
                     }
+                    None => {}
+                }
+            }
+            Ok(()) = shutdown.changed() => {
+                let phase = *shutdown.borrow_and_update();
+                if phase == Phase::Draining {
+                    let notice = serde_json::json!({
+                        "event": "server_shutdown",
+                        "data": { "drain_secs": state.config.get().gateway.shutdown_drain_secs },
+                    });
+                    let _ = outbound.send(Message::Text(notice.to_string().into())).await;
+                } else if phase == Phase::Closed {
+                    close(outbound, writer, CLOSE_GOING_AWAY, "server shutting down".into()).await;
+                    return;
                 }
             }
-            Message::Close(_) => break,
-            _ => {}
+            () = async { retired.as_mut().expect("guarded").await }, if retired.is_some() => {
+                info!(caller = %caller.name, "closing connection with retired token");
+                close(outbound, writer, CLOSE_TOKEN_RETIRED, "token retired".into()).await;
+                return;
+            }
         }
     }
 
+    // Stop writing; in-flight streams notice on their next send and stop.
+    writer.abort();
     info!("client disconnected");
 }
 
-/// Handle incoming webhook from a messaging platform.
-///
-/// 1. Look up channel adapter plugin by channel name
-/// 2. Call parse_incoming() to normalize the platform payload
-/// 3. Route through the agent loop
-/// 4. Collect the response
-/// 5. Call format_outgoing() to convert back to platform format
-/// 6. Return as HTTP response
-async fn webhook_handler(
-    Path(channel): Path<String>,
-    State(state): State<Arc<AppState>>,
-    body: Bytes,
-) -> impl IntoResponse {
-    // 1. Find channel adapter plugin
-    let adapter_name = {
-        let plugins = state.plugins.read().await;
-        plugins.find_channel_adapter(&channel).map(String::from)
-    };
-
-    let adapter_name = match adapter_name {
-        Some(name) => name,
-        None => {
-            warn!(channel = %channel, "no channel adapter found");
-            return (
-                StatusCode::NOT_FOUND,
-                format!("no channel adapter for '{channel}'"),
-            );
-        }
-    };
+/// Send a close frame and let the writer flush it before the connection ends.
+async fn close(
+    outbound: mpsc::Sender<Message>,
+    writer: tokio::task::JoinHandle<()>,
+    code: u16,
+    reason: String,
+) {
+    let _ = outbound
+        .send(Message::Close(Some(CloseFrame {
+            code,
+            reason: reason.into(),
+        })))
+        .await;
+    drop(outbound);
+    let _ = writer.await;
+    info!("client disconnected");
+}
 
-    // 2. Parse incoming payload via WASM plugin
-    let parsed = {
-        let plugins = state.plugins.read().await;
-        plugins.call_channel_parse(&adapter_name, &body)
-    };
+/// Whether a read failed because the message was over the size limit.
+fn is_too_big(error: &axum::Error) -> bool {
+    let source = std::error::Error::source(error);
+    matches!(
+        source.and_then(|e| e.downcast_ref::<tungstenite::Error>()),
+        Some(tungstenite::Error::Capacity(_))
+    )
+}
 
-    let parsed = match parsed {
-        Ok(v) => v,
-        Err(e) => {
-            warn!(channel = %channel, "parse_incoming failed: {e}");
-            return (
-                StatusCode::BAD_REQUEST,
-                format!("parse_incoming failed: {e}"),
-            );
+/// Handle one text message and queue its replies. Returns false once the
+/// connection's outbound queue has closed.
+async fn handle_text(
+    text: &str,
+    dialect: &mut Dialect,
+    caller: &auth::Caller,
+    state: &Arc<AppState>,
+    outbound: &mpsc::Sender<Message>,
+    subscriptions: &mut Subscriptions,
+) -> bool {
+    let result = super::protocol::handle_rpc_as(text, dialect, caller, state).await;
+    let is_batch = matches!(result, RpcResult::Batch(_));
+    let mut dispatch = Dispatch::default();
+    dispatch.add(result);
+
+    // Subscribe before acknowledging so no frame after the ack is missed.
+    for (session_key, subscribe) in dispatch.subscriptions {
+        if subscribe {
+            subscriptions.subscribe(session_key, state, outbound);
+        } else {
+            subscriptions.unsubscribe(&session_key);
         }
-    };
-
-    // Extract message fields from normalized payload
-    let content = parsed
-        .get("content")
-        .and_then(|c| c.as_str())
-        .unwrap_or("")
-        .to_string();
-    let account = parsed
-        .get("account")
-        .and_then(|a| a.as_str())
-        .unwrap_or("webhook")
-        .to_string();
-    let peer = parsed
-        .get("peer")
-        .and_then(|p| p.as_str())
-        .unwrap_or("main")
-        .to_string();
-    let guild = parsed
-        .get("guild")
-        .and_then(|g| g.as_str())
-        .map(String::from);
-    let team = parsed
-        .get("team")
-        .and_then(|t| t.as_str())
-        .map(String::from);
-
-    if content.is_empty() {
-        return (StatusCode::BAD_REQUEST, "empty message content".to_string());
     }
 
-    let user_message = AgentMessage::text("user", content.clone());
-
-    // 3. Route to agent
-    let route = {
-        let mut router = state.router.write().await;
-        router.resolve(
-            &channel,
-            &account,
-            Some(&peer),
-            guild.as_deref(),
-            team.as_deref(),
-        )
+    // A batch is answered with one array; an all-notification batch gets no
+    // reply at all.
+    let replies = if is_batch && !dispatch.replies.is_empty() {
+        vec![format!("[{}]", dispatch.replies.join(","))]
+    } else {
+        dispatch.replies
     };
+    for reply in replies {
+        if outbound.send(Message::Text(reply.into())).await.is_err() {
+            return false;
+        }
+    }
 
-    let session_lock = state.session_lock(&route.session_key).await;
-    let _session_guard = session_lock.lock().await;
-
-    // 4. Get/create session and append user message
-    {
-        let mut store = state.store.write().await;
-        let session = store.get_or_create(&route.session_key, &route.agent_id);
-        session.messages.push(serde_json::json!({
-            "role": "user",
-            "content": content.clone(),
-        }));
-        session.message_count += 1;
+    // Frames are read back from the replay buffer, which the run fills
+    // whether or not this socket stays open.
+    for (stream, after_seq) in dispatch.follows {
+        tokio::spawn(follow_stream(
+            stream,
+            after_seq,
+            outbound.clone(),
+            Arc::clone(state),
+        ));
     }
+    true
+}
 
-    // 5. Build message history using memory engine context
-    let messages = {
-        let mut memory = state.memory.write().await;
-        let mut context = memory.assemble_context(&route.session_key, &route.agent_id, &content);
-        context.push(user_message.clone());
-        context
-            .into_iter()
-            .filter_map(|m| m.as_provider_message())
-            .collect::<Vec<_>>()
-    };
+/// What to send back for one inbound message: replies in request order, then
+/// streams to follow (`(stream id, after_seq)`). Subscription changes
+/// (`(session key, subscribe)`) are applied first.
+#[derive(Default)]
+struct Dispatch {
+    replies: Vec<String>,
+    follows: Vec<(String, u64)>,
+    subscriptions: Vec<(String, bool)>,
+}
 
-    // 6. Create provider and run agent synchronously (collect full response)
-    let provider = match crate::agent::providers::from_config(&state.config.agent) {
-        Ok(p) => p,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("provider error: {e}"),
-            );
+impl Dispatch {
+    fn add(&mut self, result: RpcResult) {
+        match result {
+            RpcResult::Response(resp) => self.replies.push(resp),
+            RpcResult::Stream { ack, stream, .. } => {
+                self.replies.extend(ack);
+                self.follows.push((stream, 0));
+            }
+            RpcResult::Resume {
+                response,
+                stream,
+                after_seq,
+            } => {
+                self.replies.extend(response);
+                self.follows.push((stream, after_seq));
+            }
+            RpcResult::Subscription {
+                response,
+                session_key,
+                subscribe,
+            } => {
+                self.replies.extend(response);
+                self.subscriptions.push((session_key, subscribe));
+            }
+            RpcResult::Batch(results) => {
+                for result in results {
+                    self.add(result);
+                }
+            }
+            RpcResult::NoResponse => {}
         }
-    };
+    }
+}
 
-    let tool_schemas = {
-        let plugin_host = state.plugins.read().await;
-        let raw_schemas = plugin_host.tool_schemas();
-        crate::agent::providers::build_tools_for_provider(
-            &state.config.agent.provider,
-            &raw_schemas,
-        )
+/// Send a run's frames with `seq > after_seq` to the client, then keep
+/// following the live stream until it ends.
+///
+/// Stops early if the connection's outbound queue has closed. If the client
+/// falls so far behind that frames were evicted from the replay buffer, an
+/// `error` frame ends the stream for this connection.
+async fn follow_stream(
+    stream: String,
+    after_seq: u64,
+    outbound: mpsc::Sender<Message>,
+    state: Arc<AppState>,
+) {
+    let Some(mut changes) = state.replay.watch(&stream) else {
+        return;
     };
+    debug!(stream = %stream, after_seq, "following stream");
 
-    let (tx, mut rx) = tokio::sync::mpsc::channel::<AgentEvent>(32);
-    let system_prompt = state.config.agent.system_prompt.clone();
-    let plugins = Arc::clone(&state.plugins);
-
-    // Spawn agent task
-    tokio::spawn(async move {
-        let runner = crate::agent::AgentRunner::new();
-        let result = runner
-            .run_with_tools(
-                provider.as_ref(),
-                messages,
-                &tool_schemas,
-                system_prompt.as_deref(),
-                &plugins,
-                tx.clone(),
-            )
-            .await;
+    let mut last_seq = after_seq;
+    loop {
+        changes.borrow_and_update();
+        let slice = match state.replay.frames_after(&stream, last_seq) {
+            Ok(slice) => slice,
+            Err(e) => {
+                warn!(stream = %stream, last_seq, "stream follow aborted: {e}");
+                let request_id = state.replay.request_id(&stream).unwrap_or_default();
+                let mut frame = StreamEvent::Error(e).to_frame(&request_id);
+                frame["stream"] = serde_json::json!(stream);
+                let _ = outbound.send(Message::Text(frame.to_string().into())).await;
+                return;
+            }
+        };
 
-        if let Err(e) = result {
-            let _ = tx
-                .send(AgentEvent::Error(format!("agent error: {e}")))
-                .await;
-            let _ = tx.send(AgentEvent::Done).await;
+        for frame in slice.frames {
+            last_seq = frame["seq"].as_u64().unwrap_or(last_seq);
+            if outbound
+                .send(Message::Text(frame.to_string().into()))
+                .await
+                .is_err()
+            {
+                // Client disconnected; the run keeps going and stays resumable.
+                debug!(stream = %stream, last_seq, "client disconnected during stream send");
+                return;
+            }
         }
-    });
 
-    // 7. Collect full response text
-    let mut response_text = String::new();
-    while let Some(event) = rx.recv().await {
-        match event {
-            AgentEvent::Text(text) => response_text.push_str(&text),
-            AgentEvent::Done => break,
-            AgentEvent::Error(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("agent error: {e}"),
-                );
-            }
-            _ => {}
+        if slice.finished {
+            debug!(stream = %stream, last_seq, "stream follow complete");
+            return;
+        }
+        if changes.changed().await.is_err() {
+            // The buffer was dropped after its retention period.
+            return;
         }
     }
+}
 
-    // 8. Append assistant response to session
-    if !response_text.is_empty() {
-        let mut store = state.store.write().await;
-        if let Some(session) = store.get_mut(&route.session_key) {
-            session.messages.push(serde_json::json!({
-                "role": "assistant",
-                "content": response_text.clone(),
-            }));
-            session.message_count += 1;
+/// A connection's session subscriptions: one forwarding task per session.
+/// Dropping it stops them all.
+#[derive(Default)]
+struct Subscriptions(HashMap<String, tokio::task::AbortHandle>);
+
+impl Subscriptions {
+    fn subscribe(
+        &mut self,
+        session_key: String,
+        state: &AppState,
+        outbound: &mpsc::Sender<Message>,
+    ) {
+        if self.0.contains_key(&session_key) {
+            return;
         }
+        let rx = state.hub.subscribe(&session_key);
+        let task = tokio::spawn(forward_session(session_key.clone(), rx, outbound.clone()));
+        debug!(session = %session_key, "session subscribed");
+        self.0.insert(session_key, task.abort_handle());
+    }
 
-        let mut memory = state.memory.write().await;
-        let assistant_message = AgentMessage::text("assistant", response_text.clone());
-        memory.process_response(&route.session_key, &user_message, &assistant_message);
+    fn unsubscribe(&mut self, session_key: &str) {
+        if let Some(task) = self.0.remove(session_key) {
+            task.abort();
+            debug!(session = %session_key, "session unsubscribed");
+        }
     }
 
-    // 9. Format outgoing via channel adapter plugin
-    let formatted = {
-        let plugins = state.plugins.read().await;
-        plugins.call_channel_format(
-            &adapter_name,
-            &serde_json::json!({ "content": response_text }),
-        )
-    };
+    /// Drop the subscriptions a rotated token no longer allows.
+    fn retain_readable(&mut self, caller: &auth::Caller) {
+        let readable = caller.has_scope(auth::Scope::SessionRead);
+        self.0.retain(|session_key, task| {
+            let keep = readable && caller.may_access_session(session_key);
+            if !keep {
+                info!(caller = %caller.name, session = %session_key, "subscription dropped after token change");
+                task.abort();
+            }
+            keep
+        });
+    }
+}
 
-    let formatted_payload = match formatted {
-        Ok(payload) => payload,
-        Err(e) => {
-            warn!(channel = %channel, "format_outgoing failed: {e}");
-            // Return raw text as fallback
-            return (StatusCode::OK, response_text);
+impl Drop for Subscriptions {
+    fn drop(&mut self) {
+        for task in self.0.values() {
+            task.abort();
         }
-    };
+    }
+}
 
-    // 10. HTTP proxy: if format_outgoing returned JSON with a "url" field,
-    //     the host makes the API call on behalf of the plugin (T045).
-    //     Plugin never sees API tokens — the host manages credentials.
-    let formatted_json: Option<serde_json::Value> = serde_json::from_slice(&formatted_payload).ok();
-
-    if let Some(ref json) = formatted_json {
-        if let Some(proxy_url) = json.get("url").and_then(|u| u.as_str()) {
-            // Validate against allowed_hosts capability
-            let allowed = {
-                let plugins = state.plugins.read().await;
-                plugins.allowed_hosts(&adapter_name)
-            };
-
-            let url_host = url::Url::parse(proxy_url)
-                .ok()
-                .and_then(|u| u.host_str().map(String::from));
-
-            let is_allowed = match &url_host {
-                Some(host) => allowed.iter().any(|h| h == host),
-                None => false,
-            };
-
-            if is_allowed {
-                let proxy_body = json
-                    .get("body")
-                    .cloned()
-                    .unwrap_or(serde_json::json!({"text": response_text}));
-
-                let client = reqwest::Client::new();
-                match client.post(proxy_url).json(&proxy_body).send().await {
-                    Ok(resp) => {
-                        let status = resp.status();
-                        let body = resp.text().await.unwrap_or_default();
-                        info!(channel = %channel, url = %proxy_url, %status, "proxy call completed");
-                        return (StatusCode::OK, body);
-                    }
-                    Err(e) => {
-                        warn!(channel = %channel, url = %proxy_url, "proxy call failed: {e}");
-                        return (StatusCode::BAD_GATEWAY, format!("proxy call failed: {e}"));
-                    }
-                }
-            } else {
-                warn!(
-                    channel = %channel,
-                    url = %proxy_url,
-                    "proxy denied: host not in allowed_hosts"
-                );
-                return (
-                    StatusCode::FORBIDDEN,
-                    format!(
-                        "proxy denied: {} not in allowed_hosts for adapter '{}'",
-                        url_host.as_deref().unwrap_or("unknown"),
-                        adapter_name
-                    ),
-                );
+/// Send a session's live frames to the client. A client too slow to keep up
+/// gets a `subscription_lagged` frame with the number it missed, and can
+/// catch up with `session.history`.
+async fn forward_session(
+    session_key: String,
+    mut frames: broadcast::Receiver<Arc<str>>,
+    outbound: mpsc::Sender<Message>,
+) {
+    loop {
+        let text = match frames.recv().await {
+            Ok(frame) => frame.to_string(),
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                warn!(session = %session_key, missed, "session subscriber fell behind");
+                serde_json::json!({
+                    "event": "subscription_lagged",
+                    "session_key": session_key,
+                    "data": { "missed": missed },
+                })
+                .to_string()
             }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if outbound.send(Message::Text(text.into())).await.is_err() {
+            return;
         }
     }
-
-    // No proxy URL — return the formatted payload directly
-    (
-        StatusCode::OK,
-        String::from_utf8_lossy(&formatted_payload).to_string(),
-    )
 }
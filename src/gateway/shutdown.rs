@@ -0,0 +1,171 @@
+//! Graceful shutdown.
+//!
+//! On Ctrl-C or SIGTERM the gateway stops accepting connections, refuses new
+//! `chat.send` calls and webhooks, and tells connected clients with a
+//! `server_shutdown` frame. Agent runs already in flight get
+//! `gateway.shutdown_drain_secs` to finish; any still running after that are
+//! cancelled, which meters them and saves their partial reply like
+//! `chat.cancel` does. Connections are then closed with 1001 (going away).
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use super::server::AppState;
+
+/// How long cancelled runs get to meter and save their partial replies.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long connections get to send their close frames.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Running,
+    /// Finishing in-flight work; nothing new is accepted.
+    Draining,
+    /// Drained; connections should close.
+    Closed,
+}
+
+/// Shutdown state shared by the server, connections and agent runs.
+pub struct Shutdown {
+    phase: watch::Sender<Phase>,
+    /// Tasks that must finish before exit: the relay of each agent run,
+    /// which meters usage and saves the reply, and async webhook runs.
+    tasks: Arc<watch::Sender<usize>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            phase: watch::channel(Phase::Running).0,
+            tasks: Arc::new(watch::channel(0).0),
+        }
+    }
+}
+
+impl Shutdown {
+    /// Stop taking new work. Returns false if shutdown had already begun.
+    pub fn begin(&self) -> bool {
+        self.phase.send_if_modified(|phase| {
+            let begun = *phase == Phase::Running;
+            if begun {
+                *phase = Phase::Draining;
+            }
+            begun
+        })
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.phase.borrow() != Phase::Running
+    }
+
+    /// Fires on every phase change.
+    pub fn subscribe(&self) -> watch::Receiver<Phase> {
+        self.phase.subscribe()
+    }
+
+    /// Resolves once shutdown has begun.
+    pub async fn begun(&self) {
+        let mut phase = self.subscribe();
+        let _ = phase.wait_for(|phase| *phase != Phase::Running).await;
+    }
+
+    /// Hold off exit until the returned guard is dropped.
+    pub fn track(&self) -> TaskGuard {
+        self.tasks.send_modify(|count| *count += 1);
+        TaskGuard(Arc::clone(&self.tasks))
+    }
+
+    /// Tracked tasks still running.
+    pub fn tasks(&self) -> usize {
+        *self.tasks.borrow()
+    }
+
+    async fn idle(&self) {
+        let mut tasks = self.tasks.subscribe();
+        let _ = tasks.wait_for(|count| *count == 0).await;
+    }
+}
+
+/// Keeps the gateway from exiting while a tracked task runs.
+pub struct TaskGuard(Arc<watch::Sender<usize>>);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.0.send_modify(|count| *count -= 1);
+    }
+}
+
+/// Begin shutdown and wait for in-flight runs: up to `timeout` for them to
+/// finish, then cancel the rest and wait for their replies to be saved.
+pub async fn drain(state: &AppState, timeout: Duration) {
+    state.shutdown.begin();
+    let runs = state.runs.count();
+    if runs > 0 || state.shutdown.tasks() > 0 {
+        info!(
+            runs,
+            timeout_secs = timeout.as_secs(),
+            "waiting for in-flight agent runs"
+        );
+    }
+    if tokio::time::timeout(timeout, state.shutdown.idle())
+        .await
+        .is_err()
+    {
+        let cancelled = state.runs.cancel_all();
+        warn!(cancelled, "drain timeout reached; cancelling agent runs");
+        if tokio::time::timeout(FLUSH_TIMEOUT, state.shutdown.idle())
+            .await
+            .is_err()
+        {
+            warn!(
+                tasks = state.shutdown.tasks(),
+                "tasks still running at exit; their work is lost"
+            );
+        }
+    }
+    state.shutdown.phase.send_replace(Phase::Closed);
+
+    // Let connections send their close frames before the runtime stops.
+    let closed = async {
+        while state.connections.in_use() > 0 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    let _ = tokio::time::timeout(CLOSE_TIMEOUT, closed).await;
+    info!("drained");
+}
+
+/// Begin shutdown on Ctrl-C or SIGTERM.
+pub async fn on_signal(state: Arc<AppState>) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("cannot listen for Ctrl-C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("cannot listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => info!("Ctrl-C received, shutting down"),
+        () = terminate => info!("SIGTERM received, shutting down"),
+    }
+    state.shutdown.begin();
+}
@@ -0,0 +1,222 @@
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::auth::constant_time_eq;
+use crate::config::{HmacVerifyConfig, WebhookConfig};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Checks a channel's webhook requests before the adapter sees them.
+///
+/// Built once at startup from `[gateway.webhooks.<channel>]`, with secrets
+/// resolved from the credentials store.
+pub struct Verifier {
+    hmac: Option<(HmacVerifyConfig, Vec<u8>)>,
+    secret_token: Option<(String, String)>,
+}
+
+impl Verifier {
+    /// Build the verifier for `channel`, or `None` if it has no checks
+    /// configured. `secret` looks up a name in the credentials store.
+    pub fn new(
+        channel: &str,
+        config: &WebhookConfig,
+        secret: impl Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Option<Self>> {
+        let resolve = |name: &str| {
+            secret(name).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "webhook secret '{name}' for channel '{channel}' is not in the credentials store; run `exoclaw secret set {name}`"
+                )
+            })
+        };
+        let hmac = match &config.hmac {
+            Some(hmac) => Some((hmac.clone(), resolve(&hmac.secret)?.into_bytes())),
+            None => None,
+        };
+        let secret_token = match &config.secret_token {
+            Some(token) => Some((token.header.clone(), resolve(&token.secret)?)),
+            None => None,
+        };
+        if hmac.is_none() && secret_token.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self { hmac, secret_token }))
+    }
+
+    /// Check a request received at unix time `now`. The error is the reason
+    /// for the audit log; it never includes secrets.
+    pub fn verify(&self, headers: &HeaderMap, body: &[u8], now: i64) -> Result<(), String> {
+        if let Some((header, expected)) = &self.secret_token {
+            let presented = header_str(headers, header)?;
+            if !constant_time_eq(presented.as_bytes(), expected.as_bytes()) {
+                return Err(format!("{header} does not match"));
+            }
+        }
+        if let Some((config, key)) = &self.hmac {
+            verify_hmac(config, key, headers, body, now)?;
+        }
+        Ok(())
+    }
+}
+
+fn verify_hmac(
+    config: &HmacVerifyConfig,
+    key: &[u8],
+    headers: &HeaderMap,
+    body: &[u8],
+    now: i64,
+) -> Result<(), String> {
+    let signature = header_str(headers, &config.header)?;
+    let digest = signature
+        .strip_prefix(config.prefix.as_str())
+        .and_then(|hex| hex::decode(hex.trim()).ok())
+        .ok_or_else(|| format!("{} is malformed", config.header))?;
+
+    let timestamp = match &config.timestamp_header {
+        Some(name) => {
+            let value = header_str(headers, name)?;
+            let at: i64 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("{name} is not a unix timestamp"))?;
+            if now.abs_diff(at) > config.timestamp_tolerance_secs {
+                return Err(format!("{name} is outside the allowed tolerance"));
+            }
+            value
+        }
+        None => "",
+    };
+
+    let mut mac = HmacSha256::new_from_slice(key).map_err(|e| e.to_string())?;
+    // Substitute around {body} so the raw bytes are signed, not a lossy
+    // UTF-8 copy of them.
+    let (before, after) = config
+        .signed_payload
+        .split_once("{body}")
+        .unwrap_or((config.signed_payload.as_str(), ""));
+    mac.update(before.replace("{timestamp}", timestamp).as_bytes());
+    mac.update(body);
+    mac.update(after.replace("{timestamp}", timestamp).as_bytes());
+    mac.verify_slice(&digest)
+        .map_err(|_| format!("{} does not match", config.header))
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, String> {
+    headers
+        .get(name)
+        .ok_or_else(|| format!("missing {name}"))?
+        .to_str()
+        .map_err(|_| format!("{name} is not valid text"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SecretTokenConfig;
+
+    fn sign(key: &str, payload: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(key.as_bytes()).unwrap();
+        mac.update(payload);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn headers(pairs: &[(&'static str, String)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    fn store(name: &str) -> Option<String> {
+        (name == "signing").then(|| "topsecret".to_string())
+    }
+
+    #[test]
+    fn hmac_with_prefix_is_checked_over_the_body() {
+        let config = WebhookConfig {
+            hmac: Some(HmacVerifyConfig {
+                secret: "signing".into(),
+                header: "x-hub-signature-256".into(),
+                prefix: "sha256=".into(),
+                timestamp_header: None,
+                signed_payload: "{body}".into(),
+                timestamp_tolerance_secs: 300,
+            }),
+            ..Default::default()
+        };
+        let verifier = Verifier::new("github", &config, store).unwrap().unwrap();
+        let body = br#"{"text":"hi"}"#;
+
+        let good = format!("sha256={}", sign("topsecret", body));
+        let ok = headers(&[("x-hub-signature-256", good.clone())]);
+        assert!(verifier.verify(&ok, body, 0).is_ok());
+        assert!(verifier.verify(&ok, b"tampered", 0).is_err());
+
+        let unprefixed = headers(&[("x-hub-signature-256", sign("topsecret", body))]);
+        let err = verifier.verify(&unprefixed, body, 0).unwrap_err();
+        assert!(err.contains("malformed"));
+        let err = verifier.verify(&HeaderMap::new(), body, 0).unwrap_err();
+        assert!(err.contains("missing"));
+    }
+
+    #[test]
+    fn signed_timestamp_outside_tolerance_is_rejected() {
+        let config = WebhookConfig {
+            hmac: Some(HmacVerifyConfig {
+                secret: "signing".into(),
+                header: "x-slack-signature".into(),
+                prefix: "v0=".into(),
+                timestamp_header: Some("x-slack-request-timestamp".into()),
+                signed_payload: "v0:{timestamp}:{body}".into(),
+                timestamp_tolerance_secs: 300,
+            }),
+            ..Default::default()
+        };
+        let verifier = Verifier::new("slack", &config, store).unwrap().unwrap();
+        let body = b"token=x&text=hi";
+        let at = 1_700_000_000i64;
+        let signature = sign("topsecret", format!("v0:{at}:token=x&text=hi").as_bytes());
+        let request = headers(&[
+            ("x-slack-signature", format!("v0={signature}")),
+            ("x-slack-request-timestamp", at.to_string()),
+        ]);
+
+        assert!(verifier.verify(&request, body, at + 299).is_ok());
+        let err = verifier.verify(&request, body, at + 301).unwrap_err();
+        assert!(err.contains("tolerance"));
+
+        // Moving the timestamp forward breaks the signature.
+        let replayed = headers(&[
+            ("x-slack-signature", format!("v0={signature}")),
+            ("x-slack-request-timestamp", (at + 600).to_string()),
+        ]);
+        assert!(verifier.verify(&replayed, body, at + 600).is_err());
+    }
+
+    #[test]
+    fn secret_token_header_must_match_and_secrets_must_exist() {
+        let config = WebhookConfig {
+            secret_token: Some(SecretTokenConfig {
+                secret: "signing".into(),
+                header: "x-telegram-bot-api-secret-token".into(),
+            }),
+            ..Default::default()
+        };
+        let verifier = Verifier::new("telegram", &config, store).unwrap().unwrap();
+        let ok = headers(&[("x-telegram-bot-api-secret-token", "topsecret".into())]);
+        let bad = headers(&[("x-telegram-bot-api-secret-token", "guess".into())]);
+        assert!(verifier.verify(&ok, b"{}", 0).is_ok());
+        assert!(verifier.verify(&bad, b"{}", 0).is_err());
+
+        assert!(
+            Verifier::new("telegram", &WebhookConfig::default(), store)
+                .unwrap()
+                .is_none()
+        );
+        let missing = Verifier::new("telegram", &config, |_| None).err().unwrap();
+        assert!(missing.to_string().contains("exoclaw secret set signing"));
+    }
+}
@@ -0,0 +1,139 @@
+use axum_server::tls_rustls::RustlsConfig;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::config::TlsConfig;
+
+/// How often certificate files are checked for changes.
+const RELOAD_POLL: Duration = Duration::from_secs(10);
+
+/// Names an ephemeral certificate is valid for, besides the bind address.
+const SELF_SIGNED_NAMES: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+
+/// Build the rustls config for `[gateway.tls]`, and spawn a task that reloads
+/// the certificate when its files change.
+pub async fn load(tls: &TlsConfig, bind: &str) -> anyhow::Result<RustlsConfig> {
+    if tls.self_signed {
+        let (cert, key) = self_signed(bind)?;
+        warn!("serving TLS with an ephemeral self-signed certificate; for local testing only");
+        return Ok(RustlsConfig::from_pem(cert, key).await?);
+    }
+
+    let (Some(cert), Some(key)) = (&tls.cert_path, &tls.key_path) else {
+        anyhow::bail!("gateway.tls needs cert_path and key_path, or self_signed = true");
+    };
+    let config = RustlsConfig::from_pem_file(cert, key)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to load TLS certificate {cert}: {e}"))?;
+    let mut watcher = CertWatcher::new(cert, key);
+    info!(cert = %cert, "TLS certificate loaded");
+
+    let reloading = config.clone();
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(RELOAD_POLL);
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            if let Err(e) = watcher.reload_if_changed(&reloading).await {
+                warn!("TLS certificate reload failed, keeping current certificate: {e}");
+            }
+        }
+    });
+    Ok(config)
+}
+
+/// A PEM certificate and key for the bind address and localhost.
+pub fn self_signed(bind: &str) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let mut names: Vec<String> = SELF_SIGNED_NAMES.iter().map(|n| n.to_string()).collect();
+    if !names.iter().any(|n| n == bind) && bind != "0.0.0.0" && bind != "::" {
+        names.push(bind.to_string());
+    }
+    let certified = rcgen::generate_simple_self_signed(names)?;
+    Ok((
+        certified.cert.pem().into_bytes(),
+        certified.key_pair.serialize_pem().into_bytes(),
+    ))
+}
+
+/// Watches certificate and key files by modification time and size.
+pub struct CertWatcher {
+    cert: PathBuf,
+    key: PathBuf,
+    snapshot: Option<[(SystemTime, u64); 2]>,
+}
+
+impl CertWatcher {
+    pub fn new(cert: impl AsRef<Path>, key: impl AsRef<Path>) -> Self {
+        let mut watcher = Self {
+            cert: cert.as_ref().to_path_buf(),
+            key: key.as_ref().to_path_buf(),
+            snapshot: None,
+        };
+        watcher.snapshot = watcher.stamp();
+        watcher
+    }
+
+    /// Reload `config` if either file changed since the last successful load.
+    /// Returns whether a new certificate was installed. On error the current
+    /// certificate stays in place and the change is retried next time.
+    pub async fn reload_if_changed(&mut self, config: &RustlsConfig) -> anyhow::Result<bool> {
+        let stamp = self.stamp();
+        if stamp.is_none() || stamp == self.snapshot {
+            return Ok(false);
+        }
+        config
+            .reload_from_pem_file(&self.cert, &self.key)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}: {e}", self.cert.display()))?;
+        self.snapshot = stamp;
+        info!(cert = %self.cert.display(), "TLS certificate reloaded");
+        Ok(true)
+    }
+
+    fn stamp(&self) -> Option<[(SystemTime, u64); 2]> {
+        let stamp = |path: &Path| {
+            let meta = std::fs::metadata(path).ok()?;
+            Some((meta.modified().ok()?, meta.len()))
+        };
+        Some([stamp(&self.cert)?, stamp(&self.key)?])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn changed_files_are_reloaded_and_bad_ones_skipped() {
+        let dir = std::env::temp_dir().join(format!("exoclaw-tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (cert_path, key_path) = (dir.join("cert.pem"), dir.join("key.pem"));
+        let write = |bump: u64| {
+            let (cert, key) = self_signed("127.0.0.1").unwrap();
+            std::fs::write(&cert_path, cert).unwrap();
+            std::fs::write(&key_path, key).unwrap();
+            // Filesystem timestamps can be coarse; move them on explicitly.
+            let when = SystemTime::now() + Duration::from_secs(bump);
+            for path in [&cert_path, &key_path] {
+                let file = std::fs::File::options().write(true).open(path).unwrap();
+                file.set_modified(when).unwrap();
+            }
+        };
+
+        write(0);
+        let config = RustlsConfig::from_pem_file(&cert_path, &key_path)
+            .await
+            .unwrap();
+        let mut watcher = CertWatcher::new(&cert_path, &key_path);
+        assert!(!watcher.reload_if_changed(&config).await.unwrap());
+
+        write(10);
+        assert!(watcher.reload_if_changed(&config).await.unwrap());
+
+        std::fs::write(&cert_path, "not a certificate").unwrap();
+        assert!(watcher.reload_if_changed(&config).await.is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
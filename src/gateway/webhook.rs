@@ -0,0 +1,660 @@
+use axum::body::Bytes;
+use axum::{
+    Json,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use super::ratelimit::{Dimension, RateLimited};
+use super::server::AppState;
+use super::signature::Verifier;
+use crate::agent::AgentEvent;
+use crate::config::{GatewayConfig, WebhookConfig, WebhookMode};
+use crate::router::RouteResult;
+use crate::types::{Attachment, Message as AgentMessage, StreamEvent};
+
+/// Dead letters kept for `webhook.dead_letters`; the oldest are dropped first.
+const MAX_DEAD_LETTERS: usize = 1000;
+
+/// Message ids remembered before expired ones are swept.
+const DEDUP_SWEEP_AT: usize = 10_000;
+
+/// Signature checks, redelivery tracking and failed deliveries for webhooks.
+#[derive(Default)]
+pub struct WebhookState {
+    /// Channels with `hmac` or `secret_token` configured.
+    verifiers: HashMap<String, Verifier>,
+    /// `(channel, message id)` → when it was first accepted.
+    seen: Mutex<HashMap<(String, String), Instant>>,
+    dead_letters: Mutex<VecDeque<DeadLetter>>,
+}
+
+/// A reply that could not be delivered.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadLetter {
+    pub channel: String,
+    pub message_id: Option<String>,
+    pub session_key: String,
+    /// The reply text, so it can be resent by hand.
+    pub content: String,
+    pub url: Option<String>,
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl WebhookState {
+    /// Build the per-channel verifiers. Fails if a configured secret is not
+    /// in the credentials store, so a misconfigured channel never runs open.
+    pub fn new(
+        config: &GatewayConfig,
+        secret: impl Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Self> {
+        let mut verifiers = HashMap::new();
+        for (channel, webhook) in &config.webhooks {
+            if let Some(verifier) = Verifier::new(channel, webhook, &secret)? {
+                verifiers.insert(channel.clone(), verifier);
+            }
+        }
+        Ok(Self {
+            verifiers,
+            ..Default::default()
+        })
+    }
+
+    /// Check a request against the channel's signature config, if any.
+    pub fn verify(&self, channel: &str, headers: &HeaderMap, body: &[u8]) -> Result<(), String> {
+        match self.verifiers.get(channel) {
+            Some(verifier) => verifier.verify(headers, body, chrono::Utc::now().timestamp()),
+            None => Ok(()),
+        }
+    }
+
+    /// Record a message id. Returns false if it was already seen within `ttl`.
+    pub fn first_delivery(&self, channel: &str, message_id: &str, ttl: Duration) -> bool {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if seen.len() >= DEDUP_SWEEP_AT {
+            seen.retain(|_, at| now.duration_since(*at) < ttl);
+        }
+        let key = (channel.to_string(), message_id.to_string());
+        match seen.get(&key) {
+            Some(at) if now.duration_since(*at) < ttl => false,
+            _ => {
+                seen.insert(key, now);
+                true
+            }
+        }
+    }
+
+    pub fn dead_letter(&self, letter: DeadLetter) {
+        warn!(
+            channel = %letter.channel,
+            message_id = ?letter.message_id,
+            attempts = letter.attempts,
+            "webhook reply dead-lettered: {}",
+            letter.error
+        );
+        let mut letters = self.dead_letters.lock().unwrap_or_else(|e| e.into_inner());
+        if letters.len() >= MAX_DEAD_LETTERS {
+            letters.pop_front();
+        }
+        letters.push_back(letter);
+    }
+
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        let letters = self.dead_letters.lock().unwrap_or_else(|e| e.into_inner());
+        letters.iter().cloned().collect()
+    }
+}
+
+/// A platform message as normalized by `parse_incoming`.
+struct Incoming {
+    content: String,
+    /// Platform media (photos, files) the adapter passed along.
+    attachments: Vec<Attachment>,
+    account: String,
+    peer: String,
+    guild: Option<String>,
+    team: Option<String>,
+    /// Platform message id, for dropping redeliveries.
+    message_id: Option<String>,
+    /// Adapter data echoed back to `format_outgoing`.
+    metadata: serde_json::Value,
+}
+
+impl Incoming {
+    fn from_parsed(parsed: &serde_json::Value) -> Result<Self, String> {
+        let field = |name: &str| parsed.get(name).and_then(|v| v.as_str()).map(String::from);
+        let attachments = match parsed.get("attachments") {
+            Some(attachments) => serde_json::from_value(attachments.clone())
+                .map_err(|e| format!("invalid attachments: {e}"))?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            content: field("content").unwrap_or_default(),
+            attachments,
+            account: field("account").unwrap_or_else(|| "webhook".into()),
+            peer: field("peer").unwrap_or_else(|| "main".into()),
+            guild: field("guild"),
+            team: field("team"),
+            message_id: parsed.get("message_id").and_then(|id| match id {
+                serde_json::Value::String(id) => Some(id.clone()),
+                serde_json::Value::Number(id) => Some(id.to_string()),
+                _ => None,
+            }),
+            metadata: parsed
+                .get("metadata")
+                .cloned()
+                .unwrap_or(serde_json::Value::Null),
+        })
+    }
+
+    /// Input for `format_outgoing`.
+    fn outgoing(&self, reply: &str) -> serde_json::Value {
+        serde_json::json!({
+            "content": reply,
+            "account": self.account,
+            "peer": self.peer,
+            "message_id": self.message_id,
+            "metadata": self.metadata,
+        })
+    }
+}
+
+/// What `format_outgoing` asked the host to do with a reply.
+enum Outgoing {
+    /// Return (sync) the formatted payload as-is.
+    Direct(Vec<u8>),
+    /// POST `body` to `url` on the adapter's behalf.
+    Proxy {
+        url: String,
+        body: serde_json::Value,
+    },
+    /// The adapter asked for a host outside its `allowed_hosts`.
+    Denied { host: String },
+}
+
+/// Handle incoming webhook from a messaging platform.
+///
+/// 1. Look up channel adapter plugin by channel name
+/// 2. Call parse_incoming() to normalize the platform payload
+/// 3. Route through the agent loop
+/// 4. Collect the response
+/// 5. Call format_outgoing() to convert back to platform format
+/// 6. Return as HTTP response
+///
+/// In async mode (`[gateway.webhooks.<channel>] mode = "async"`) the request
+/// is acknowledged with 202 after step 2; the rest runs in the background and
+/// the reply goes out through the adapter's proxy call.
+///
+/// Signatures (`hmac`, `secret_token`) are checked before step 1, so an
+/// unsigned request never reaches the adapter. Only the IP limit is charged
+/// before the check; the channel limit counts signed deliveries, so a flood
+/// of forged requests cannot lock the platform out.
+pub async fn webhook_handler(
+    Path(channel): Path<String>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    // Platforms redeliver on 503, so a message that arrives now is not lost.
+    if state.shutdown.is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "server is shutting down").into_response();
+    }
+
+    let ip = remote.ip().to_string();
+    if let Err(limited) = state.rate_limit("webhook", &[(Dimension::Ip, ip.as_str())]) {
+        return too_many_requests(&limited);
+    }
+
+    if let Err(reason) = state.webhooks.verify(&channel, &headers, &body) {
+        warn!(channel = %channel, remote = %remote, "webhook rejected: {reason}");
+        crate::audit::record(
+            "webhook_rejected",
+            serde_json::json!({
+                "channel": channel,
+                "remote": ip,
+                "reason": reason,
+            }),
+        );
+        return (StatusCode::UNAUTHORIZED, "invalid webhook signature").into_response();
+    }
+    if let Err(limited) = state.rate_limit("webhook", &[(Dimension::Channel, channel.as_str())]) {
+        return too_many_requests(&limited);
+    }
+
+    // 1. Find channel adapter plugin
+    let adapter_name = {
+        let plugins = state.plugins.read().await;
+        plugins.find_channel_adapter(&channel).map(String::from)
+    };
+
+    let adapter_name = match adapter_name {
+        Some(name) => name,
+        None => {
+            warn!(channel = %channel, "no channel adapter found");
+            return (
+                StatusCode::NOT_FOUND,
+                format!("no channel adapter for '{channel}'"),
+            )
+                .into_response();
+        }
+    };
+
+    // 2. Parse incoming payload via WASM plugin
+    let parsed = {
+        let plugins = state.plugins.read().await;
+        plugins.call_channel_parse(&adapter_name, &body)
+    };
+
+    let parsed = match parsed {
+        Ok(v) => v,
+        Err(e) => {
+            warn!(channel = %channel, "parse_incoming failed: {e}");
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("parse_incoming failed: {e}"),
+            )
+                .into_response();
+        }
+    };
+    let incoming = match Incoming::from_parsed(&parsed) {
+        Ok(incoming) => incoming,
+        Err(e) => {
+            warn!(channel = %channel, "parse_incoming returned {e}");
+            return (StatusCode::BAD_REQUEST, e).into_response();
+        }
+    };
+
+    if incoming.content.is_empty() && incoming.attachments.is_empty() {
+        return (StatusCode::BAD_REQUEST, "empty message content".to_string()).into_response();
+    }
+    let gateway_config = &state.config.get().gateway;
+    let max_chars = gateway_config.max_content_chars;
+    if incoming.content.chars().count() > max_chars {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("message content exceeds {max_chars} characters"),
+        )
+            .into_response();
+    }
+    if let Err(e) = Attachment::validate_all(
+        &incoming.attachments,
+        gateway_config.max_attachments,
+        gateway_config.max_attachment_bytes,
+    ) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+
+    // 3. Route to agent, recording the session only once the call is admitted
+    let route = state.router.read().await.route(
+        &channel,
+        &incoming.account,
+        Some(&incoming.peer),
+        incoming.guild.as_deref(),
+        incoming.team.as_deref(),
+    );
+
+    let limits = [(Dimension::Session, route.session_key.as_str())];
+    if let Err(limited) = state.rate_limit("webhook", &limits) {
+        return too_many_requests(&limited);
+    }
+
+    let Some(stream_slot) = state.streams.try_take() else {
+        warn!(channel = %channel, "webhook rejected: too many streams");
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!(
+                "too many streams in flight (max_streams = {})",
+                state.streams.limit()
+            ),
+        )
+            .into_response();
+    };
+    state.router.write().await.open(&route);
+
+    let settings = state.config.get().gateway.webhook(&channel);
+    if settings.mode == WebhookMode::Async {
+        // Marked only once every check has passed, so a rejected request is
+        // still run when the platform retries it.
+        let ttl = Duration::from_secs(settings.dedup_ttl_secs);
+        let duplicate = incoming
+            .message_id
+            .as_deref()
+            .is_some_and(|id| !state.webhooks.first_delivery(&channel, id, ttl));
+        let status = if duplicate { "duplicate" } else { "accepted" };
+        let ack = serde_json::json!({ "status": status, "message_id": incoming.message_id });
+        if duplicate {
+            info!(channel = %channel, message_id = ?incoming.message_id, "dropping redelivered webhook");
+        } else {
+            let guard = state.shutdown.track();
+            tokio::spawn(async move {
+                let _guard = guard;
+                let _stream_slot = stream_slot;
+                run_async(state, channel, adapter_name, incoming, route, settings).await;
+            });
+        }
+        return (StatusCode::ACCEPTED, Json(ack)).into_response();
+    }
+
+    // 4. Run the agent and collect its reply
+    let response_text = match run_agent(&state, &channel, &incoming, &route).await {
+        Ok(text) => text,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+    drop(stream_slot);
+
+    // 5. Format outgoing via channel adapter plugin
+    let outgoing = match format_reply(&state, &adapter_name, &incoming, &response_text).await {
+        Ok(outgoing) => outgoing,
+        Err(e) => {
+            warn!(channel = %channel, "format_outgoing failed: {e}");
+            // Return raw text as fallback
+            return (StatusCode::OK, response_text).into_response();
+        }
+    };
+
+    // 6. HTTP proxy: if format_outgoing returned JSON with a "url" field,
+    //    the host makes the API call on behalf of the plugin (T045).
+    //    Plugin never sees API tokens — the host manages credentials.
+    match outgoing {
+        Outgoing::Direct(payload) => (
+            StatusCode::OK,
+            String::from_utf8_lossy(&payload).to_string(),
+        )
+            .into_response(),
+        Outgoing::Proxy { url, body } => match post(&url, &body).await {
+            Ok((status, body)) => {
+                info!(channel = %channel, url = %url, %status, "proxy call completed");
+                (StatusCode::OK, body).into_response()
+            }
+            Err(e) => {
+                warn!(channel = %channel, url = %url, "proxy call failed: {e}");
+                (StatusCode::BAD_GATEWAY, format!("proxy call failed: {e}")).into_response()
+            }
+        },
+        Outgoing::Denied { host } => (
+            StatusCode::FORBIDDEN,
+            format!("proxy denied: {host} not in allowed_hosts for adapter '{adapter_name}'"),
+        )
+            .into_response(),
+    }
+}
+
+/// Run the agent for one incoming message and return its full reply. The
+/// reply is saved to the session and memory.
+async fn run_agent(
+    state: &Arc<AppState>,
+    channel: &str,
+    incoming: &Incoming,
+    route: &RouteResult,
+) -> Result<String, String> {
+    let content = &incoming.content;
+    let user_message =
+        AgentMessage::with_attachments("user", content.clone(), incoming.attachments.clone());
+    // Webhook runs are not resumable; the id only ties frames together for
+    // session subscribers.
+    let run_id = format!("webhook-{}", uuid::Uuid::new_v4());
+
+    let session_lock = state.session_lock(&route.session_key).await;
+    let _session_guard = session_lock.lock().await;
+
+    // Get/create session and append user message
+    {
+        let mut store = state.store.write().await;
+        let session = store.get_or_create(&route.session_key, &route.agent_id);
+        session
+            .messages
+            .push(super::protocol::user_entry(content, &incoming.attachments));
+        session.message_count += 1;
+    }
+    state.hub.publish(
+        &route.session_key,
+        super::hub::user_message(&run_id, channel, &incoming.account, content),
+    );
+
+    // Build message history using memory engine context
+    let messages = {
+        let mut memory = state.memory.write().await;
+        let mut context = memory.assemble_context(&route.session_key, &route.agent_id, content);
+        context.push(user_message.clone());
+        context
+            .into_iter()
+            .filter_map(|m| m.as_provider_message())
+            .collect::<Vec<_>>()
+    };
+
+    // Create provider for the routed agent and run it, collecting the full response
+    let config = state.config.get();
+    let agent = config.agent_def(&route.agent_id);
+    let provider =
+        crate::agent::providers::from_config(agent).map_err(|e| format!("provider error: {e}"))?;
+
+    let tool_schemas = state.tool_schemas_for(agent).await;
+    let runner = state.runner_for(&config, agent, &route.session_key).await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<AgentEvent>(32);
+    let system_prompt = agent.system_prompt.clone();
+    let plugins = Arc::clone(&state.plugins);
+
+    // Spawn agent task
+    tokio::spawn(async move {
+        let result = runner
+            .run_with_tools(
+                provider.as_ref(),
+                messages,
+                &tool_schemas,
+                system_prompt.as_deref(),
+                &plugins,
+                tx.clone(),
+            )
+            .await;
+
+        if let Err(e) = result {
+            let _ = tx
+                .send(AgentEvent::Error(format!("agent error: {e}")))
+                .await;
+            let _ = tx.send(AgentEvent::Done).await;
+        }
+    });
+
+    let mut response_text = String::new();
+    while let Some(event) = rx.recv().await {
+        state.hub.publish(
+            &route.session_key,
+            StreamEvent::from(&event).to_frame(&run_id),
+        );
+        match event {
+            AgentEvent::Text(text) => response_text.push_str(&text),
+            AgentEvent::Done => break,
+            AgentEvent::Error(e) => return Err(format!("agent error: {e}")),
+            _ => {}
+        }
+    }
+
+    // Append assistant response to session
+    if !response_text.is_empty() {
+        let mut store = state.store.write().await;
+        if let Some(session) = store.get_mut(&route.session_key) {
+            session.messages.push(serde_json::json!({
+                "role": "assistant",
+                "content": response_text.clone(),
+            }));
+            session.message_count += 1;
+        }
+
+        let mut memory = state.memory.write().await;
+        let assistant_message = AgentMessage::text("assistant", response_text.clone());
+        memory.process_response(&route.session_key, &user_message, &assistant_message);
+    }
+
+    Ok(response_text)
+}
+
+/// Call `format_outgoing` and work out where the reply should go.
+async fn format_reply(
+    state: &AppState,
+    adapter_name: &str,
+    incoming: &Incoming,
+    reply: &str,
+) -> anyhow::Result<Outgoing> {
+    let plugins = state.plugins.read().await;
+    let payload = plugins.call_channel_format(adapter_name, &incoming.outgoing(reply))?;
+
+    let json: Option<serde_json::Value> = serde_json::from_slice(&payload).ok();
+    let Some(url) = json
+        .as_ref()
+        .and_then(|json| json.get("url"))
+        .and_then(|u| u.as_str())
+    else {
+        return Ok(Outgoing::Direct(payload));
+    };
+
+    // Validate against allowed_hosts capability
+    let host = url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(String::from));
+    let allowed = plugins.allowed_hosts(adapter_name);
+    match host {
+        Some(host) if allowed.contains(&host) => Ok(Outgoing::Proxy {
+            url: url.to_string(),
+            body: json
+                .as_ref()
+                .and_then(|json| json.get("body"))
+                .cloned()
+                .unwrap_or(serde_json::json!({ "text": reply })),
+        }),
+        host => {
+            let host = host.unwrap_or_else(|| "unknown".into());
+            warn!(
+                adapter = adapter_name,
+                url, "proxy denied: host not in allowed_hosts"
+            );
+            crate::audit::record(
+                "proxy_denied",
+                serde_json::json!({
+                    "adapter": adapter_name,
+                    "host": host,
+                    "peer": incoming.peer,
+                    "message_id": incoming.message_id,
+                }),
+            );
+            Ok(Outgoing::Denied { host })
+        }
+    }
+}
+
+/// One proxy POST. Returns the status and body of any response.
+async fn post(url: &str, body: &serde_json::Value) -> reqwest::Result<(StatusCode, String)> {
+    let resp = reqwest::Client::new().post(url).json(body).send().await?;
+    let status = StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    Ok((status, resp.text().await.unwrap_or_default()))
+}
+
+/// The background half of an async webhook: run the agent, then deliver the
+/// reply with retries. Failures end up in the dead-letter list.
+async fn run_async(
+    state: Arc<AppState>,
+    channel: String,
+    adapter_name: String,
+    incoming: Incoming,
+    route: RouteResult,
+    settings: WebhookConfig,
+) {
+    let dead_letter = |content: &str, url: Option<String>, attempts: u32, error: String| {
+        state.webhooks.dead_letter(DeadLetter {
+            channel: channel.clone(),
+            message_id: incoming.message_id.clone(),
+            session_key: route.session_key.clone(),
+            content: content.to_string(),
+            url,
+            attempts,
+            error,
+            failed_at: chrono::Utc::now(),
+        })
+    };
+
+    let reply = match run_agent(&state, &channel, &incoming, &route).await {
+        Ok(reply) => reply,
+        Err(e) => return dead_letter("", None, 0, e),
+    };
+
+    let (url, body) = match format_reply(&state, &adapter_name, &incoming, &reply).await {
+        Ok(Outgoing::Proxy { url, body }) => (url, body),
+        Ok(Outgoing::Direct(_)) => {
+            let error = "format_outgoing returned no url to deliver to".to_string();
+            return dead_letter(&reply, None, 0, error);
+        }
+        Ok(Outgoing::Denied { host }) => {
+            let error = format!("proxy denied: {host} not in allowed_hosts");
+            return dead_letter(&reply, None, 0, error);
+        }
+        Err(e) => return dead_letter(&reply, None, 0, format!("format_outgoing failed: {e}")),
+    };
+
+    let mut backoff = Duration::from_millis(settings.delivery_backoff_ms);
+    let mut attempt = 0;
+    let error = loop {
+        attempt += 1;
+        let error = match post(&url, &body).await {
+            Ok((status, _)) if status.is_success() => {
+                info!(channel = %channel, url = %url, attempt, "webhook reply delivered");
+                return;
+            }
+            // Other client errors will not get better with a retry.
+            Ok((status, body)) if is_permanent(status) => {
+                break format!("delivery rejected with {status}: {body}");
+            }
+            Ok((status, _)) => format!("delivery failed with {status}"),
+            Err(e) => format!("delivery failed: {e}"),
+        };
+        if attempt >= settings.delivery_attempts {
+            break error;
+        }
+        warn!(channel = %channel, url = %url, attempt, "{error}; retrying in {backoff:?}");
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    };
+    dead_letter(&reply, Some(url), attempt, error);
+}
+
+fn is_permanent(status: StatusCode) -> bool {
+    status.is_client_error()
+        && status != StatusCode::TOO_MANY_REQUESTS
+        && status != StatusCode::REQUEST_TIMEOUT
+}
+
+/// 429 with `Retry-After` for a webhook that hit a rate limit.
+fn too_many_requests(limited: &RateLimited) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, limited.retry_after_secs().to_string())],
+        limited.to_string(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redeliveries_are_dropped_until_ttl() {
+        let state = WebhookState::default();
+        let ttl = Duration::from_secs(60);
+        assert!(state.first_delivery("telegram", "42", ttl));
+        assert!(!state.first_delivery("telegram", "42", ttl));
+        assert!(state.first_delivery("slack", "42", ttl));
+        assert!(state.first_delivery("telegram", "43", Duration::ZERO));
+        assert!(state.first_delivery("telegram", "43", Duration::ZERO));
+    }
+}
@@ -1,9 +1,11 @@
 pub mod agent;
+pub mod audit;
 pub mod bus;
 pub mod config;
 pub mod fs_util;
 pub mod gateway;
 pub mod memory;
+pub mod metrics;
 pub mod router;
 pub mod sandbox;
 pub mod secrets;
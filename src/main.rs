@@ -34,6 +34,18 @@ enum Commands {
         /// Auth token (required for non-loopback)
         #[arg(long, env = "EXOCLAW_TOKEN")]
         token: Option<String>,
+
+        /// Token file with `active`/`next` tokens, re-read on SIGHUP (overrides config file)
+        #[arg(long, env = "EXOCLAW_TOKEN_FILE")]
+        token_file: Option<String>,
+
+        /// Bearer token for the /metrics endpoint (overrides config file)
+        #[arg(long, env = "EXOCLAW_METRICS_TOKEN")]
+        metrics_token: Option<String>,
+
+        /// Serve HTTPS/WSS with an ephemeral self-signed certificate (local testing only)
+        #[arg(long)]
+        self_signed: bool,
     },
 
     /// Manage plugins
@@ -42,10 +54,62 @@ enum Commands {
         action: PluginAction,
     },
 
+    /// Manage secrets in the credentials store
+    Secret {
+        #[command(subcommand)]
+        action: SecretAction,
+    },
+
+    /// Inspect the security audit log
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+
     /// Show runtime status
     Status,
 }
 
+#[derive(Subcommand)]
+enum AuditAction {
+    /// Print the latest audit events, oldest first
+    Tail {
+        /// Number of events to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+        /// Only this event kind, e.g. auth_failed (repeatable)
+        #[arg(long = "kind")]
+        kinds: Vec<String>,
+        /// Only events from this remote address (prefix match)
+        #[arg(long)]
+        remote: Option<String>,
+        /// Only events for this method, e.g. connect or chat.send
+        #[arg(long)]
+        method: Option<String>,
+        /// Only events for this session key
+        #[arg(long)]
+        session: Option<String>,
+        /// Only events newer than this: RFC 3339, or an age like 30m, 2h, 7d
+        #[arg(long)]
+        since: Option<String>,
+        /// Keep printing events as they are written
+        #[arg(short, long)]
+        follow: bool,
+        /// Audit log to read (overrides [audit] path in the config file)
+        #[arg(long)]
+        path: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretAction {
+    /// Store a named secret (e.g. a webhook signing secret); prompts for the value
+    Set {
+        /// Secret name, referenced from config (lowercase letters, digits, '-' and '_')
+        name: String,
+    },
+}
+
 #[derive(Subcommand)]
 enum PluginAction {
     /// List loaded plugins
@@ -68,7 +132,14 @@ async fn main() -> anyhow::Result<()> {
 
     match cli.command {
         Commands::Onboard { provider } => run_onboard(provider),
-        Commands::Gateway { port, bind, token } => {
+        Commands::Gateway {
+            port,
+            bind,
+            token,
+            token_file,
+            metrics_token,
+            self_signed,
+        } => {
             let mut config = exoclaw::config::load()?;
 
             // CLI args override config file
@@ -78,10 +149,23 @@ async fn main() -> anyhow::Result<()> {
             if let Some(b) = bind {
                 config.gateway.bind = b;
             }
+            if let Some(f) = token_file {
+                config.gateway.token_file = Some(f);
+            }
+            if let Some(t) = metrics_token {
+                config.gateway.metrics_token = Some(exoclaw::config::Secret::new(t));
+            }
+            if self_signed {
+                config.gateway.tls = Some(exoclaw::config::TlsConfig {
+                    self_signed: true,
+                    ..Default::default()
+                });
+            }
 
             info!(
                 provider = %config.agent.provider,
                 model = %config.agent.model,
+                agents = config.agents.len(),
                 plugins = config.plugins.len(),
                 bindings = config.bindings.len(),
                 "config loaded"
@@ -96,6 +180,39 @@ async fn main() -> anyhow::Result<()> {
             }
             PluginAction::Load { path } => exoclaw::sandbox::load_plugin(&path).await,
         },
+        Commands::Secret { action } => match action {
+            SecretAction::Set { name } => {
+                let value = rpassword::prompt_password(format!("Value for secret '{name}': "))?;
+                let path = exoclaw::secrets::store_secret(&name, &value)?;
+                println!("Saved secret to {}", path.display());
+                Ok(())
+            }
+        },
+        Commands::Audit {
+            action:
+                AuditAction::Tail {
+                    lines,
+                    kinds,
+                    remote,
+                    method,
+                    session,
+                    since,
+                    follow,
+                    path,
+                },
+        } => {
+            let filter = exoclaw::audit::Filter {
+                kinds,
+                remote,
+                method,
+                session_key: session,
+                since: since
+                    .as_deref()
+                    .map(exoclaw::audit::parse_since)
+                    .transpose()?,
+            };
+            run_audit_tail(path, &filter, lines, follow).await
+        }
         Commands::Status => {
             println!("exoclaw v{}", env!("CARGO_PKG_VERSION"));
             println!("status: idle");
@@ -142,6 +259,36 @@ fn run_onboard(provider_arg: Option<String>) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn run_audit_tail(
+    path: Option<String>,
+    filter: &exoclaw::audit::Filter,
+    lines: usize,
+    follow: bool,
+) -> anyhow::Result<()> {
+    let path = match path {
+        Some(path) => path,
+        None => exoclaw::config::load()?.audit.path.ok_or_else(|| {
+            anyhow::anyhow!("no audit log configured: set [audit] path or pass --path")
+        })?,
+    };
+    let path = std::path::Path::new(&path);
+
+    // The follower picks up where the tail stopped, so no line is printed twice.
+    let (backlog, mut follower) = exoclaw::audit::tail_and_follow(path, filter, lines)?;
+    for line in backlog {
+        println!("{line}");
+    }
+    if !follow {
+        return Ok(());
+    }
+    loop {
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        for line in follower.poll(filter)? {
+            println!("{line}");
+        }
+    }
+}
+
 fn resolve_provider(input: Option<String>, current: &str) -> anyhow::Result<String> {
     let provider = match input {
         Some(value) => value,
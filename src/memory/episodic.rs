@@ -50,6 +50,11 @@ impl EpisodicMemory {
         self.sessions.get(session_key).cloned().unwrap_or_default()
     }
 
+    /// Drop the window for a session.
+    pub fn clear(&mut self, session_key: &str) {
+        self.sessions.remove(session_key);
+    }
+
     /// Get the configured window size in turns.
     pub fn window_size(&self) -> usize {
         self.window_turns
@@ -107,12 +107,15 @@ impl MemoryEngine {
         user_message: &Message,
         assistant_message: &Message,
     ) {
-        // Append both messages to episodic memory
-        self.episodic.append(session_key, user_message.clone());
+        // Append both messages to episodic memory, without attachment bytes
+        self.episodic
+            .append(session_key, user_message.without_inline_data());
         self.episodic.append(session_key, assistant_message.clone());
 
         // Extract entities from the user message (user states facts about themselves)
-        if let MessageContent::Text { ref text } = user_message.content {
+        if let MessageContent::Text { ref text } | MessageContent::Attachments { ref text, .. } =
+            user_message.content
+        {
             let entities = extract_entities(text, session_key);
             for entity in entities {
                 self.semantic.store(entity);
@@ -128,6 +131,11 @@ impl MemoryEngine {
         }
     }
 
+    /// Forget a session's recent turns so its next context starts fresh.
+    pub fn clear_session(&mut self, session_key: &str) {
+        self.episodic.clear(session_key);
+    }
+
     /// Append a single message to episodic memory without entity extraction.
     pub fn append_to_episodic(&mut self, session_key: &str, message: Message) {
         self.episodic.append(session_key, message);
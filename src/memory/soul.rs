@@ -14,6 +14,31 @@ pub struct Soul {
     file_mtime: Option<std::time::SystemTime>,
 }
 
+impl Soul {
+    /// Read a soul document without adding it to a loader.
+    pub fn read(agent_id: &str, path: &str) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read soul file {path}: {e}"))?;
+
+        let mtime = Path::new(path)
+            .metadata()
+            .ok()
+            .and_then(|m| m.modified().ok());
+        let token_count = estimate_tokens(&content);
+
+        info!(agent_id, path, token_count, "loaded soul document");
+
+        Ok(Self {
+            agent_id: agent_id.to_string(),
+            content,
+            token_count,
+            loaded_from: path.to_string(),
+            loaded_at: Utc::now(),
+            file_mtime: mtime,
+        })
+    }
+}
+
 /// Loads and caches soul documents from the filesystem.
 /// Supports hot-reload by checking file mtime on access.
 pub struct SoulLoader {
@@ -35,30 +60,19 @@ impl SoulLoader {
 
     /// Load a soul document from a file path for the given agent.
     pub fn load(&mut self, agent_id: &str, path: &str) -> anyhow::Result<&Soul> {
-        let content = std::fs::read_to_string(path)
-            .map_err(|e| anyhow::anyhow!("failed to read soul file {path}: {e}"))?;
-
-        let mtime = Path::new(path)
-            .metadata()
-            .ok()
-            .and_then(|m| m.modified().ok());
-        let token_count = estimate_tokens(&content);
-
-        let soul = Soul {
-            agent_id: agent_id.to_string(),
-            content,
-            token_count,
-            loaded_from: path.to_string(),
-            loaded_at: Utc::now(),
-            file_mtime: mtime,
-        };
-
-        info!(agent_id, path, token_count, "loaded soul document");
-
+        let soul = Soul::read(agent_id, path)?;
         self.souls.insert(agent_id.to_string(), soul);
         Ok(self.souls.get(agent_id).unwrap())
     }
 
+    /// Replace every loaded soul, e.g. after a config reload.
+    pub fn replace_all(&mut self, souls: Vec<Soul>) {
+        self.souls = souls
+            .into_iter()
+            .map(|soul| (soul.agent_id.clone(), soul))
+            .collect();
+    }
+
     /// Get the soul document for an agent, hot-reloading if the file changed.
     pub fn get(&mut self, agent_id: &str) -> Option<&Soul> {
         // Check if reload is needed
@@ -0,0 +1,260 @@
+//! Prometheus metrics.
+//!
+//! Counters and histograms live in one process-wide registry so the agent,
+//! sandbox and gateway can record without sharing state. Gauges (connections,
+//! sessions) are read from the gateway at scrape time and passed to
+//! [`render`].
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+pub const RPC_REQUESTS: &str = "exoclaw_rpc_requests_total";
+pub const PROVIDER_REQUEST_SECONDS: &str = "exoclaw_provider_request_duration_seconds";
+pub const PROVIDER_ERRORS: &str = "exoclaw_provider_errors_total";
+pub const TOOL_CALL_SECONDS: &str = "exoclaw_tool_call_duration_seconds";
+pub const TOOL_TRAPS: &str = "exoclaw_tool_traps_total";
+pub const TOKENS: &str = "exoclaw_tokens_total";
+pub const COST_USD: &str = "exoclaw_cost_usd_total";
+pub const BUDGET_REJECTIONS: &str = "exoclaw_budget_rejections_total";
+
+/// Upper bounds for duration histograms, in seconds. Provider calls stream
+/// for tens of seconds; tool calls usually take milliseconds.
+const BUCKETS: [f64; 13] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Counter,
+    Histogram,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Histogram => "histogram",
+        }
+    }
+}
+
+/// Every recorded family, in exposition order.
+const FAMILIES: [(&str, Kind, &str); 8] = [
+    (
+        RPC_REQUESTS,
+        Kind::Counter,
+        "JSON-RPC calls by method and outcome.",
+    ),
+    (
+        PROVIDER_REQUEST_SECONDS,
+        Kind::Histogram,
+        "LLM provider call duration, including streaming.",
+    ),
+    (
+        PROVIDER_ERRORS,
+        Kind::Counter,
+        "LLM provider calls that failed.",
+    ),
+    (
+        TOOL_CALL_SECONDS,
+        Kind::Histogram,
+        "WASM tool call duration by plugin.",
+    ),
+    (
+        TOOL_TRAPS,
+        Kind::Counter,
+        "WASM tool calls that trapped or failed to run.",
+    ),
+    (
+        TOKENS,
+        Kind::Counter,
+        "Tokens metered by provider and model.",
+    ),
+    (
+        COST_USD,
+        Kind::Counter,
+        "Estimated spend in USD by provider and model.",
+    ),
+    (
+        BUDGET_REJECTIONS,
+        Kind::Counter,
+        "Requests refused by a token budget, by scope.",
+    ),
+];
+
+type Labels = Vec<(&'static str, String)>;
+
+enum Series {
+    Counter(f64),
+    Histogram {
+        /// Per-bucket (not cumulative) counts; the last slot is `+Inf`.
+        counts: [u64; BUCKETS.len() + 1],
+        sum: f64,
+    },
+}
+
+static REGISTRY: LazyLock<Mutex<BTreeMap<(&'static str, Labels), Series>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+fn key(name: &'static str, labels: &[(&'static str, &str)]) -> (&'static str, Labels) {
+    let labels = labels.iter().map(|(k, v)| (*k, v.to_string())).collect();
+    (name, labels)
+}
+
+/// Add one to a counter.
+pub fn inc(name: &'static str, labels: &[(&'static str, &str)]) {
+    add(name, labels, 1.0);
+}
+
+/// Add `value` to a counter.
+pub fn add(name: &'static str, labels: &[(&'static str, &str)], value: f64) {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let series = registry
+        .entry(key(name, labels))
+        .or_insert(Series::Counter(0.0));
+    if let Series::Counter(total) = series {
+        *total += value;
+    }
+}
+
+/// Record a duration in a histogram.
+pub fn observe(name: &'static str, labels: &[(&'static str, &str)], elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64();
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let series = registry
+        .entry(key(name, labels))
+        .or_insert(Series::Histogram {
+            counts: [0; BUCKETS.len() + 1],
+            sum: 0.0,
+        });
+    if let Series::Histogram { counts, sum } = series {
+        let bucket = BUCKETS
+            .iter()
+            .position(|le| seconds <= *le)
+            .unwrap_or(BUCKETS.len());
+        counts[bucket] += 1;
+        *sum += seconds;
+    }
+}
+
+/// A value sampled at scrape time.
+pub struct Gauge {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub labels: Vec<(&'static str, String)>,
+    pub value: f64,
+}
+
+impl Gauge {
+    pub fn new(name: &'static str, help: &'static str, value: f64) -> Self {
+        Self {
+            name,
+            help,
+            labels: Vec::new(),
+            value,
+        }
+    }
+
+    pub fn with_label(mut self, key: &'static str, value: impl Into<String>) -> Self {
+        self.labels.push((key, value.into()));
+        self
+    }
+}
+
+/// The Prometheus text exposition of every recorded series plus `gauges`.
+pub fn render(gauges: &[Gauge]) -> String {
+    let mut out = String::new();
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+
+    for (family, kind, help) in FAMILIES {
+        let _ = writeln!(out, "# HELP {family} {help}");
+        let _ = writeln!(out, "# TYPE {family} {}", kind.as_str());
+        for ((_, labels), series) in registry.iter().filter(|((name, _), _)| *name == family) {
+            match series {
+                Series::Counter(total) => {
+                    let _ = writeln!(out, "{family}{} {total}", format_labels(labels, None));
+                }
+                Series::Histogram { counts, sum } => {
+                    let mut cumulative = 0;
+                    for (i, count) in counts.iter().enumerate() {
+                        cumulative += count;
+                        let le = BUCKETS.get(i).map_or("+Inf".to_string(), |b| b.to_string());
+                        let labels = format_labels(labels, Some(&le));
+                        let _ = writeln!(out, "{family}_bucket{labels} {cumulative}");
+                    }
+                    let labels = format_labels(labels, None);
+                    let _ = writeln!(out, "{family}_sum{labels} {sum}");
+                    let _ = writeln!(out, "{family}_count{labels} {cumulative}");
+                }
+            }
+        }
+    }
+    drop(registry);
+
+    let mut described = Vec::new();
+    for gauge in gauges {
+        if !described.contains(&gauge.name) {
+            let _ = writeln!(out, "# HELP {} {}", gauge.name, gauge.help);
+            let _ = writeln!(out, "# TYPE {} gauge", gauge.name);
+            described.push(gauge.name);
+        }
+        let labels = format_labels(&gauge.labels, None);
+        let _ = writeln!(out, "{}{labels} {}", gauge.name, gauge.value);
+    }
+    out
+}
+
+fn format_labels(labels: &[(&'static str, String)], le: Option<&str>) -> String {
+    let pairs = labels
+        .iter()
+        .map(|(k, v)| (*k, v.as_str()))
+        .chain(le.map(|le| ("le", le)));
+    let rendered: Vec<String> = pairs
+        .map(|(k, v)| {
+            let v = v
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{k}=\"{v}\"")
+        })
+        .collect();
+    if rendered.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", rendered.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters_histograms_and_gauges() {
+        inc(BUDGET_REJECTIONS, &[("scope", "test\"quoted")]);
+        inc(BUDGET_REJECTIONS, &[("scope", "test\"quoted")]);
+        observe(
+            TOOL_CALL_SECONDS,
+            &[("plugin", "metrics-test")],
+            Duration::from_millis(20),
+        );
+        let text =
+            render(&[Gauge::new("exoclaw_test_gauge", "A gauge.", 3.0).with_label("kind", "x")]);
+
+        assert!(text.contains("# TYPE exoclaw_budget_rejections_total counter"));
+        assert!(text.contains(r#"exoclaw_budget_rejections_total{scope="test\"quoted"} 2"#));
+        let bucket =
+            r#"exoclaw_tool_call_duration_seconds_bucket{plugin="metrics-test",le="0.01"} 0"#;
+        assert!(text.contains(bucket), "{text}");
+        let bucket =
+            r#"exoclaw_tool_call_duration_seconds_bucket{plugin="metrics-test",le="0.025"} 1"#;
+        assert!(text.contains(bucket), "{text}");
+        assert!(
+            text.contains(r#"exoclaw_tool_call_duration_seconds_count{plugin="metrics-test"} 1"#)
+        );
+        assert!(text.contains("# TYPE exoclaw_test_gauge gauge"));
+        assert!(text.contains(r#"exoclaw_test_gauge{kind="x"} 3"#));
+    }
+}
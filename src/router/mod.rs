@@ -54,6 +54,13 @@ impl SessionRouter {
         self.bindings.push(binding);
     }
 
+    /// Replace the bindings and default agent, keeping known sessions.
+    pub fn set_bindings(&mut self, default_agent: &str, bindings: Vec<Binding>) {
+        self.default_agent = default_agent.into();
+        self.bindings = bindings;
+    }
+
+    /// Resolve a route and record the session as opened.
     pub fn resolve(
         &mut self,
         channel: &str,
@@ -61,6 +68,21 @@ impl SessionRouter {
         peer: Option<&str>,
         guild: Option<&str>,
         team: Option<&str>,
+    ) -> RouteResult {
+        let route = self.route(channel, account, peer, guild, team);
+        self.open(&route);
+        route
+    }
+
+    /// Resolve a route without touching session state, so callers can check
+    /// limits on the session key before committing to it with [`Self::open`].
+    pub fn route(
+        &self,
+        channel: &str,
+        account: &str,
+        peer: Option<&str>,
+        guild: Option<&str>,
+        team: Option<&str>,
     ) -> RouteResult {
         // Priority: peer > guild > team > account > channel > default
         let (agent_id, matched_by) = self
@@ -118,21 +140,49 @@ impl SessionRouter {
             })
             .unwrap_or((&self.default_agent, "default"));
 
-        let session_key = format!("{agent_id}:{channel}:{account}:{}", peer.unwrap_or("main"));
+        route_to(agent_id.clone(), channel, account, peer, matched_by)
+    }
+
+    /// Route straight to `agent_id`, skipping binding resolution. Like
+    /// [`Self::route`], this leaves session state to [`Self::open`].
+    pub fn route_agent(
+        agent_id: &str,
+        channel: &str,
+        account: &str,
+        peer: Option<&str>,
+    ) -> RouteResult {
+        route_to(agent_id.to_string(), channel, account, peer, "explicit")
+    }
 
+    /// Record a message on a routed session, creating its state if needed.
+    pub fn open(&mut self, route: &RouteResult) {
         self.sessions
-            .entry(session_key.clone())
+            .entry(route.session_key.clone())
             .and_modify(|s| s.message_count += 1)
             .or_insert(SessionState { message_count: 1 });
-
-        RouteResult {
-            agent_id: agent_id.clone(),
-            session_key,
-            matched_by,
-        }
     }
 
     pub fn session_count(&self) -> usize {
         self.sessions.len()
     }
+
+    /// Forget routing state for a session. Returns false if it was unknown.
+    pub fn remove_session(&mut self, session_key: &str) -> bool {
+        self.sessions.remove(session_key).is_some()
+    }
+}
+
+fn route_to(
+    agent_id: String,
+    channel: &str,
+    account: &str,
+    peer: Option<&str>,
+    matched_by: &'static str,
+) -> RouteResult {
+    let session_key = format!("{agent_id}:{channel}:{account}:{}", peer.unwrap_or("main"));
+    RouteResult {
+        agent_id,
+        session_key,
+        matched_by,
+    }
 }
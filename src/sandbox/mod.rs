@@ -19,7 +19,8 @@ pub struct PluginHost {
 }
 
 /// Whether a plugin is a tool (handle_tool_call) or a channel adapter.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PluginType {
     Tool,
     ChannelAdapter,
@@ -27,11 +28,15 @@ pub enum PluginType {
 
 struct PluginEntry {
     name: String,
+    /// The `.wasm` file, re-read on reload.
+    path: String,
     manifest: Manifest,
     plugin_type: PluginType,
     capabilities: Vec<Capability>,
     /// Tool schema from the plugin's `describe()` export, if available.
     tool_schema: Option<serde_json::Value>,
+    /// Set by `plugin.load`; `[[plugins]]` entries carry it in the config.
+    require_approval: bool,
 }
 
 #[derive(Serialize)]
@@ -39,6 +44,36 @@ pub struct PluginInfo {
     pub name: String,
 }
 
+/// Everything known about a loaded plugin, for `plugin.inspect`.
+#[derive(Debug, Serialize)]
+pub struct PluginDetails {
+    pub name: String,
+    pub path: String,
+    pub plugin_type: PluginType,
+    pub capabilities: Vec<String>,
+    pub allowed_hosts: Vec<String>,
+    pub tool_schema: Option<serde_json::Value>,
+    pub require_approval: bool,
+}
+
+/// A compiled and validated plugin, ready for [`PluginHost::install`].
+///
+/// Preparing is the slow part (compiling the module), so it can happen
+/// without holding a lock on the host.
+pub struct PreparedPlugin(PluginEntry);
+
+impl PreparedPlugin {
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// Hold the plugin's tool calls until a client approves them.
+    pub fn require_approval(mut self, required: bool) -> Self {
+        self.0.require_approval = required;
+        self
+    }
+}
+
 /// Result of a tool call invocation.
 #[derive(Debug)]
 pub struct ToolCallResult {
@@ -88,6 +123,19 @@ impl PluginHost {
             .collect()
     }
 
+    /// Get tool schemas limited to an agent's `tools` allowlist.
+    /// An empty allowlist exposes every loaded tool.
+    pub fn tool_schemas_for(&self, allowed: &[String]) -> Vec<serde_json::Value> {
+        if allowed.is_empty() {
+            return self.tool_schemas();
+        }
+        self.plugins
+            .values()
+            .filter(|p| p.plugin_type == PluginType::Tool && allowed.contains(&p.name))
+            .filter_map(|p| p.tool_schema.clone())
+            .collect()
+    }
+
     /// Register a WASM plugin from a file path with capabilities.
     pub fn register(
         &mut self,
@@ -95,6 +143,69 @@ impl PluginHost {
         wasm_path: &str,
         caps: Vec<Capability>,
     ) -> anyhow::Result<()> {
+        let plugin = Self::prepare(name, wasm_path, caps)?;
+        self.install(plugin);
+        Ok(())
+    }
+
+    /// Install a prepared plugin, replacing any plugin with the same name.
+    pub fn install(&mut self, plugin: PreparedPlugin) {
+        let entry = plugin.0;
+        info!("plugin loaded: {} ({})", entry.name, entry.path);
+        self.plugins.insert(entry.name.clone(), entry);
+    }
+
+    /// Remove a plugin. Returns false if it was not loaded.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        let removed = self.plugins.remove(name).is_some();
+        if removed {
+            info!("plugin unloaded: {name}");
+        }
+        removed
+    }
+
+    /// Details of a loaded plugin.
+    pub fn inspect(&self, name: &str) -> Option<PluginDetails> {
+        self.plugins.get(name).map(|entry| PluginDetails {
+            name: entry.name.clone(),
+            path: entry.path.clone(),
+            plugin_type: entry.plugin_type.clone(),
+            capabilities: entry.capabilities.iter().map(|c| c.to_string()).collect(),
+            allowed_hosts: capabilities::allowed_hosts(&entry.capabilities),
+            tool_schema: entry.tool_schema.clone(),
+            require_approval: entry.require_approval,
+        })
+    }
+
+    /// Whether a plugin was loaded with `require_approval`.
+    pub fn requires_approval(&self, name: &str) -> bool {
+        self.plugins
+            .get(name)
+            .is_some_and(|entry| entry.require_approval)
+    }
+
+    /// Plugins loaded with `require_approval`.
+    pub fn approval_tools(&self) -> Vec<String> {
+        self.plugins
+            .values()
+            .filter(|entry| entry.require_approval)
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    /// Path and capabilities a plugin was loaded with, for reloading it.
+    pub fn source(&self, name: &str) -> Option<(String, Vec<Capability>)> {
+        self.plugins
+            .get(name)
+            .map(|entry| (entry.path.clone(), entry.capabilities.clone()))
+    }
+
+    /// Compile and validate a plugin without installing it.
+    pub fn prepare(
+        name: &str,
+        wasm_path: &str,
+        caps: Vec<Capability>,
+    ) -> anyhow::Result<PreparedPlugin> {
         let path = Path::new(wasm_path);
         anyhow::ensure!(path.exists(), "plugin file not found: {wasm_path}");
 
@@ -116,19 +227,15 @@ impl PluginHost {
         // Detect plugin type and extract tool schema
         let (plugin_type, tool_schema) = detect_plugin_type(&mut plugin);
 
-        self.plugins.insert(
-            name.into(),
-            PluginEntry {
-                name: name.into(),
-                manifest,
-                plugin_type,
-                capabilities: caps,
-                tool_schema,
-            },
-        );
-
-        info!("plugin loaded: {name} ({wasm_path})");
-        Ok(())
+        Ok(PreparedPlugin(PluginEntry {
+            name: name.into(),
+            path: wasm_path.into(),
+            manifest,
+            plugin_type,
+            capabilities: caps,
+            tool_schema,
+            require_approval: false,
+        }))
     }
 
     /// Call a function on a loaded plugin.
@@ -162,9 +269,26 @@ impl PluginHost {
             }
         };
 
-        let output = match self.call(plugin_name, "handle_tool_call", &input_bytes) {
+        let started = std::time::Instant::now();
+        let output = self.call(plugin_name, "handle_tool_call", &input_bytes);
+        let labels = [("plugin", plugin_name)];
+        crate::metrics::observe(
+            crate::metrics::TOOL_CALL_SECONDS,
+            &labels,
+            started.elapsed(),
+        );
+        let output = match output {
             Ok(bytes) => bytes,
             Err(e) => {
+                crate::metrics::inc(crate::metrics::TOOL_TRAPS, &labels);
+                crate::audit::record(
+                    "plugin_trap",
+                    serde_json::json!({
+                        "plugin": plugin_name,
+                        "function": "handle_tool_call",
+                        "error": e.to_string(),
+                    }),
+                );
                 return ToolCallResult {
                     content: format!("tool execution failed: {e}"),
                     is_error: true,
@@ -203,7 +327,8 @@ impl PluginHost {
 
     /// Call a channel adapter's `parse_incoming` to convert platform payload to normalized message.
     ///
-    /// Returns JSON with at minimum `{ "content": "...", "account": "...", "peer": "..." }`.
+    /// Returns JSON with at minimum `{ "content": "...", "account": "...", "peer": "..." }`,
+    /// plus optional `attachments` for platform media, in the `chat.send` format.
     /// Creates a fresh Plugin instance per invocation for isolation.
     pub fn call_channel_parse(
         &self,
@@ -34,26 +34,29 @@ fn key_file_path_for(state_dir: &Path, provider: &str) -> anyhow::Result<PathBuf
     Ok(credentials_dir_for(state_dir).join(format!("{provider}.key")))
 }
 
-pub fn write_key_to(state_dir: &Path, provider: &str, api_key: &str) -> anyhow::Result<PathBuf> {
-    let api_key = api_key.trim();
-    if api_key.is_empty() {
-        anyhow::bail!("API key cannot be empty");
+fn secret_file_path_for(state_dir: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        anyhow::bail!("invalid secret name '{name}': use lowercase letters, digits, '-' and '_'");
     }
+    Ok(credentials_dir_for(state_dir).join(format!("{name}.secret")))
+}
 
+fn write_credential(state_dir: &Path, path: &Path, value: &str) -> anyhow::Result<()> {
     let dir = credentials_dir_for(state_dir);
     std::fs::create_dir_all(&dir)
         .map_err(|e| anyhow::anyhow!("failed to create {}: {e}", dir.display()))?;
     set_secure_dir_permissions(&dir)?;
 
-    let path = key_file_path_for(state_dir, provider)?;
-    std::fs::write(&path, api_key)
+    std::fs::write(path, value)
         .map_err(|e| anyhow::anyhow!("failed to write {}: {e}", path.display()))?;
-    set_secure_file_permissions(&path)?;
-    Ok(path)
+    set_secure_file_permissions(path)
 }
 
-pub fn read_key_from(state_dir: &Path, provider: &str) -> Option<String> {
-    let path = key_file_path_for(state_dir, provider).ok()?;
+fn read_credential(path: &Path) -> Option<String> {
     let value = std::fs::read_to_string(path).ok()?;
     let value = value.trim();
     if value.is_empty() {
@@ -63,6 +66,36 @@ pub fn read_key_from(state_dir: &Path, provider: &str) -> Option<String> {
     }
 }
 
+pub fn write_key_to(state_dir: &Path, provider: &str, api_key: &str) -> anyhow::Result<PathBuf> {
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        anyhow::bail!("API key cannot be empty");
+    }
+
+    let path = key_file_path_for(state_dir, provider)?;
+    write_credential(state_dir, &path, api_key)?;
+    Ok(path)
+}
+
+pub fn read_key_from(state_dir: &Path, provider: &str) -> Option<String> {
+    read_credential(&key_file_path_for(state_dir, provider).ok()?)
+}
+
+pub fn write_secret_to(state_dir: &Path, name: &str, secret: &str) -> anyhow::Result<PathBuf> {
+    let secret = secret.trim();
+    if secret.is_empty() {
+        anyhow::bail!("secret cannot be empty");
+    }
+
+    let path = secret_file_path_for(state_dir, name)?;
+    write_credential(state_dir, &path, secret)?;
+    Ok(path)
+}
+
+pub fn read_secret_from(state_dir: &Path, name: &str) -> Option<String> {
+    read_credential(&secret_file_path_for(state_dir, name).ok()?)
+}
+
 /// Store a provider API key in ~/.exoclaw/credentials/{provider}.key.
 pub fn store_api_key(provider: &str, api_key: &str) -> anyhow::Result<PathBuf> {
     write_key_to(&state_dir(), provider, api_key)
@@ -73,9 +106,20 @@ pub fn load_api_key(provider: &str) -> Option<String> {
     read_key_from(&state_dir(), provider)
 }
 
+/// Store a named secret (e.g. a webhook signing secret) in
+/// ~/.exoclaw/credentials/{name}.secret.
+pub fn store_secret(name: &str, secret: &str) -> anyhow::Result<PathBuf> {
+    write_secret_to(&state_dir(), name, secret)
+}
+
+/// Load a named secret from ~/.exoclaw/credentials/{name}.secret.
+pub fn load_secret(name: &str) -> Option<String> {
+    read_secret_from(&state_dir(), name)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{read_key_from, write_key_to};
+    use super::{read_key_from, read_secret_from, write_key_to, write_secret_to};
     use std::path::PathBuf;
 
     fn tmp_dir() -> PathBuf {
@@ -105,4 +149,16 @@ mod tests {
         assert!(err.to_string().contains("unsupported provider"));
         std::fs::remove_dir_all(dir).ok();
     }
+
+    #[test]
+    fn writes_and_reads_named_secret() {
+        let dir = tmp_dir();
+        write_secret_to(&dir, "github-webhook", "s3cret\n").expect("write secret");
+        let loaded = read_secret_from(&dir, "github-webhook");
+        assert_eq!(loaded.as_deref(), Some("s3cret"));
+        assert!(read_secret_from(&dir, "missing").is_none());
+        let err = write_secret_to(&dir, "../escape", "x").expect_err("should fail");
+        assert!(err.to_string().contains("invalid secret name"));
+        std::fs::remove_dir_all(dir).ok();
+    }
 }
@@ -54,6 +54,31 @@ impl SessionStore {
         self.sessions.len()
     }
 
+    /// All sessions, ordered by creation time (oldest first).
+    pub fn list(&self) -> Vec<&Session> {
+        let mut sessions: Vec<&Session> = self.sessions.values().collect();
+        sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.key.cmp(&b.key)));
+        sessions
+    }
+
+    /// Clear a session's history but keep the session itself.
+    /// Returns false if the session does not exist.
+    pub fn reset(&mut self, key: &str) -> bool {
+        match self.sessions.get_mut(key) {
+            Some(session) => {
+                session.messages.clear();
+                session.message_count = 0;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a session and its history.
+    pub fn remove(&mut self, key: &str) -> Option<Session> {
+        self.sessions.remove(key)
+    }
+
     pub fn sessions_mut(&mut self) -> &mut HashMap<String, Session> {
         &mut self.sessions
     }
@@ -1,3 +1,4 @@
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 
 /// A message in a conversation. Used for episodic memory and LLM context.
@@ -10,13 +11,19 @@ pub struct Message {
     pub token_count: Option<u32>,
 }
 
-/// Content of a message — text, tool use request, or tool result.
+/// Content of a message — text, text with attachments, tool use request, or
+/// tool result.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum MessageContent {
     Text {
         text: String,
     },
+    /// User text with images or documents attached.
+    Attachments {
+        text: String,
+        attachments: Vec<Attachment>,
+    },
     ToolUse {
         id: String,
         name: String,
@@ -41,6 +48,48 @@ impl Message {
         }
     }
 
+    /// Create a text message carrying attachments, or a plain text message
+    /// if there are none.
+    pub fn with_attachments(
+        role: &str,
+        text: impl Into<String>,
+        attachments: Vec<Attachment>,
+    ) -> Self {
+        let text = text.into();
+        let content = if attachments.is_empty() {
+            MessageContent::Text { text }
+        } else {
+            MessageContent::Attachments { text, attachments }
+        };
+        Self {
+            role: role.to_string(),
+            content,
+            timestamp: chrono::Utc::now(),
+            token_count: None,
+        }
+    }
+
+    /// The message as kept in memory after its turn: inline attachments
+    /// become a note naming each file, so later turns do not resend the
+    /// bytes. Attachments by URL are kept.
+    pub fn without_inline_data(&self) -> Self {
+        let MessageContent::Attachments { text, attachments } = &self.content else {
+            return self.clone();
+        };
+        let (uploads, inline): (Vec<&Attachment>, Vec<&Attachment>) = attachments
+            .iter()
+            .partition(|a| matches!(a.source, AttachmentSource::Url { .. }));
+        let mut notes: Vec<String> = inline.iter().map(|a| a.note()).collect();
+        if !text.is_empty() {
+            notes.push(text.clone());
+        }
+        Self::with_attachments(
+            &self.role,
+            notes.join("\n"),
+            uploads.into_iter().cloned().collect(),
+        )
+    }
+
     /// Convert to a provider-facing message format.
     pub fn as_provider_message(&self) -> Option<serde_json::Value> {
         match &self.content {
@@ -48,6 +97,18 @@ impl Message {
                 "role": self.role,
                 "content": text,
             })),
+            // Files go before the text that asks about them.
+            MessageContent::Attachments { text, attachments } => {
+                let mut content: Vec<serde_json::Value> =
+                    attachments.iter().map(Attachment::content_block).collect();
+                if !text.is_empty() {
+                    content.push(serde_json::json!({ "type": "text", "text": text }));
+                }
+                Some(serde_json::json!({
+                    "role": self.role,
+                    "content": content,
+                }))
+            }
             MessageContent::ToolUse { id, name, input } => Some(serde_json::json!({
                 "role": "assistant",
                 "content": [{
@@ -74,6 +135,152 @@ impl Message {
     }
 }
 
+/// MIME types accepted for attachments: what both providers read as images,
+/// plus PDF documents.
+pub const ATTACHMENT_MIME_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+];
+
+/// An image or document attached to a user message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub mime_type: String,
+    pub source: AttachmentSource,
+    /// Original file name, if the client or platform gave one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Where an attachment's bytes are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AttachmentSource {
+    /// Inline, base64-encoded.
+    Base64 { data: String },
+    /// Already uploaded; the provider fetches it.
+    Url { url: String },
+}
+
+impl Attachment {
+    pub fn is_image(&self) -> bool {
+        self.mime_type.starts_with("image/")
+    }
+
+    /// Check the MIME type, that inline data decodes to at most `max_bytes`,
+    /// and that uploads are referenced by an http(s) URL.
+    pub fn validate(&self, max_bytes: usize) -> Result<(), String> {
+        if !ATTACHMENT_MIME_TYPES.contains(&self.mime_type.as_str()) {
+            return Err(format!(
+                "unsupported attachment type '{}' (supported: {})",
+                self.mime_type,
+                ATTACHMENT_MIME_TYPES.join(", ")
+            ));
+        }
+        match &self.source {
+            AttachmentSource::Base64 { data } => {
+                // Cheap bound before decoding: 4 base64 characters per 3 bytes.
+                if data.len() / 4 * 3 > max_bytes + 2 {
+                    return Err(too_large(max_bytes));
+                }
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(data)
+                    .map_err(|e| format!("attachment data is not valid base64: {e}"))?;
+                if bytes.len() > max_bytes {
+                    return Err(too_large(max_bytes));
+                }
+            }
+            AttachmentSource::Url { url } => {
+                let parsed =
+                    url::Url::parse(url).map_err(|e| format!("invalid attachment url: {e}"))?;
+                if !matches!(parsed.scheme(), "http" | "https") {
+                    return Err(format!("attachment url must be http(s): {url}"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Decoded size of inline data; `None` for attachments by URL.
+    pub fn size(&self) -> Option<usize> {
+        match &self.source {
+            AttachmentSource::Base64 { data } => {
+                let padding = data.bytes().rev().take_while(|&b| b == b'=').count();
+                Some((data.len() / 4 * 3).saturating_sub(padding))
+            }
+            AttachmentSource::Url { .. } => None,
+        }
+    }
+
+    /// The attachment as kept in a session transcript: name, type and size
+    /// in place of inline data. Attachments by URL are kept whole.
+    pub fn placeholder(&self) -> serde_json::Value {
+        let Some(size) = self.size() else {
+            return serde_json::json!(self);
+        };
+        let mut entry = serde_json::json!({ "mime_type": self.mime_type, "size": size });
+        if let Some(name) = &self.name {
+            entry["name"] = serde_json::json!(name);
+        }
+        entry
+    }
+
+    /// Stand-in text for an inline attachment in later turns.
+    fn note(&self) -> String {
+        format!(
+            "[attachment: {} ({}, {} bytes)]",
+            self.name.as_deref().unwrap_or("unnamed"),
+            self.mime_type,
+            self.size().unwrap_or(0)
+        )
+    }
+
+    /// Validate a message's attachments against the gateway's limits.
+    pub fn validate_all(
+        attachments: &[Attachment],
+        max_count: usize,
+        max_bytes: usize,
+    ) -> Result<(), String> {
+        if attachments.len() > max_count {
+            return Err(format!(
+                "{} attachments; the limit is {max_count}",
+                attachments.len()
+            ));
+        }
+        attachments
+            .iter()
+            .try_for_each(|attachment| attachment.validate(max_bytes))
+    }
+
+    /// Anthropic content block: `image` for images, `document` for PDFs.
+    /// Providers with other formats convert from this one.
+    pub fn content_block(&self) -> serde_json::Value {
+        let source = match &self.source {
+            AttachmentSource::Base64 { data } => serde_json::json!({
+                "type": "base64",
+                "media_type": self.mime_type,
+                "data": data,
+            }),
+            AttachmentSource::Url { url } => serde_json::json!({ "type": "url", "url": url }),
+        };
+        let kind = if self.is_image() { "image" } else { "document" };
+        let mut block = serde_json::json!({ "type": kind, "source": source });
+        if !self.is_image()
+            && let Some(name) = &self.name
+        {
+            block["title"] = serde_json::json!(name);
+        }
+        block
+    }
+}
+
+fn too_large(max_bytes: usize) -> String {
+    format!("attachment exceeds {max_bytes} bytes")
+}
+
 /// Normalized incoming message from any channel.
 /// Used by the router to determine the target agent and session.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -107,12 +314,25 @@ pub enum StreamEvent {
         content: String,
         is_error: bool,
     },
+    ToolApprovalRequest {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+        timeout_secs: u64,
+    },
+    ClientToolCall {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+        timeout_secs: u64,
+    },
     Usage {
         input_tokens: u32,
         output_tokens: u32,
     },
     Done,
     Error(String),
+    Cancelled,
 }
 
 impl StreamEvent {
@@ -138,6 +358,26 @@ impl StreamEvent {
                 "event": "tool_result",
                 "data": { "tool_use_id": tool_use_id, "content": content, "is_error": is_error },
             }),
+            StreamEvent::ToolApprovalRequest {
+                id,
+                name,
+                input,
+                timeout_secs,
+            } => serde_json::json!({
+                "id": request_id,
+                "event": "tool_approval_request",
+                "data": { "id": id, "name": name, "input": input, "timeout_secs": timeout_secs },
+            }),
+            StreamEvent::ClientToolCall {
+                id,
+                name,
+                input,
+                timeout_secs,
+            } => serde_json::json!({
+                "id": request_id,
+                "event": "client_tool_call",
+                "data": { "id": id, "name": name, "input": input, "timeout_secs": timeout_secs },
+            }),
             StreamEvent::Usage {
                 input_tokens,
                 output_tokens,
@@ -155,6 +395,10 @@ impl StreamEvent {
                 "event": "error",
                 "data": data,
             }),
+            StreamEvent::Cancelled => serde_json::json!({
+                "id": request_id,
+                "event": "cancelled",
+            }),
         }
     }
 }
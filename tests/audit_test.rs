@@ -0,0 +1,131 @@
+//! The audit log sink is process-wide, so these tests live in their own
+//! binary with a single gateway.
+
+use exoclaw::audit::{Filter, tail};
+use exoclaw::config::{ApiTokenConfig, ExoclawConfig};
+use exoclaw::gateway::auth::Scope;
+use tokio::time::{Duration, sleep};
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .expect("bind ephemeral")
+        .local_addr()
+        .expect("local addr")
+        .port()
+}
+
+async fn wait_for_health(port: u16) {
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{port}/health");
+    for _ in 0..80 {
+        if client
+            .get(&url)
+            .send()
+            .await
+            .is_ok_and(|r| r.status().is_success())
+        {
+            return;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!("gateway did not become healthy at {url}");
+}
+
+fn kind(kind: &str) -> Filter {
+    Filter {
+        kinds: vec![kind.to_string()],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn security_events_are_written_to_the_audit_log() {
+    let dir = std::env::temp_dir().join(format!("exoclaw-audit-{}", uuid::Uuid::new_v4()));
+    let path = dir.join("audit.jsonl");
+
+    let port = free_port();
+    let mut config = ExoclawConfig::default();
+    config.gateway.port = port;
+    config.agent.provider = "mock".to_string();
+    config.audit.path = Some(path.display().to_string());
+    config.budgets.session = Some(1);
+    config.gateway.tokens = vec![
+        ApiTokenConfig {
+            name: "reader".to_string(),
+            token: "read-only".to_string(),
+            scopes: vec![Scope::SessionRead],
+            channel: None,
+            account: None,
+        },
+        ApiTokenConfig {
+            name: "cron".to_string(),
+            token: "cron-tok".to_string(),
+            scopes: vec![Scope::ChatSend],
+            channel: None,
+            account: None,
+        },
+    ];
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+    wait_for_health(port).await;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{port}/v1/chat?stream=false");
+    let body = r#"{"channel":"http","account":"cron","content":"hello"}"#;
+    for (token, expected) in [
+        ("wrong", reqwest::StatusCode::UNAUTHORIZED),
+        ("read-only", reqwest::StatusCode::FORBIDDEN),
+        ("cron-tok", reqwest::StatusCode::TOO_MANY_REQUESTS),
+    ] {
+        let resp = client
+            .post(&url)
+            .bearer_auth(token)
+            .body(body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), expected, "token {token}");
+    }
+
+    // The OpenAI facade audits its failures too, and answers in OpenAI's shape.
+    let resp = client
+        .post(format!("http://127.0.0.1:{port}/v1/chat/completions"))
+        .bearer_auth("wrong")
+        .body(r#"{"model":"default","messages":[{"role":"user","content":"hi"}]}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    assert!(resp.headers().contains_key("www-authenticate"));
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "invalid_api_key");
+
+    let failed = tail(&path, &kind("auth_failed"), 10).unwrap();
+    assert_eq!(failed.len(), 2);
+    let event: serde_json::Value = serde_json::from_str(&failed[0]).unwrap();
+    assert_eq!(event["method"], "http");
+    assert!(event["ts"].is_string());
+    for line in &failed {
+        let event: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert!(event["remote"].as_str().unwrap().starts_with("127.0.0.1:"));
+    }
+
+    let denied = tail(&path, &kind("scope_denied"), 10).unwrap();
+    assert_eq!(denied.len(), 1);
+    assert!(denied[0].contains(r#""caller":"reader""#));
+    assert!(denied[0].contains(r#""remote":"127.0.0.1:"#));
+
+    let budget = Filter {
+        kinds: vec!["budget_rejected".to_string()],
+        session_key: Some("default:http:cron:main".to_string()),
+        ..Default::default()
+    };
+    let rejected = tail(&path, &budget, 10).unwrap();
+    assert_eq!(rejected.len(), 1, "{rejected:?}");
+    assert!(rejected[0].contains(r#""method":"chat.send""#));
+
+    gateway.abort();
+    let _ = gateway.await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
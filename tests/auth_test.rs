@@ -1,4 +1,7 @@
-use exoclaw::gateway::auth::verify_connect;
+use exoclaw::config::ApiTokenConfig;
+use exoclaw::gateway::auth::{
+    Caller, Scope, authenticate, origin_allowed, verify_bearer, verify_connect,
+};
 
 #[test]
 fn valid_token_authenticates() {
@@ -49,3 +52,109 @@ fn json_with_extra_fields_accepted() {
     let msg = r#"{"token": "correct", "extra": true}"#;
     assert!(verify_connect(msg, &expected));
 }
+
+#[test]
+fn bearer_header_must_match_token() {
+    let expected = Some("secret".to_string());
+    assert!(verify_bearer(Some("Bearer secret"), &expected));
+    assert!(!verify_bearer(Some("Bearer wrong"), &expected));
+    assert!(!verify_bearer(Some("secret"), &expected));
+    assert!(!verify_bearer(None, &expected));
+    assert!(verify_bearer(None, &None));
+}
+
+fn scoped_token(name: &str, token: &str, scopes: Vec<Scope>) -> ApiTokenConfig {
+    ApiTokenConfig {
+        name: name.to_string(),
+        token: token.to_string(),
+        scopes,
+        channel: None,
+        account: None,
+    }
+}
+
+#[test]
+fn authenticate_resolves_named_tokens() {
+    let tokens = vec![
+        scoped_token("reader", "read-tok", vec![Scope::SessionRead]),
+        scoped_token("chatter", "chat-tok", vec![Scope::ChatSend]),
+    ];
+    let legacy = Some("legacy".to_string());
+
+    let caller = authenticate("chat-tok", &legacy, &tokens).expect("known token");
+    assert_eq!(caller.name, "chatter");
+    assert!(caller.has_scope(Scope::ChatSend));
+    assert!(!caller.has_scope(Scope::SessionRead));
+
+    let caller = authenticate("legacy", &legacy, &tokens).expect("legacy token");
+    assert_eq!(caller.name, "default");
+    assert!(caller.has_scope(Scope::PluginAdmin));
+
+    assert!(authenticate("nope", &legacy, &tokens).is_none());
+}
+
+#[test]
+fn admin_scope_implies_every_other_scope() {
+    let caller = Caller::from_token(&scoped_token("ops", "t", vec![Scope::Admin]));
+    assert!(Scope::ALL.iter().all(|s| caller.has_scope(*s)));
+}
+
+#[test]
+fn unlisted_methods_need_admin() {
+    assert_eq!(Scope::for_method("ping"), None);
+    assert_eq!(Scope::for_method("chat.send"), Some(Scope::ChatSend));
+    assert_eq!(
+        Scope::for_method("session.delete"),
+        Some(Scope::SessionWrite)
+    );
+    assert_eq!(Scope::for_method("plugin.list"), Some(Scope::PluginAdmin));
+    assert_eq!(Scope::for_method("something.new"), Some(Scope::Admin));
+}
+
+#[test]
+fn pinned_caller_only_reaches_its_route() {
+    let mut token = scoped_token("bot", "t", vec![Scope::ChatSend]);
+    token.channel = Some("telegram".to_string());
+    token.account = Some("support".to_string());
+    let caller = Caller::from_token(&token);
+
+    assert!(caller.may_route("telegram", "support"));
+    assert!(!caller.may_route("telegram", "sales"));
+    assert!(caller.may_access_session("default:telegram:support:main"));
+    assert!(!caller.may_access_session("default:discord:support:main"));
+    assert!(!caller.may_access_session("garbage"));
+}
+
+#[test]
+fn origin_check_allows_same_origin_and_listed_origins() {
+    let host = Some("127.0.0.1:7200");
+    let listed = vec!["https://app.example.com".to_string()];
+
+    // Non-browser clients send no Origin.
+    assert!(origin_allowed(None, host, &[]));
+    assert!(origin_allowed(Some("http://127.0.0.1:7200"), host, &[]));
+    assert!(!origin_allowed(Some("http://127.0.0.1:8080"), host, &[]));
+    assert!(!origin_allowed(Some("https://evil.example"), host, &[]));
+    assert!(!origin_allowed(Some("null"), host, &[]));
+
+    assert!(origin_allowed(
+        Some("https://app.example.com"),
+        host,
+        &listed
+    ));
+    assert!(origin_allowed(
+        Some("https://app.example.com:443"),
+        host,
+        &listed
+    ));
+    assert!(!origin_allowed(
+        Some("http://app.example.com"),
+        host,
+        &listed
+    ));
+    assert!(origin_allowed(
+        Some("https://evil.example"),
+        host,
+        &["*".to_string()]
+    ));
+}
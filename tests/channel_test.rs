@@ -1,5 +1,6 @@
 use exoclaw::sandbox::capabilities::Capability;
 use exoclaw::sandbox::{PluginHost, PluginType};
+use exoclaw::types::{Attachment, AttachmentSource};
 
 /// Path to the mock-channel plugin WASM binary.
 fn mock_channel_wasm_path() -> String {
@@ -59,6 +60,33 @@ fn parse_incoming_default_peer() {
     assert_eq!(result["peer"], "main");
 }
 
+#[test]
+fn parse_incoming_surfaces_media_as_attachments() {
+    let mut host = PluginHost::new();
+    host.register("mock", &mock_channel_wasm_path(), vec![])
+        .unwrap();
+
+    let webhook_payload = serde_json::json!({
+        "text": "look at this",
+        "user_id": "user-1",
+        "photo_url": "https://cdn.example.com/p/1.jpg"
+    });
+    let payload_bytes = serde_json::to_vec(&webhook_payload).unwrap();
+
+    let result = host.call_channel_parse("mock", &payload_bytes).unwrap();
+
+    let attachments: Vec<Attachment> =
+        serde_json::from_value(result["attachments"].clone()).unwrap();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].mime_type, "image/jpeg");
+    assert_eq!(
+        attachments[0].source,
+        AttachmentSource::Url {
+            url: "https://cdn.example.com/p/1.jpg".into()
+        }
+    );
+}
+
 #[test]
 fn parse_incoming_invalid_payload_returns_error() {
     let mut host = PluginHost::new();
@@ -1,4 +1,4 @@
-use exoclaw::config::{ExoclawConfig, load};
+use exoclaw::config::{ExoclawConfig, Scope, load, validate};
 
 #[test]
 fn default_config_has_sensible_values() {
@@ -193,6 +193,33 @@ capabilities = ["http:api.example.com", "http:cdn.example.com"]
     assert_eq!(config.plugins[1].capabilities.len(), 2);
 }
 
+#[test]
+fn approval_policy_combines_plugin_and_agent_settings() {
+    let toml_str = r#"
+[agent]
+require_approval = ["shell"]
+
+[[agents]]
+id = "ops"
+
+[[plugins]]
+name = "web"
+path = "/tmp/web.wasm"
+capabilities = ["http:api.example.com"]
+require_approval = true
+
+[[plugins]]
+name = "echo"
+path = "/tmp/echo.wasm"
+"#;
+
+    let config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    assert_eq!(config.approval_tools(&config.agent), vec!["shell", "web"]);
+    assert_eq!(config.approval_tools(&config.agents[0]), vec!["web"]);
+    assert_eq!(config.gateway.tool_approval_timeout_secs, 300);
+    assert_eq!(config.gateway.client_tool_timeout_secs, 60);
+}
+
 #[test]
 fn missing_config_file_uses_defaults() {
     // Set EXOCLAW_CONFIG to a non-existent file
@@ -239,3 +266,290 @@ provider = "anthropic"
     let config = result.unwrap();
     assert_eq!(config.gateway.port, 9999);
 }
+
+#[test]
+fn agents_registry_parses_and_resolves_by_id() {
+    let toml_str = r#"
+[agent]
+id = "personal"
+provider = "anthropic"
+
+[[agents]]
+id = "work"
+provider = "openai"
+model = "gpt-4o"
+max_tokens = 1024
+system_prompt = "Be brief."
+tools = ["echo"]
+
+[[bindings]]
+agent_id = "work"
+guild_id = "office"
+"#;
+
+    let config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    validate(&config).unwrap();
+    assert_eq!(config.agents.len(), 1);
+
+    let work = config.agent_def("work");
+    assert_eq!(work.provider, "openai");
+    assert_eq!(work.model, "gpt-4o");
+    assert_eq!(work.max_tokens, 1024);
+    assert_eq!(work.system_prompt.as_deref(), Some("Be brief."));
+    assert_eq!(work.tools, vec!["echo"]);
+
+    assert_eq!(config.agent_def("personal").provider, "anthropic");
+    // Unknown ids (e.g. the router default) fall back to the primary agent.
+    assert_eq!(config.agent_def("default").id, "personal");
+    assert!(config.find_agent("default").is_none());
+}
+
+#[test]
+fn binding_to_unknown_agent_fails_validation() {
+    let toml_str = r#"
+[[bindings]]
+agent_id = "work"
+channel = "telegram"
+"#;
+
+    let config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    let err = validate(&config).expect_err("unknown agent must be rejected");
+    assert!(
+        err.to_string().contains("unknown agent 'work'"),
+        "unexpected error: {err}"
+    );
+}
+
+#[test]
+fn duplicate_agent_ids_fail_validation() {
+    let toml_str = r#"
+[[agents]]
+id = "work"
+
+[[agents]]
+id = "work"
+"#;
+
+    let config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    let err = validate(&config).expect_err("duplicate ids must be rejected");
+    assert!(err.to_string().contains("defined more than once"));
+}
+
+#[test]
+fn gateway_tokens_parse_with_scopes_and_pins() {
+    let toml_str = r#"
+[[gateway.tokens]]
+name = "support-bot"
+token = "tok-1"
+scopes = ["chat:send", "session:read"]
+channel = "telegram"
+account = "support"
+"#;
+
+    let config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    validate(&config).unwrap();
+    let token = &config.gateway.tokens[0];
+    assert_eq!(token.name, "support-bot");
+    assert_eq!(token.scopes, vec![Scope::ChatSend, Scope::SessionRead]);
+    assert_eq!(token.channel.as_deref(), Some("telegram"));
+    assert_eq!(token.account.as_deref(), Some("support"));
+}
+
+#[test]
+fn token_debug_output_redacts_the_secret() {
+    let toml_str = r#"
+[gateway]
+metrics_token = "scrape-secret-2"
+
+[[gateway.tokens]]
+name = "support-bot"
+token = "tok-secret-1"
+"#;
+
+    let config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    let dump = format!("{config:?}");
+    assert!(dump.contains("support-bot"));
+    assert!(!dump.contains("tok-secret-1"));
+    assert!(!dump.contains("scrape-secret-2"));
+    let metrics_token = config.gateway.metrics_token.as_ref().unwrap();
+    assert_eq!(metrics_token.expose(), "scrape-secret-2");
+    // Saved configs keep the value itself.
+    assert!(
+        toml::to_string(&config)
+            .unwrap()
+            .contains("scrape-secret-2")
+    );
+}
+
+#[test]
+fn unknown_token_scope_is_a_parse_error() {
+    let toml_str = r#"
+[[gateway.tokens]]
+name = "x"
+token = "tok"
+scopes = ["everything"]
+"#;
+    assert!(toml::from_str::<ExoclawConfig>(toml_str).is_err());
+}
+
+#[test]
+fn duplicate_token_names_fail_validation() {
+    let toml_str = r#"
+[[gateway.tokens]]
+name = "ci"
+token = "a"
+
+[[gateway.tokens]]
+name = "ci"
+token = "b"
+"#;
+
+    let config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    let err = validate(&config).expect_err("duplicate names must be rejected");
+    assert!(err.to_string().contains("defined more than once"));
+}
+
+#[test]
+fn rate_limits_parse_and_reject_zero() {
+    let toml_str = r#"
+[gateway.rate_limits]
+ip = { per_minute = 60 }
+session = { per_minute = 10, burst = 3 }
+"#;
+
+    let config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    validate(&config).unwrap();
+    let limits = &config.gateway.rate_limits;
+    assert_eq!(limits.ip.as_ref().unwrap().per_minute, 60);
+    assert_eq!(limits.session.as_ref().unwrap().burst, Some(3));
+    assert!(limits.channel.is_none() && limits.token.is_none());
+
+    let config: ExoclawConfig =
+        toml::from_str("[gateway.rate_limits]\ntoken = { per_minute = 0 }\n").unwrap();
+    assert!(validate(&config).is_err());
+}
+
+#[test]
+fn gateway_size_and_stream_limits_have_defaults() {
+    let config: ExoclawConfig = toml::from_str("[gateway]\nmax_streams = 8\n").unwrap();
+    validate(&config).unwrap();
+    assert_eq!(config.gateway.max_streams, 8);
+    assert_eq!(config.gateway.max_message_bytes, 1024 * 1024);
+    assert_eq!(config.gateway.max_streams_per_connection, 16);
+
+    let config: ExoclawConfig = toml::from_str("[gateway]\nmax_connections = 0\n").unwrap();
+    let err = validate(&config).expect_err("zero limit must be rejected");
+    assert!(err.to_string().contains("max_connections"));
+}
+
+#[test]
+fn attachment_limits_must_fit_in_one_message() {
+    // The defaults leave room for a message carrying every attachment.
+    let config = ExoclawConfig::default();
+    validate(&config).unwrap();
+    let gateway = &config.gateway;
+    assert!(
+        gateway.max_attachments * gateway.max_attachment_bytes * 4 / 3 <= gateway.max_message_bytes
+    );
+
+    let config: ExoclawConfig =
+        toml::from_str("[gateway]\nmax_attachment_bytes = 5242880\n").unwrap();
+    let err = validate(&config).expect_err("attachments larger than a message");
+    assert!(err.to_string().contains("max_message_bytes"));
+
+    let config: ExoclawConfig =
+        toml::from_str("[gateway]\nmax_attachment_bytes = 5242880\nmax_message_bytes = 33554432\n")
+            .unwrap();
+    validate(&config).unwrap();
+
+    let config: ExoclawConfig =
+        toml::from_str("[gateway]\nmax_attachments = 0\nmax_attachment_bytes = 5242880\n").unwrap();
+    validate(&config).unwrap();
+}
+
+#[test]
+fn allowed_origins_must_be_bare_origins() {
+    let config: ExoclawConfig =
+        toml::from_str("[gateway]\nallowed_origins = [\"https://app.example.com\", \"*\"]\n")
+            .unwrap();
+    validate(&config).unwrap();
+
+    let config: ExoclawConfig =
+        toml::from_str("[gateway]\nallowed_origins = [\"app.example.com/path\"]\n").unwrap();
+    assert!(validate(&config).is_err());
+}
+
+#[test]
+fn tls_needs_files_or_self_signed() {
+    let config: ExoclawConfig =
+        toml::from_str("[gateway.tls]\ncert_path = \"c.pem\"\nkey_path = \"k.pem\"\n").unwrap();
+    validate(&config).unwrap();
+
+    let config: ExoclawConfig = toml::from_str("[gateway.tls]\nself_signed = true\n").unwrap();
+    validate(&config).unwrap();
+
+    let config: ExoclawConfig = toml::from_str("[gateway.tls]\ncert_path = \"c.pem\"\n").unwrap();
+    assert!(validate(&config).is_err());
+}
+
+#[test]
+fn webhooks_default_to_sync_per_channel() {
+    let toml_str = r#"
+[gateway.webhooks.telegram]
+mode = "async"
+delivery_attempts = 3
+"#;
+    let config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    validate(&config).unwrap();
+    let telegram = config.gateway.webhook("telegram");
+    assert_eq!(telegram.mode, exoclaw::config::WebhookMode::Async);
+    assert_eq!(telegram.delivery_attempts, 3);
+    assert_eq!(telegram.dedup_ttl_secs, 3600);
+    assert_eq!(
+        config.gateway.webhook("slack").mode,
+        exoclaw::config::WebhookMode::Sync
+    );
+}
+
+#[test]
+fn webhook_signature_config_is_validated() {
+    let toml_str = r#"
+[gateway.webhooks.slack.hmac]
+secret = "slack-signing"
+header = "X-Slack-Signature"
+prefix = "v0="
+timestamp_header = "X-Slack-Request-Timestamp"
+signed_payload = "v0:{timestamp}:{body}"
+
+[gateway.webhooks.telegram.secret_token]
+secret = "telegram-webhook"
+header = "X-Telegram-Bot-Api-Secret-Token"
+"#;
+    let mut config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    validate(&config).unwrap();
+    let hmac = config.gateway.webhook("slack").hmac.unwrap();
+    assert_eq!(hmac.timestamp_tolerance_secs, 300);
+    assert!(config.gateway.webhook("telegram").secret_token.is_some());
+
+    let slack = config.gateway.webhooks.get_mut("slack").unwrap();
+    slack.hmac.as_mut().unwrap().timestamp_header = None;
+    let err = validate(&config).unwrap_err().to_string();
+    assert!(err.contains("timestamp_header"), "{err}");
+}
+
+#[test]
+fn audit_log_is_off_until_a_path_is_set() {
+    let config = ExoclawConfig::default();
+    assert!(config.audit.path.is_none());
+    assert_eq!(config.audit.max_bytes, 10 * 1024 * 1024);
+    assert_eq!(config.audit.max_files, 5);
+
+    let toml_str = r#"
+[audit]
+path = "/var/log/exoclaw/audit.jsonl"
+max_bytes = 0
+"#;
+    let config: ExoclawConfig = toml::from_str(toml_str).unwrap();
+    let err = validate(&config).unwrap_err().to_string();
+    assert!(err.contains("audit.max_bytes"), "{err}");
+}
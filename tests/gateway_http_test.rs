@@ -1,4 +1,5 @@
-use exoclaw::config::ExoclawConfig;
+use exoclaw::config::{ApiTokenConfig, ExoclawConfig, RateLimit, TlsConfig};
+use exoclaw::gateway::auth::Scope;
 use tokio::time::{Duration, sleep};
 
 fn free_port() -> u16 {
@@ -64,6 +65,41 @@ async fn health_endpoint_returns_ok() {
     let _ = gateway.await;
 }
 
+#[tokio::test]
+async fn self_signed_tls_serves_https() {
+    let port = free_port();
+    let mut config = loopback_config(port);
+    config.gateway.tls = Some(TlsConfig {
+        self_signed: true,
+        ..Default::default()
+    });
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let url = format!("https://127.0.0.1:{port}/health");
+    let mut body = None;
+    for _ in 0..80 {
+        if let Ok(resp) = client.get(&url).send().await {
+            body = Some(resp.text().await.expect("health body"));
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(body.as_deref(), Some("ok"));
+
+    // Plain HTTP is not served on a TLS port.
+    let plain = reqwest::get(format!("http://127.0.0.1:{port}/health")).await;
+    assert!(plain.is_err() || !plain.unwrap().status().is_success());
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
 #[tokio::test]
 async fn ui_root_and_spa_fallback_routes_serve_html() {
     let port = free_port();
@@ -105,6 +141,52 @@ async fn ui_root_and_spa_fallback_routes_serve_html() {
     let _ = gateway.await;
 }
 
+async fn ws_upgrade_status(port: u16, origin: &str) -> reqwest::StatusCode {
+    reqwest::Client::new()
+        .get(format!("http://127.0.0.1:{port}/ws"))
+        .header(reqwest::header::CONNECTION, "Upgrade")
+        .header(reqwest::header::UPGRADE, "websocket")
+        .header(reqwest::header::SEC_WEBSOCKET_VERSION, "13")
+        .header(
+            reqwest::header::SEC_WEBSOCKET_KEY,
+            "dGhlIHNhbXBsZSBub25jZQ==",
+        )
+        .header(reqwest::header::ORIGIN, origin)
+        .send()
+        .await
+        .expect("upgrade response")
+        .status()
+}
+
+#[tokio::test]
+async fn ws_upgrade_checks_origin() {
+    let port = free_port();
+    let mut config = loopback_config(port);
+    config.gateway.allowed_origins = vec!["https://app.example.com".to_string()];
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    let same_origin = format!("http://127.0.0.1:{port}");
+    assert_eq!(
+        ws_upgrade_status(port, &same_origin).await,
+        reqwest::StatusCode::SWITCHING_PROTOCOLS
+    );
+    assert_eq!(
+        ws_upgrade_status(port, "https://app.example.com").await,
+        reqwest::StatusCode::SWITCHING_PROTOCOLS
+    );
+    assert_eq!(
+        ws_upgrade_status(port, "https://evil.example").await,
+        reqwest::StatusCode::FORBIDDEN
+    );
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
 #[tokio::test]
 async fn webhook_without_adapter_returns_not_found() {
     let port = free_port();
@@ -130,3 +212,433 @@ async fn webhook_without_adapter_returns_not_found() {
     gateway.abort();
     let _ = gateway.await;
 }
+
+fn mock_config(port: u16) -> ExoclawConfig {
+    let mut config = loopback_config(port);
+    config.agent.provider = "mock".to_string();
+    config
+}
+
+const CHAT_BODY: &str = r#"{"channel":"http","account":"cron","content":"hello"}"#;
+
+#[tokio::test]
+async fn http_chat_streams_sse_events() {
+    let port = free_port();
+    let config = mock_config(port);
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}/v1/chat"))
+        .body(CHAT_BODY)
+        .send()
+        .await
+        .expect("chat response");
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    assert!(content_type.contains("text/event-stream"));
+
+    let body = response.text().await.expect("sse body");
+    assert!(body.contains("event: text"));
+    assert!(body.contains("mock response"));
+    assert!(body.contains("event: usage"));
+    assert!(body.contains("event: done"));
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn http_chat_without_stream_returns_collected_json() {
+    let port = free_port();
+    let config = mock_config(port);
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}/v1/chat?stream=false"))
+        .body(CHAT_BODY)
+        .send()
+        .await
+        .expect("chat response");
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let body: serde_json::Value = response.json().await.expect("json body");
+    assert_eq!(body["content"], "mock response");
+    assert_eq!(body["session_key"], "default:http:cron:main");
+    assert_eq!(body["usage"]["input_tokens"], 5);
+    assert_eq!(body["cancelled"], false);
+    assert!(body["error"].is_null());
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn http_chat_requires_bearer_token_when_configured() {
+    let port = free_port();
+    let config = mock_config(port);
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, Some("secret".to_string())).await;
+    });
+
+    wait_for_health(port).await;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{port}/v1/chat?stream=false");
+    let denied = client
+        .post(&url)
+        .bearer_auth("wrong")
+        .body(CHAT_BODY)
+        .send()
+        .await
+        .expect("chat response");
+    assert_eq!(denied.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let allowed = client
+        .post(&url)
+        .bearer_auth("secret")
+        .body(CHAT_BODY)
+        .send()
+        .await
+        .expect("chat response");
+    assert_eq!(allowed.status(), reqwest::StatusCode::OK);
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn http_chat_enforces_token_scopes() {
+    let port = free_port();
+    let mut config = mock_config(port);
+    config.gateway.tokens = vec![
+        ApiTokenConfig {
+            name: "reader".to_string(),
+            token: "read-only".to_string(),
+            scopes: vec![Scope::SessionRead],
+            channel: None,
+            account: None,
+        },
+        ApiTokenConfig {
+            name: "cron".to_string(),
+            token: "cron-tok".to_string(),
+            scopes: vec![Scope::ChatSend],
+            channel: Some("http".to_string()),
+            account: Some("cron".to_string()),
+        },
+    ];
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{port}/v1/chat?stream=false");
+    let anonymous = client
+        .post(&url)
+        .body(CHAT_BODY)
+        .send()
+        .await
+        .expect("chat response");
+    assert_eq!(anonymous.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let denied = client
+        .post(&url)
+        .bearer_auth("read-only")
+        .body(CHAT_BODY)
+        .send()
+        .await
+        .expect("chat response");
+    assert_eq!(denied.status(), reqwest::StatusCode::FORBIDDEN);
+    let body: serde_json::Value = denied.json().await.expect("json body");
+    assert_eq!(body["error"], "missing scope: chat:send");
+
+    let off_route = client
+        .post(&url)
+        .bearer_auth("cron-tok")
+        .body(r#"{"channel":"http","account":"someone-else","content":"hello"}"#)
+        .send()
+        .await
+        .expect("chat response");
+    assert_eq!(off_route.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let allowed = client
+        .post(&url)
+        .bearer_auth("cron-tok")
+        .body(CHAT_BODY)
+        .send()
+        .await
+        .expect("chat response");
+    assert_eq!(allowed.status(), reqwest::StatusCode::OK);
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn http_chat_over_ip_limit_returns_429() {
+    let port = free_port();
+    let mut config = mock_config(port);
+    config.gateway.rate_limits.ip = Some(RateLimit {
+        per_minute: 1,
+        burst: None,
+    });
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{port}/v1/chat?stream=false");
+    let first = client.post(&url).body(CHAT_BODY).send().await.unwrap();
+    assert_eq!(first.status(), reqwest::StatusCode::OK);
+
+    let second = client.post(&url).body(CHAT_BODY).send().await.unwrap();
+    assert_eq!(second.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = second
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .expect("Retry-After header");
+    assert!(retry_after >= 1);
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn http_chat_rejects_invalid_body() {
+    let port = free_port();
+    let config = mock_config(port);
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}/v1/chat"))
+        .body(r#"{"channel":"http"}"#)
+        .send()
+        .await
+        .expect("chat response");
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = response.json().await.expect("json body");
+    assert!(
+        body["error"]
+            .as_str()
+            .unwrap_or("")
+            .contains("invalid chat.send params")
+    );
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+const COMPLETION_BODY: &str =
+    r#"{"model":"default","user":"sdk","messages":[{"role":"user","content":"hello"}]}"#;
+
+#[tokio::test]
+async fn openai_completions_returns_chat_completion() {
+    let port = free_port();
+    let config = mock_config(port);
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}/v1/chat/completions"))
+        .body(COMPLETION_BODY)
+        .send()
+        .await
+        .expect("completion response");
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let body: serde_json::Value = response.json().await.expect("json body");
+    assert_eq!(body["object"], "chat.completion");
+    assert_eq!(body["model"], "default");
+    assert_eq!(body["choices"][0]["message"]["role"], "assistant");
+    assert_eq!(body["choices"][0]["message"]["content"], "mock response");
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    assert_eq!(body["usage"]["prompt_tokens"], 5);
+    assert_eq!(body["usage"]["total_tokens"], 6);
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn openai_completions_unknown_model_is_not_found() {
+    let port = free_port();
+    let config = mock_config(port);
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}/v1/chat/completions"))
+        .body(r#"{"model":"gpt-4o","messages":[{"role":"user","content":"hi"}]}"#)
+        .send()
+        .await
+        .expect("completion response");
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    let body: serde_json::Value = response.json().await.expect("json body");
+    assert_eq!(body["error"]["code"], "model_not_found");
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn openai_models_lists_agents() {
+    let port = free_port();
+    let mut config = mock_config(port);
+    config.agents.push(exoclaw::config::AgentDefConfig {
+        id: "research".to_string(),
+        ..Default::default()
+    });
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    let body: serde_json::Value = reqwest::get(format!("http://127.0.0.1:{port}/v1/models"))
+        .await
+        .expect("models response")
+        .json()
+        .await
+        .expect("json body");
+    let ids: Vec<&str> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["default", "research"]);
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn openai_streaming_round_trips_through_openai_provider() {
+    use exoclaw::agent::AgentEvent;
+    use exoclaw::agent::providers::{LlmProvider, OpenAiProvider};
+
+    let port = free_port();
+    let config = mock_config(port);
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    // Point exoclaw's own OpenAI client at the facade: if it can parse the
+    // chunks, OpenAI SDKs can too.
+    // SAFETY: test-scoped env mutation; no other test in this binary uses it.
+    unsafe {
+        std::env::set_var(
+            "EXOCLAW_OPENAI_ENDPOINT",
+            format!("http://127.0.0.1:{port}/v1/chat/completions"),
+        );
+    }
+    let provider = OpenAiProvider::new("unused".to_string(), "default".to_string(), 64);
+    let (tx, mut rx) = tokio::sync::mpsc::channel(32);
+    provider
+        .call_streaming(
+            &[serde_json::json!({"role":"user","content":"hello"})],
+            &[],
+            None,
+            tx,
+        )
+        .await
+        .expect("provider call");
+    // SAFETY: undo test-scoped env mutation.
+    unsafe {
+        std::env::remove_var("EXOCLAW_OPENAI_ENDPOINT");
+    }
+
+    let mut text = String::new();
+    let mut usage = None;
+    let mut done = false;
+    while let Some(event) = rx.recv().await {
+        match event {
+            AgentEvent::Text(chunk) => text.push_str(&chunk),
+            AgentEvent::Usage {
+                input_tokens,
+                output_tokens,
+            } => usage = Some((input_tokens, output_tokens)),
+            AgentEvent::Done => done = true,
+            other => panic!("unexpected event: {other:?}"),
+        }
+    }
+    assert_eq!(text, "mock response");
+    assert_eq!(usage, Some((5, 1)));
+    assert!(done);
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn metrics_endpoint_requires_its_token_and_reports_runs() {
+    let port = free_port();
+    let mut config = mock_config(port);
+    config.gateway.metrics_token = Some(exoclaw::config::Secret::new("scrape-me"));
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    wait_for_health(port).await;
+
+    let client = reqwest::Client::new();
+    let chat = client
+        .post(format!("http://127.0.0.1:{port}/v1/chat?stream=false"))
+        .body(CHAT_BODY)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(chat.status(), reqwest::StatusCode::OK);
+
+    let url = format!("http://127.0.0.1:{port}/metrics");
+    let anonymous = client.get(&url).send().await.unwrap();
+    assert_eq!(anonymous.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let scraped = client
+        .get(&url)
+        .bearer_auth("scrape-me")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(scraped.status(), reqwest::StatusCode::OK);
+    let text = scraped.text().await.unwrap();
+    assert!(text.contains("# TYPE exoclaw_provider_request_duration_seconds histogram"));
+    assert!(
+        text.contains(r#"exoclaw_provider_request_duration_seconds_count{provider="mock",model="#),
+        "{text}"
+    );
+    assert!(text.contains(r#"exoclaw_tokens_total{provider="mock",model="#));
+    assert!(text.contains("exoclaw_sessions_stored 1"), "{text}");
+    assert!(text.contains("exoclaw_streams_active 0"));
+
+    gateway.abort();
+    let _ = gateway.await;
+}
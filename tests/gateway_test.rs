@@ -1,5 +1,6 @@
 use axum::{Router, http::header, response::IntoResponse, routing::post};
 use exoclaw::config::ExoclawConfig;
+use std::sync::OnceLock;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::time::{Duration, sleep, timeout};
@@ -13,6 +14,44 @@ async fn mock_openai_handler() -> impl IntoResponse {
     ([(header::CONTENT_TYPE, "text/event-stream")], body)
 }
 
+async fn slow_anthropic_handler() -> impl IntoResponse {
+    sleep(Duration::from_millis(500)).await;
+    let body = concat!(
+        "event: message_start\n",
+        "data: {\"message\":{\"usage\":{\"input_tokens\":5}}}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"delta\":{\"type\":\"text_delta\",\"text\":\"slow\"}}\n\n",
+        "event: message_stop\n",
+        "data: {}\n\n"
+    );
+    ([(header::CONTENT_TYPE, "text/event-stream")], body)
+}
+
+/// Shared slow Anthropic mock. It runs on its own thread so tests that point
+/// `EXOCLAW_ANTHROPIC_ENDPOINT` at it can overlap without racing on the env var.
+fn slow_anthropic_endpoint() -> &'static str {
+    static ENDPOINT: OnceLock<String> = OnceLock::new();
+    ENDPOINT.get_or_init(|| {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                let app = Router::new().route("/v1/messages", post(slow_anthropic_handler));
+                let _ = axum::serve(listener, app).await;
+            });
+        });
+        let endpoint = format!("http://{addr}/v1/messages");
+        // SAFETY: set once, before any gateway in this binary talks to Anthropic.
+        unsafe {
+            std::env::set_var("EXOCLAW_ANTHROPIC_ENDPOINT", &endpoint);
+        }
+        endpoint
+    })
+}
+
 async fn start_mock_openai_server() -> (String, tokio::task::JoinHandle<()>) {
     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
@@ -160,7 +199,13 @@ impl WsClient {
 
         match opcode {
             0x1 => Ok(String::from_utf8(payload)?),
-            0x8 => anyhow::bail!("received close frame"),
+            0x8 => {
+                let code = payload
+                    .get(..2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .unwrap_or_default();
+                anyhow::bail!("received close frame {code}")
+            }
             other => anyhow::bail!("unexpected opcode: {other}"),
         }
     }
@@ -301,3 +346,516 @@ async fn loopback_mode_allows_no_auth_ping() {
     gateway.abort();
     let _ = gateway.await;
 }
+
+#[tokio::test]
+async fn rpcs_are_answered_while_a_stream_is_in_flight() {
+    slow_anthropic_endpoint();
+
+    let port = free_port();
+    let mut config = gateway_config(port, false);
+    config.agent.api_key = Some("test-key".to_string());
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    let mut ws = connect_ws_with_retry("127.0.0.1", port).await;
+    let hello = ws.recv_json_timeout("hello").await.unwrap();
+    assert_eq!(hello["ok"], true);
+
+    ws.send_text(
+        r#"{"id":"slow","method":"chat.send","params":{"channel":"websocket","account":"me","content":"hi"}}"#,
+    )
+    .await
+    .unwrap();
+    ws.send_text(r#"{"id":"p1","method":"ping"}"#)
+        .await
+        .unwrap();
+
+    // The provider is still sleeping, so the pong must arrive first.
+    let first = ws.recv_json_timeout("pong").await.unwrap();
+    assert_eq!(first["id"], "p1");
+    assert_eq!(first["result"], "pong");
+
+    let mut saw_done = false;
+    for _ in 0..10 {
+        let frame = ws.recv_json_timeout("slow stream").await.unwrap();
+        assert_eq!(frame["id"], "slow");
+        if frame["event"] == "done" {
+            saw_done = true;
+            break;
+        }
+    }
+    assert!(saw_done, "expected done event for the slow stream");
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn chat_resume_replays_stream_after_reconnect() {
+    slow_anthropic_endpoint();
+
+    let port = free_port();
+    let mut config = gateway_config(port, false);
+    config.agent.api_key = Some("test-key".to_string());
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    // Start a run, then drop the socket before any frame arrives.
+    let mut ws = connect_ws_with_retry("127.0.0.1", port).await;
+    ws.recv_json_timeout("hello").await.unwrap();
+    ws.send_text(
+        r#"{"jsonrpc":"2.0","id":"lost","method":"chat.send","params":{"channel":"websocket","account":"me","content":"hi"}}"#,
+    )
+    .await
+    .unwrap();
+    let ack = ws.recv_json_timeout("chat.send ack").await.unwrap();
+    let stream = ack["result"]["stream"].as_str().unwrap().to_string();
+    drop(ws);
+
+    let mut ws = connect_ws_with_retry("127.0.0.1", port).await;
+    ws.recv_json_timeout("hello").await.unwrap();
+    ws.send_text(&format!(
+        r#"{{"id":"r1","method":"chat.resume","params":{{"stream":"{stream}","after_seq":0}}}}"#
+    ))
+    .await
+    .unwrap();
+
+    let ack = ws.recv_json_timeout("resume ack").await.unwrap();
+    assert_eq!(ack["id"], "r1");
+    assert_eq!(ack["result"]["resumed"], stream.as_str());
+
+    let mut seqs = Vec::new();
+    let mut text = String::new();
+    loop {
+        let frame = ws.recv_json_timeout("resumed frame").await.unwrap();
+        assert_eq!(frame["id"], "lost");
+        seqs.push(frame["seq"].as_u64().unwrap());
+        if let Some(chunk) = frame["data"].as_str() {
+            text.push_str(chunk);
+        }
+        if frame["event"] == "done" {
+            break;
+        }
+    }
+    assert_eq!(seqs, (1..=seqs.len() as u64).collect::<Vec<_>>());
+    assert_eq!(text, "slow");
+
+    // The reply was saved even though the original socket was gone.
+    ws.send_text(
+        r#"{"id":"h","method":"session.history","params":{"key":"default:websocket:me:main"}}"#,
+    )
+    .await
+    .unwrap();
+    let history = ws.recv_json_timeout("history").await.unwrap();
+    let messages = history["result"]["messages"].as_array().unwrap();
+    assert_eq!(messages.last().unwrap()["content"], "slow");
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn rotated_token_connection_closes_after_grace() {
+    let dir = std::env::temp_dir().join(format!("exoclaw-rotate-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let token_file = dir.join("tokens.toml");
+    std::fs::write(
+        &token_file,
+        "active = \"old-token\"\nnext = \"new-token\"\n",
+    )
+    .unwrap();
+
+    let port = free_port();
+    let mut config = gateway_config(port, false);
+    config.gateway.token_file = Some(token_file.display().to_string());
+    config.gateway.token_grace_secs = 1;
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    // Both tokens work during the rotation window.
+    let mut old = connect_ws_with_retry("127.0.0.1", port).await;
+    old.send_text(r#"{"token":"old-token"}"#).await.unwrap();
+    assert_eq!(
+        old.recv_json_timeout("old hello").await.unwrap()["ok"],
+        true
+    );
+    let mut new = connect_ws_with_retry("127.0.0.1", port).await;
+    new.send_text(r#"{"token":"new-token"}"#).await.unwrap();
+    assert_eq!(
+        new.recv_json_timeout("new hello").await.unwrap()["ok"],
+        true
+    );
+
+    // Promote the next token and reload over RPC.
+    std::fs::write(&token_file, "active = \"new-token\"\n").unwrap();
+    new.send_text(r#"{"jsonrpc":"2.0","id":1,"method":"auth.reload"}"#)
+        .await
+        .unwrap();
+    let reloaded = new.recv_json_timeout("reload result").await.unwrap();
+    assert_eq!(reloaded["result"]["active"], true);
+    assert_eq!(reloaded["result"]["next"], false);
+
+    let notice = old.recv_json_timeout("retired notice").await.unwrap();
+    assert_eq!(notice["event"], "token_retired");
+    assert_eq!(notice["data"]["grace_secs"], 1);
+
+    // Still usable during the grace period.
+    old.send_text(r#"{"id":"p","method":"ping"}"#)
+        .await
+        .unwrap();
+    assert_eq!(
+        old.recv_json_timeout("grace ping").await.unwrap()["result"],
+        "pong"
+    );
+
+    let closed = old
+        .recv_json_timeout("close")
+        .await
+        .expect_err("connection closes");
+    assert!(closed.to_string().contains("close frame 4001"), "{closed}");
+
+    // The promoted token keeps working; the retired one no longer authenticates.
+    new.send_text(r#"{"id":"p","method":"ping"}"#)
+        .await
+        .unwrap();
+    assert_eq!(
+        new.recv_json_timeout("new ping").await.unwrap()["result"],
+        "pong"
+    );
+    let mut stale = connect_ws_with_retry("127.0.0.1", port).await;
+    stale.send_text(r#"{"token":"old-token"}"#).await.unwrap();
+    assert_eq!(
+        stale.recv_json_timeout("stale").await.unwrap()["error"],
+        "auth_failed"
+    );
+
+    gateway.abort();
+    let _ = gateway.await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn oversized_messages_and_extra_connections_are_closed() {
+    let port = free_port();
+    let mut config = gateway_config(port, false);
+    config.gateway.max_message_bytes = 64;
+    config.gateway.max_connections = 1;
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+
+    let mut first = connect_ws_with_retry("127.0.0.1", port).await;
+    assert_eq!(first.recv_json_timeout("hello").await.unwrap()["ok"], true);
+
+    let mut second = connect_ws_with_retry("127.0.0.1", port).await;
+    let rejected = second
+        .recv_json_timeout("connection limit")
+        .await
+        .expect_err("second connection is closed");
+    assert!(
+        rejected.to_string().contains("close frame 1013"),
+        "{rejected}"
+    );
+
+    let big = format!(
+        r#"{{"id":"1","method":"ping","pad":"{}"}}"#,
+        "x".repeat(100)
+    );
+    first.send_text(&big).await.unwrap();
+    let closed = first
+        .recv_json_timeout("size limit")
+        .await
+        .expect_err("oversized message closes the connection");
+    assert!(closed.to_string().contains("close frame 1009"), "{closed}");
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+fn mock_channel_wasm_path() -> String {
+    format!(
+        "{}/examples/mock-channel/target/wasm32-unknown-unknown/release/mock_channel.wasm",
+        env!("CARGO_MANIFEST_DIR")
+    )
+}
+
+/// Platform send-message API: fails the first call to each path with 503,
+/// then accepts. `/reject` always answers 400.
+async fn start_reply_server() -> (u16, tokio::sync::mpsc::UnboundedReceiver<serde_json::Value>) {
+    use axum::{Json, extract::State, http::StatusCode, http::Uri};
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    type Seen = Arc<Mutex<HashSet<String>>>;
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let seen: Seen = Arc::default();
+    let app = Router::new()
+        .fallback(post(
+            move |State(seen): State<Seen>, uri: Uri, Json(body): Json<serde_json::Value>| {
+                let tx = tx.clone();
+                async move {
+                    if uri.path() == "/reject" {
+                        return StatusCode::BAD_REQUEST;
+                    }
+                    if seen.lock().unwrap().insert(uri.path().to_string()) {
+                        return StatusCode::SERVICE_UNAVAILABLE;
+                    }
+                    let _ = tx.send(body);
+                    StatusCode::OK
+                }
+            },
+        ))
+        .with_state(seen);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    (port, rx)
+}
+
+#[tokio::test]
+async fn async_webhook_acks_dedupes_and_delivers_with_retry() {
+    let (reply_port, mut delivered) = start_reply_server().await;
+    let port = free_port();
+    let mut config = gateway_config(port, false);
+    config.agent.provider = "mock".to_string();
+    config.plugins.push(exoclaw::config::PluginConfig {
+        name: "mock".to_string(),
+        path: mock_channel_wasm_path(),
+        capabilities: vec!["http:127.0.0.1".to_string()],
+        require_approval: false,
+    });
+    config.gateway.webhooks.insert(
+        "mock".to_string(),
+        exoclaw::config::WebhookConfig {
+            mode: exoclaw::config::WebhookMode::Async,
+            delivery_attempts: 2,
+            delivery_backoff_ms: 10,
+            ..Default::default()
+        },
+    );
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+    let mut ws = connect_ws_with_retry("127.0.0.1", port).await;
+    ws.recv_json_timeout("hello").await.unwrap();
+
+    let client = reqwest::Client::new();
+    let webhook = format!("http://127.0.0.1:{port}/webhook/mock");
+    let payload = |id: &str, path: &str| {
+        serde_json::json!({
+            "text": "hi",
+            "user_id": "u1",
+            "message_id": id,
+            "reply_url": format!("http://127.0.0.1:{reply_port}{path}"),
+        })
+    };
+
+    for expected in ["accepted", "duplicate"] {
+        let resp = client
+            .post(&webhook)
+            .json(&payload("m1", "/send"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+        let ack: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(ack["status"], expected);
+        assert_eq!(ack["message_id"], "m1");
+    }
+
+    // Delivered on the second attempt, exactly once.
+    let body = timeout(Duration::from_secs(5), delivered.recv())
+        .await
+        .expect("reply delivered")
+        .unwrap();
+    assert_eq!(body["text"], "mock response");
+    assert_eq!(body["peer"], "main");
+
+    // A rejected delivery is dead-lettered without retrying.
+    let resp = client
+        .post(&webhook)
+        .json(&payload("m2", "/reject"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+    let mut letters = serde_json::Value::Null;
+    for _ in 0..50 {
+        ws.send_text(r#"{"id":"d","method":"webhook.dead_letters"}"#)
+            .await
+            .unwrap();
+        letters =
+            ws.recv_json_timeout("dead letters").await.unwrap()["result"]["dead_letters"].clone();
+        if letters.as_array().is_some_and(|l| !l.is_empty()) {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(letters[0]["message_id"], "m2");
+    assert_eq!(letters[0]["attempts"], 1);
+    assert_eq!(letters[0]["content"], "mock response");
+    assert!(delivered.try_recv().is_err());
+
+    gateway.abort();
+    let _ = gateway.await;
+}
+
+#[tokio::test]
+async fn webhook_signature_is_checked_before_the_adapter() {
+    use hmac::{Hmac, Mac};
+
+    let state_dir = std::env::temp_dir().join(format!("exoclaw-webhook-{}", uuid::Uuid::new_v4()));
+    exoclaw::secrets::write_secret_to(&state_dir, "mock-signing", "topsecret").unwrap();
+    // SAFETY: EXOCLAW_CONFIG is only read through std::env, which serializes
+    // access, and no other test in this binary depends on its value.
+    unsafe {
+        std::env::set_var("EXOCLAW_CONFIG", state_dir.join("config.toml"));
+    }
+
+    let port = free_port();
+    let mut config = gateway_config(port, false);
+    config.agent.provider = "mock".to_string();
+    config.plugins.push(exoclaw::config::PluginConfig {
+        name: "mock".to_string(),
+        path: mock_channel_wasm_path(),
+        capabilities: vec![],
+        require_approval: false,
+    });
+    config.gateway.webhooks.insert(
+        "mock".to_string(),
+        exoclaw::config::WebhookConfig {
+            hmac: Some(exoclaw::config::HmacVerifyConfig {
+                secret: "mock-signing".to_string(),
+                header: "x-signature".to_string(),
+                prefix: "sha256=".to_string(),
+                timestamp_header: None,
+                signed_payload: "{body}".to_string(),
+                timestamp_tolerance_secs: 300,
+            }),
+            ..Default::default()
+        },
+    );
+    // Room for one delivery: forged requests must not use it up.
+    config.gateway.rate_limits.channel = Some(exoclaw::config::RateLimit {
+        per_minute: 1,
+        burst: Some(1),
+    });
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+    let _ws = connect_ws_with_retry("127.0.0.1", port).await;
+
+    let client = reqwest::Client::new();
+    let webhook = format!("http://127.0.0.1:{port}/webhook/mock");
+    let body = r#"{"text":"hi","user_id":"u1"}"#;
+    for _ in 0..5 {
+        let resp = client.post(&webhook).body(body).send().await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"topsecret").unwrap();
+    mac.update(body.as_bytes());
+    let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+    for (header, expected) in [
+        (None, reqwest::StatusCode::UNAUTHORIZED),
+        (Some("sha256=00"), reqwest::StatusCode::UNAUTHORIZED),
+        (Some(signature.as_str()), reqwest::StatusCode::OK),
+    ] {
+        let mut request = client.post(&webhook).body(body);
+        if let Some(value) = header {
+            request = request.header("x-signature", value);
+        }
+        let resp = request.send().await.unwrap();
+        assert_eq!(resp.status(), expected, "signature {header:?}");
+    }
+    // The signed delivery did use the channel's bucket.
+    let resp = client
+        .post(&webhook)
+        .header("x-signature", &signature)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+
+    gateway.abort();
+    let _ = gateway.await;
+    let _ = std::fs::remove_dir_all(&state_dir);
+}
+
+#[tokio::test]
+async fn session_subscribers_see_webhook_traffic_live() {
+    let port = free_port();
+    let mut config = gateway_config(port, false);
+    config.agent.provider = "mock".to_string();
+    config.plugins.push(exoclaw::config::PluginConfig {
+        name: "mock".to_string(),
+        path: mock_channel_wasm_path(),
+        capabilities: vec![],
+        require_approval: false,
+    });
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+    let mut ws = connect_ws_with_retry("127.0.0.1", port).await;
+    ws.recv_json_timeout("hello").await.unwrap();
+
+    ws.send_text(
+        r#"{"jsonrpc":"2.0","id":1,"method":"session.subscribe","params":{"session_key":"default:mock:u1:main"}}"#,
+    )
+    .await
+    .unwrap();
+    let ack = ws.recv_json_timeout("subscribe ack").await.unwrap();
+    assert_eq!(ack["result"]["subscribed"], "default:mock:u1:main");
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}/webhook/mock"))
+        .json(&serde_json::json!({ "text": "from the platform", "user_id": "u1" }))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+
+    let first = ws.recv_json_timeout("user message").await.unwrap();
+    assert_eq!(first["event"], "user_message");
+    assert_eq!(first["session_key"], "default:mock:u1:main");
+    assert_eq!(first["data"]["channel"], "mock");
+    assert_eq!(first["data"]["content"], "from the platform");
+    let mut text = String::new();
+    loop {
+        let frame = ws.recv_json_timeout("agent frame").await.unwrap();
+        assert_eq!(frame["id"], first["id"]);
+        match frame["event"].as_str() {
+            Some("text") => text.push_str(frame["data"].as_str().unwrap()),
+            Some("done") => break,
+            _ => {}
+        }
+    }
+    assert_eq!(text, "mock response");
+
+    // After unsubscribing, only the caller's own replies arrive.
+    ws.send_text(
+        r#"{"jsonrpc":"2.0","id":2,"method":"session.unsubscribe","params":{"session_key":"default:mock:u1:main"}}"#,
+    )
+    .await
+    .unwrap();
+    let ack = ws.recv_json_timeout("unsubscribe ack").await.unwrap();
+    assert_eq!(ack["result"]["unsubscribed"], "default:mock:u1:main");
+    reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}/webhook/mock"))
+        .json(&serde_json::json!({ "text": "again", "user_id": "u1" }))
+        .send()
+        .await
+        .unwrap();
+    ws.send_text(r#"{"id":"p","method":"ping"}"#).await.unwrap();
+    let pong = ws.recv_json_timeout("pong").await.unwrap();
+    assert_eq!(pong["result"], "pong");
+
+    gateway.abort();
+    let _ = gateway.await;
+}
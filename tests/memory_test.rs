@@ -93,6 +93,18 @@ fn episodic_empty_session_returns_empty() {
     assert!(mem.all("nonexistent").is_empty());
 }
 
+#[test]
+fn episodic_clear_drops_only_that_session() {
+    let mut mem = EpisodicMemory::new(3);
+    mem.append("a", make_text_message("user", "keep"));
+    mem.append("b", make_text_message("user", "drop"));
+
+    mem.clear("b");
+
+    assert!(mem.all("b").is_empty());
+    assert_eq!(mem.all("a").len(), 1);
+}
+
 #[test]
 fn episodic_recent_with_less_than_n() {
     let mut mem = EpisodicMemory::new(10);
@@ -105,6 +105,11 @@ provider = "anthropic"
 model = "claude-sonnet-4-5-20250929"
 max_tokens = 4096
 
+[[agents]]
+id = "my-agent"
+provider = "openai"
+model = "gpt-4o"
+
 [[plugins]]
 name = "echo"
 path = "/opt/plugins/echo.wasm"
@@ -157,6 +162,10 @@ semantic_enabled = false
         vec!["http:api.example.com"]
     );
 
+    // Agent registry preserved
+    assert_eq!(reloaded.agents.len(), 1);
+    assert_eq!(reloaded.agents[0].id, "my-agent");
+
     // Bindings preserved
     assert_eq!(reloaded.bindings.len(), 1);
     assert_eq!(reloaded.bindings[0].agent_id, "my-agent");
@@ -1,9 +1,21 @@
 use exoclaw::agent::AgentEvent;
-use exoclaw::config::ExoclawConfig;
-use exoclaw::gateway::protocol::{RpcResult, handle_rpc};
+use exoclaw::agent::approval::ApprovalGate;
+use exoclaw::agent::client_tools::{ClientCalls, ClientTools};
+use exoclaw::agent::metering::{self, BudgetScope};
+use exoclaw::config::{AgentDefConfig, ApiTokenConfig, ExoclawConfig, RateLimit};
+use exoclaw::gateway::auth::{Caller, Scope};
+use exoclaw::gateway::jsonrpc::Dialect;
+use exoclaw::gateway::keyring::Keyring;
+use exoclaw::gateway::limits::Slots;
+use exoclaw::gateway::protocol::{RpcResult, handle_rpc, handle_rpc_as};
+use exoclaw::gateway::ratelimit::RateLimiter;
+use exoclaw::gateway::reload;
+use exoclaw::gateway::replay::ReplayBuffers;
+use exoclaw::gateway::runs::RunRegistry;
 use exoclaw::gateway::server::AppState;
+use exoclaw::gateway::shutdown;
 use exoclaw::memory::MemoryEngine;
-use exoclaw::router::SessionRouter;
+use exoclaw::router::{Binding, SessionRouter};
 use exoclaw::sandbox::PluginHost;
 use exoclaw::store::SessionStore;
 use std::collections::HashMap;
@@ -13,7 +25,14 @@ use tokio::time::{Duration, timeout};
 
 fn build_state(config: ExoclawConfig) -> Arc<AppState> {
     Arc::new(AppState {
-        token: None,
+        keyring: Keyring::default(),
+        limiter: RateLimiter::new(config.gateway.rate_limits.clone()),
+        connections: Slots::new(config.gateway.max_connections),
+        streams: Slots::new(config.gateway.max_streams),
+        webhooks: Default::default(),
+        shutdown: Default::default(),
+        hub: Default::default(),
+        approvals: Default::default(),
         router: RwLock::new(SessionRouter::new()),
         plugins: Arc::new(RwLock::new(PluginHost::new())),
         store: RwLock::new(SessionStore::new()),
@@ -21,15 +40,21 @@ fn build_state(config: ExoclawConfig) -> Arc<AppState> {
             config.memory.episodic_window as usize,
             config.memory.semantic_enabled,
         ))),
-        config,
+        config: config.into(),
         session_locks: RwLock::new(HashMap::<String, Arc<Mutex<()>>>::new()),
+        runs: RunRegistry::new(),
+        replay: ReplayBuffers::default(),
     })
 }
 
+fn caller() -> Caller {
+    Caller::trusted("test")
+}
+
 #[tokio::test]
 async fn ping_returns_pong() {
     let state = build_state(ExoclawConfig::default());
-    let result = handle_rpc(r#"{"id":"1","method":"ping"}"#, &state).await;
+    let result = handle_rpc(r#"{"id":"1","method":"ping"}"#, &caller(), &state).await;
     let RpcResult::Response(resp) = result else {
         panic!("expected response");
     };
@@ -41,7 +66,7 @@ async fn ping_returns_pong() {
 #[tokio::test]
 async fn ping_accepts_numeric_id() {
     let state = build_state(ExoclawConfig::default());
-    let result = handle_rpc(r#"{"id":1,"method":"ping"}"#, &state).await;
+    let result = handle_rpc(r#"{"id":1,"method":"ping"}"#, &caller(), &state).await;
     let RpcResult::Response(resp) = result else {
         panic!("expected response");
     };
@@ -53,7 +78,7 @@ async fn ping_accepts_numeric_id() {
 #[tokio::test]
 async fn status_returns_version_plugins_sessions() {
     let state = build_state(ExoclawConfig::default());
-    let result = handle_rpc(r#"{"id":"2","method":"status"}"#, &state).await;
+    let result = handle_rpc(r#"{"id":"2","method":"status"}"#, &caller(), &state).await;
     let RpcResult::Response(resp) = result else {
         panic!("expected response");
     };
@@ -69,6 +94,7 @@ async fn chat_send_missing_params_returns_error() {
     let state = build_state(ExoclawConfig::default());
     let result = handle_rpc(
         r#"{"id":"3","method":"chat.send","params":{"channel":"ws"}}"#,
+        &caller(),
         &state,
     )
     .await;
@@ -88,7 +114,7 @@ async fn chat_send_missing_params_returns_error() {
 #[tokio::test]
 async fn unknown_method_returns_error() {
     let state = build_state(ExoclawConfig::default());
-    let result = handle_rpc(r#"{"id":"4","method":"nope.method"}"#, &state).await;
+    let result = handle_rpc(r#"{"id":"4","method":"nope.method"}"#, &caller(), &state).await;
     let RpcResult::Response(resp) = result else {
         panic!("expected response");
     };
@@ -105,6 +131,7 @@ async fn chat_send_valid_params_streams_response() {
 
     let result = handle_rpc(
         r#"{"id":"5","method":"chat.send","params":{"channel":"websocket","account":"me","content":"hello world"}}"#,
+        &caller(),
         &state,
     )
     .await;
@@ -145,3 +172,1451 @@ async fn chat_send_valid_params_streams_response() {
     assert!(saw_usage);
     assert!(saw_done);
 }
+
+#[tokio::test]
+async fn chat_send_uses_bound_agent_definition() {
+    let mut config = ExoclawConfig::default();
+    // The primary agent has no key, so only the bound agent can answer.
+    config.agent.provider = "openai".to_string();
+    config.agent.api_key = None;
+    config.agents.push(AgentDefConfig {
+        id: "work".to_string(),
+        provider: "mock".to_string(),
+        ..AgentDefConfig::default()
+    });
+    let state = build_state(config);
+    state.router.write().await.add_binding(Binding {
+        agent_id: "work".to_string(),
+        channel: Some("websocket".to_string()),
+        account_id: None,
+        peer_id: None,
+        guild_id: None,
+        team_id: None,
+    });
+
+    let result = handle_rpc(
+        r#"{"id":"6","method":"chat.send","params":{"channel":"websocket","account":"me","content":"hi"}}"#,
+        &caller(),
+        &state,
+    )
+    .await;
+
+    let RpcResult::Stream {
+        session_key,
+        agent_id,
+        mut rx,
+        ..
+    } = result
+    else {
+        panic!("expected stream from bound mock agent");
+    };
+    assert_eq!(agent_id, "work");
+    assert_eq!(session_key, "work:websocket:me:main");
+
+    let first = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+    assert!(matches!(first, Some(AgentEvent::Text(ref t)) if t == "mock response"));
+}
+
+#[tokio::test]
+async fn chat_send_unbound_route_uses_primary_agent() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "openai".to_string();
+    config.agent.api_key = None;
+    config.agents.push(AgentDefConfig {
+        id: "work".to_string(),
+        provider: "mock".to_string(),
+        ..AgentDefConfig::default()
+    });
+    let state = build_state(config);
+
+    let result = handle_rpc(
+        r#"{"id":"7","method":"chat.send","params":{"channel":"websocket","account":"me","content":"hi"}}"#,
+        &caller(),
+        &state,
+    )
+    .await;
+
+    let RpcResult::Response(resp) = result else {
+        panic!("expected provider error from primary agent");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert!(
+        parsed["error"]
+            .as_str()
+            .unwrap_or("")
+            .contains("no API key for provider 'openai'")
+    );
+}
+
+#[tokio::test]
+async fn chat_cancel_stops_run_and_releases_session() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+
+    // Hold the session lock so the run is parked before reaching the provider.
+    let session_key = "default:websocket:cancel-me:main";
+    let lock = state.session_lock(session_key).await;
+    let guard = lock.lock().await;
+
+    let result = handle_rpc(
+        r#"{"id":"c1","method":"chat.send","params":{"channel":"websocket","account":"cancel-me","content":"a long question"}}"#,
+        &caller(),
+        &state,
+    )
+    .await;
+    let RpcResult::Stream { stream, mut rx, .. } = result else {
+        panic!("expected stream");
+    };
+
+    let result = handle_rpc(
+        &format!(r#"{{"id":"c2","method":"chat.cancel","params":{{"stream":"{stream}"}}}}"#),
+        &caller(),
+        &state,
+    )
+    .await;
+    let RpcResult::Response(resp) = result else {
+        panic!("expected response");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(parsed["id"], "c2");
+    assert_eq!(parsed["result"]["cancelled"], stream.as_str());
+
+    let event = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+    assert!(matches!(event, Some(AgentEvent::Cancelled)));
+    assert_eq!(state.runs.count(), 0);
+
+    // The cancelled run must not hold the session lock.
+    drop(guard);
+    let _reacquired = timeout(Duration::from_secs(1), lock.lock())
+        .await
+        .expect("session lock should be free after cancel");
+
+    // The run never reached the provider, so nothing is billed.
+    let counter = metering::get_or_init_global(&Default::default());
+    let usage = counter
+        .lock()
+        .unwrap()
+        .get_usage(&BudgetScope::Session(session_key.to_string()));
+    assert_eq!((usage.input_tokens, usage.output_tokens), (0, 0));
+}
+
+#[tokio::test]
+async fn chat_cancel_during_streaming_bills_the_partial_call() {
+    use axum::body::{Body, Bytes};
+    use tokio_stream::StreamExt;
+
+    // An OpenAI endpoint that streams one chunk and then never finishes.
+    async fn stalled_openai() -> axum::response::Response {
+        let chunk = "data: {\"choices\":[{\"delta\":{\"content\":\"partial answer\"},\"finish_reason\":null}]}\n\n";
+        let body = tokio_stream::iter([Ok::<_, std::io::Error>(Bytes::from(chunk))])
+            .chain(tokio_stream::pending());
+        axum::response::Response::builder()
+            .header("content-type", "text/event-stream")
+            .body(Body::from_stream(body))
+            .unwrap()
+    }
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app =
+        axum::Router::new().route("/v1/chat/completions", axum::routing::post(stalled_openai));
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    // SAFETY: test-scoped env mutation; the other OpenAI agents in this
+    // binary have no key and fail before reading it.
+    unsafe {
+        std::env::set_var(
+            "EXOCLAW_OPENAI_ENDPOINT",
+            format!("http://{addr}/v1/chat/completions"),
+        );
+    }
+
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "openai".to_string();
+    config.agent.api_key = Some("test".to_string());
+    let state = build_state(config);
+
+    let result = handle_rpc(
+        r#"{"id":"s1","method":"chat.send","params":{"channel":"websocket","account":"cancel-streaming","content":"a long question"}}"#,
+        &caller(),
+        &state,
+    )
+    .await;
+    let RpcResult::Stream { stream, mut rx, .. } = result else {
+        panic!("expected stream");
+    };
+    let first = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+    assert!(matches!(first, Some(AgentEvent::Text(ref t)) if t == "partial answer"));
+
+    assert!(state.runs.cancel(&stream).is_some());
+    let event = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+    assert!(matches!(event, Some(AgentEvent::Cancelled)));
+    // SAFETY: undo test-scoped env mutation.
+    unsafe {
+        std::env::remove_var("EXOCLAW_OPENAI_ENDPOINT");
+    }
+
+    // The provider never reported usage; the call in flight is estimated.
+    let counter = metering::get_or_init_global(&Default::default());
+    let usage = counter.lock().unwrap().get_usage(&BudgetScope::Session(
+        "default:websocket:cancel-streaming:main".to_string(),
+    ));
+    assert!(usage.input_tokens > 0);
+    assert!(usage.output_tokens > 0);
+}
+
+#[tokio::test]
+async fn chat_cancel_unknown_run_returns_error() {
+    let state = build_state(ExoclawConfig::default());
+    let result = handle_rpc(
+        r#"{"id":"c3","method":"chat.cancel","params":{"stream":"nope"}}"#,
+        &caller(),
+        &state,
+    )
+    .await;
+    let RpcResult::Response(resp) = result else {
+        panic!("expected response");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(parsed["error"], "no in-flight run with id: nope");
+}
+
+#[tokio::test]
+async fn shutdown_refuses_new_runs_and_cancels_those_past_the_drain_timeout() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+
+    // Park a run on its session lock so it cannot finish in time.
+    let lock = state.session_lock("default:websocket:drain-me:main").await;
+    let guard = lock.lock().await;
+    let RpcResult::Stream { mut rx, .. } = handle_rpc(
+        r#"{"id":"d1","method":"chat.send","params":{"channel":"websocket","account":"drain-me","content":"still going"}}"#,
+        &caller(),
+        &state,
+    )
+    .await
+    else {
+        panic!("expected stream");
+    };
+
+    let drained = tokio::spawn({
+        let state = Arc::clone(&state);
+        async move { shutdown::drain(&state, Duration::from_millis(200)).await }
+    });
+    while !state.shutdown.is_draining() {
+        tokio::task::yield_now().await;
+    }
+
+    let refused = rpc_response(
+        &state,
+        r#"{"jsonrpc":"2.0","id":"d2","method":"chat.send","params":{"channel":"websocket","account":"late","content":"hi"}}"#,
+    )
+    .await;
+    assert_eq!(refused["error"]["code"], -32007);
+    assert_eq!(refused["error"]["data"]["shutdown"], true);
+
+    timeout(Duration::from_secs(5), drained)
+        .await
+        .unwrap()
+        .unwrap();
+    let event = timeout(Duration::from_secs(1), rx.recv()).await.unwrap();
+    assert!(matches!(event, Some(AgentEvent::Cancelled)));
+    assert_eq!(state.runs.count(), 0);
+    assert_eq!(state.shutdown.tasks(), 0);
+    drop(guard);
+}
+
+#[tokio::test]
+async fn shutdown_lets_runs_finish_within_the_drain_timeout() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+
+    let session_key = "default:websocket:finish-me:main";
+    let lock = state.session_lock(session_key).await;
+    let guard = lock.lock().await;
+    let RpcResult::Stream { mut rx, .. } = handle_rpc(
+        r#"{"id":"f1","method":"chat.send","params":{"channel":"websocket","account":"finish-me","content":"almost done"}}"#,
+        &caller(),
+        &state,
+    )
+    .await
+    else {
+        panic!("expected stream");
+    };
+
+    let drained = tokio::spawn({
+        let state = Arc::clone(&state);
+        async move { shutdown::drain(&state, Duration::from_secs(5)).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    drop(guard);
+
+    timeout(Duration::from_secs(5), drained)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut events = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        events.push(event);
+    }
+    assert!(events.iter().any(|e| matches!(e, AgentEvent::Done)));
+    assert!(!events.iter().any(|e| matches!(e, AgentEvent::Cancelled)));
+    // The reply was persisted before the drain finished.
+    let session = state.store.read().await.get(session_key).cloned().unwrap();
+    assert_eq!(session.messages.len(), 2);
+}
+
+async fn rpc_response(state: &Arc<AppState>, msg: &str) -> serde_json::Value {
+    let RpcResult::Response(resp) = handle_rpc(msg, &caller(), state).await else {
+        panic!("expected response");
+    };
+    serde_json::from_str(&resp).unwrap()
+}
+
+async fn seed_session(state: &Arc<AppState>, key: &str, messages: usize) {
+    let mut store = state.store.write().await;
+    store.get_or_create(key, "default");
+    for i in 0..messages {
+        store.append_message(
+            key,
+            serde_json::json!({"role":"user","content":format!("m{i}")}),
+        );
+    }
+}
+
+#[tokio::test]
+async fn session_list_returns_known_sessions() {
+    let state = build_state(ExoclawConfig::default());
+    seed_session(&state, "default:websocket:me:main", 2).await;
+
+    let parsed = rpc_response(&state, r#"{"id":"l","method":"session.list"}"#).await;
+    let sessions = parsed["result"]["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["key"], "default:websocket:me:main");
+    assert_eq!(sessions[0]["agent_id"], "default");
+    assert_eq!(sessions[0]["message_count"], 2);
+}
+
+#[tokio::test]
+async fn session_history_paginates() {
+    let state = build_state(ExoclawConfig::default());
+    seed_session(&state, "k", 5).await;
+
+    let parsed = rpc_response(
+        &state,
+        r#"{"id":"h","method":"session.history","params":{"key":"k","offset":1,"limit":2}}"#,
+    )
+    .await;
+    let result = &parsed["result"];
+    assert_eq!(result["total"], 5);
+    assert_eq!(result["messages"].as_array().unwrap().len(), 2);
+    assert_eq!(result["messages"][0]["content"], "m1");
+    assert_eq!(result["next_offset"], 3);
+
+    let last = rpc_response(
+        &state,
+        r#"{"id":"g","method":"session.get","params":{"key":"k","offset":3}}"#,
+    )
+    .await;
+    assert_eq!(last["result"]["messages"].as_array().unwrap().len(), 2);
+    assert!(last["result"]["next_offset"].is_null());
+}
+
+#[tokio::test]
+async fn session_history_unknown_key_returns_error() {
+    let state = build_state(ExoclawConfig::default());
+    let parsed = rpc_response(
+        &state,
+        r#"{"id":"h","method":"session.history","params":{"key":"nope"}}"#,
+    )
+    .await;
+    assert_eq!(parsed["error"], "session not found: nope");
+}
+
+#[tokio::test]
+async fn session_reset_clears_history_memory_and_route_state() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+
+    let result = handle_rpc(
+        r#"{"id":"s","method":"chat.send","params":{"channel":"websocket","account":"me","content":"hello"}}"#,
+        &caller(),
+        &state,
+    )
+    .await;
+    let RpcResult::Stream { session_key, .. } = result else {
+        panic!("expected stream");
+    };
+    state
+        .memory
+        .write()
+        .await
+        .append_to_episodic(&session_key, exoclaw::types::Message::text("user", "hello"));
+
+    let msg =
+        format!(r#"{{"id":"r","method":"session.reset","params":{{"key":"{session_key}"}}}}"#);
+    let parsed = rpc_response(&state, &msg).await;
+    assert_eq!(parsed["result"]["reset"], session_key);
+
+    let store = state.store.read().await;
+    assert!(store.get(&session_key).unwrap().messages.is_empty());
+    drop(store);
+    assert!(
+        state
+            .memory
+            .read()
+            .await
+            .episodic
+            .all(&session_key)
+            .is_empty()
+    );
+    assert_eq!(state.router.read().await.session_count(), 0);
+}
+
+#[tokio::test]
+async fn session_delete_removes_session() {
+    let state = build_state(ExoclawConfig::default());
+    seed_session(&state, "k", 1).await;
+
+    let parsed = rpc_response(
+        &state,
+        r#"{"id":"d","method":"session.delete","params":{"key":"k"}}"#,
+    )
+    .await;
+    assert_eq!(parsed["result"]["deleted"], "k");
+    assert_eq!(state.store.read().await.count(), 0);
+
+    let again = rpc_response(
+        &state,
+        r#"{"id":"d2","method":"session.delete","params":{"key":"k"}}"#,
+    )
+    .await;
+    assert_eq!(again["error"], "session not found: k");
+}
+
+#[tokio::test]
+async fn session_reset_fails_fast_while_a_run_holds_the_session() {
+    let state = build_state(ExoclawConfig::default());
+    seed_session(&state, "k", 1).await;
+    let lock = state.session_lock("k").await;
+    let guard = lock.lock().await;
+
+    let parsed = timeout(
+        Duration::from_secs(1),
+        rpc_response(
+            &state,
+            r#"{"jsonrpc":"2.0","id":1,"method":"session.reset","params":{"key":"k"}}"#,
+        ),
+    )
+    .await
+    .expect("reset must not wait for the run");
+    assert_eq!(parsed["error"]["code"], -32004);
+    assert_eq!(state.store.read().await.get("k").unwrap().messages.len(), 1);
+
+    drop(guard);
+    let parsed = rpc_response(
+        &state,
+        r#"{"jsonrpc":"2.0","id":2,"method":"session.reset","params":{"key":"k"}}"#,
+    )
+    .await;
+    assert_eq!(parsed["result"]["reset"], "k");
+}
+
+#[tokio::test]
+async fn chat_send_buffers_sequenced_frames_and_persists_without_reader() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+
+    let result = handle_rpc(
+        r#"{"id":"gone","method":"chat.send","params":{"channel":"websocket","account":"me","content":"hello"}}"#,
+        &caller(),
+        &state,
+    )
+    .await;
+    let RpcResult::Stream {
+        stream,
+        session_key,
+        rx,
+        ..
+    } = result
+    else {
+        panic!("expected stream");
+    };
+    // Nobody reads the live stream; the run must still finish and be saved.
+    drop(rx);
+
+    let mut changes = state.replay.watch(&stream).expect("replay buffer");
+    let slice = timeout(Duration::from_secs(2), async {
+        loop {
+            let slice = state.replay.frames_after(&stream, 0).unwrap();
+            if slice.finished {
+                return slice;
+            }
+            changes.changed().await.unwrap();
+        }
+    })
+    .await
+    .expect("run should finish");
+
+    let seqs: Vec<u64> = slice
+        .frames
+        .iter()
+        .map(|f| f["seq"].as_u64().unwrap())
+        .collect();
+    assert_eq!(seqs, (1..=slice.frames.len() as u64).collect::<Vec<_>>());
+    assert_eq!(slice.frames.last().unwrap()["event"], "done");
+    assert!(
+        slice
+            .frames
+            .iter()
+            .all(|f| f["id"] == "gone" && f["stream"] == stream.as_str())
+    );
+
+    let store = state.store.read().await;
+    let messages = &store.get(&session_key).unwrap().messages;
+    assert_eq!(messages.last().unwrap()["content"], "mock response");
+}
+
+#[tokio::test]
+async fn chat_resume_acknowledges_known_stream() {
+    let state = build_state(ExoclawConfig::default());
+    state
+        .replay
+        .start("run-1", "req-1", "default:websocket:me:main");
+    state
+        .replay
+        .push("run-1", serde_json::json!({"id":"req-1","event":"text"}));
+
+    let result = handle_rpc(
+        r#"{"id":"r","method":"chat.resume","params":{"stream":"run-1","after_seq":1}}"#,
+        &caller(),
+        &state,
+    )
+    .await;
+    let RpcResult::Resume {
+        response,
+        stream,
+        after_seq,
+    } = result
+    else {
+        panic!("expected resume");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&response.unwrap()).unwrap();
+    assert_eq!(parsed["id"], "r");
+    assert_eq!(parsed["result"]["resumed"], "run-1");
+    assert_eq!(stream, "run-1");
+    assert_eq!(after_seq, 1);
+}
+
+#[tokio::test]
+async fn chat_resume_hides_streams_outside_pinned_route() {
+    let state = build_state(ExoclawConfig::default());
+    state
+        .replay
+        .start("theirs", "req-1", "default:websocket:bob:main");
+    state
+        .replay
+        .push("theirs", serde_json::json!({"id":"req-1","event":"text"}));
+
+    let alice = scoped_caller(vec![Scope::ChatSend], Some("alice"));
+    let result = handle_rpc(
+        r#"{"id":"r","method":"chat.resume","params":{"stream":"theirs"}}"#,
+        &alice,
+        &state,
+    )
+    .await;
+    let RpcResult::Response(resp) = result else {
+        panic!("expected rejection");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(parsed["error"], "no resumable stream with id: theirs");
+}
+
+#[tokio::test]
+async fn chat_resume_unknown_stream_returns_error() {
+    let state = build_state(ExoclawConfig::default());
+    let parsed = rpc_response(
+        &state,
+        r#"{"id":"r","method":"chat.resume","params":{"stream":"nope"}}"#,
+    )
+    .await;
+    assert_eq!(parsed["error"], "no resumable stream with id: nope");
+}
+
+#[tokio::test]
+async fn strict_request_echoes_id_and_version() {
+    let state = build_state(ExoclawConfig::default());
+    let parsed = rpc_response(&state, r#"{"jsonrpc":"2.0","id":7,"method":"ping"}"#).await;
+    assert_eq!(parsed["jsonrpc"], "2.0");
+    assert_eq!(parsed["id"], 7);
+    assert_eq!(parsed["result"], "pong");
+}
+
+#[tokio::test]
+async fn strict_errors_carry_codes() {
+    let state = build_state(ExoclawConfig::default());
+
+    let parsed = rpc_response(&state, r#"{"jsonrpc":"2.0","id":"a","method":"nope"}"#).await;
+    assert_eq!(parsed["error"]["code"], -32601);
+    assert_eq!(parsed["error"]["message"], "unknown method: nope");
+
+    let parsed = rpc_response(
+        &state,
+        r#"{"jsonrpc":"2.0","id":"b","method":"chat.send","params":{}}"#,
+    )
+    .await;
+    assert_eq!(parsed["error"]["code"], -32602);
+
+    let parsed = rpc_response(
+        &state,
+        r#"{"jsonrpc":"2.0","id":"c","method":"session.get","params":{"key":"missing"}}"#,
+    )
+    .await;
+    assert_eq!(parsed["error"]["code"], -32003);
+}
+
+#[tokio::test]
+async fn malformed_json_is_a_parse_error() {
+    let state = build_state(ExoclawConfig::default());
+    let parsed = rpc_response(&state, "{not json").await;
+    assert_eq!(parsed["jsonrpc"], "2.0");
+    assert!(parsed["id"].is_null());
+    assert_eq!(parsed["error"]["code"], -32700);
+}
+
+#[tokio::test]
+async fn malformed_json_from_a_legacy_client_gets_a_legacy_error() {
+    let state = build_state(ExoclawConfig::default());
+    let mut dialect = Dialect::JsonRpc2;
+    let respond = async |msg: &str, dialect: &mut Dialect| {
+        let RpcResult::Response(resp) = handle_rpc_as(msg, dialect, &caller(), &state).await else {
+            panic!("expected response");
+        };
+        serde_json::from_str::<serde_json::Value>(&resp).unwrap()
+    };
+
+    respond(r#"{"id":"1","method":"ping"}"#, &mut dialect).await;
+    let parsed = respond("{not json", &mut dialect).await;
+    assert!(parsed.get("jsonrpc").is_none());
+    assert_eq!(parsed["id"], "0");
+    assert!(parsed["error"].as_str().unwrap().starts_with("parse error"));
+
+    // A strict request switches the connection back.
+    respond(r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#, &mut dialect).await;
+    let parsed = respond("{not json", &mut dialect).await;
+    assert_eq!(parsed["error"]["code"], -32700);
+}
+
+#[tokio::test]
+async fn wrong_version_is_an_invalid_request() {
+    let state = build_state(ExoclawConfig::default());
+    let parsed = rpc_response(&state, r#"{"jsonrpc":"1.0","id":"1","method":"ping"}"#).await;
+    assert_eq!(parsed["error"]["code"], -32600);
+
+    let parsed = rpc_response(&state, "[]").await;
+    assert_eq!(parsed["error"]["code"], -32600);
+}
+
+#[tokio::test]
+async fn notifications_get_no_response() {
+    let state = build_state(ExoclawConfig::default());
+    let result = handle_rpc(r#"{"jsonrpc":"2.0","method":"ping"}"#, &caller(), &state).await;
+    assert!(matches!(result, RpcResult::NoResponse));
+}
+
+#[tokio::test]
+async fn batch_answers_each_member_in_order() {
+    let state = build_state(ExoclawConfig::default());
+    let result = handle_rpc(
+        r#"[
+            {"jsonrpc":"2.0","id":1,"method":"ping"},
+            {"jsonrpc":"2.0","method":"status"},
+            {"id":"legacy","method":"ping"},
+            {"jsonrpc":"2.0","id":2,"method":"nope"}
+        ]"#,
+        &caller(),
+        &state,
+    )
+    .await;
+    let RpcResult::Batch(results) = result else {
+        panic!("expected batch");
+    };
+    assert_eq!(results.len(), 4);
+
+    let responses: Vec<serde_json::Value> = results
+        .into_iter()
+        .filter_map(|r| match r {
+            RpcResult::Response(resp) => Some(serde_json::from_str(&resp).unwrap()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0]["result"], "pong");
+    assert_eq!(responses[1]["error"]["code"], -32600);
+    assert_eq!(responses[2]["id"], 2);
+    assert_eq!(responses[2]["error"]["code"], -32601);
+}
+
+#[tokio::test]
+async fn strict_chat_send_acknowledges_stream() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+
+    let result = handle_rpc(
+        r#"{"jsonrpc":"2.0","id":9,"method":"chat.send","params":{"channel":"websocket","account":"me","content":"hi"}}"#,
+        &caller(),
+        &state,
+    )
+    .await;
+    let RpcResult::Stream {
+        ack, id, stream, ..
+    } = result
+    else {
+        panic!("expected stream");
+    };
+    assert_eq!(id, "9");
+    let ack: serde_json::Value = serde_json::from_str(&ack.expect("strict ack")).unwrap();
+    assert_eq!(ack["id"], 9);
+    assert_eq!(ack["result"]["stream"], stream.as_str());
+    assert_eq!(ack["result"]["session_key"], "default:websocket:me:main");
+}
+
+#[tokio::test]
+async fn clients_reusing_a_request_id_get_separate_streams() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+
+    // Park both runs so they are in flight at the same time.
+    let mine = state.session_lock("default:websocket:me:main").await;
+    let mine_guard = mine.lock().await;
+    let yours = state.session_lock("default:websocket:you:main").await;
+    let yours_guard = yours.lock().await;
+    let send = |account: &str| {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"chat.send","params":{{"channel":"websocket","account":"{account}","content":"hi"}}}}"#
+        )
+    };
+    let RpcResult::Stream { stream: first, .. } = handle_rpc(&send("me"), &caller(), &state).await
+    else {
+        panic!("expected stream");
+    };
+    let RpcResult::Stream {
+        stream: second,
+        mut rx,
+        ..
+    } = handle_rpc(&send("you"), &caller(), &state).await
+    else {
+        panic!("expected stream for the same request id");
+    };
+    assert_ne!(first, second);
+    assert_eq!(state.runs.count(), 2);
+
+    drop(yours_guard);
+    while timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .is_some()
+    {}
+    drop(mine_guard);
+
+    // Each stream only replays its own frames.
+    let slice = state.replay.frames_after(&second, 0).unwrap();
+    assert!(slice.frames.iter().all(|f| f["stream"] == second.as_str()));
+}
+
+fn scoped_caller(scopes: Vec<Scope>, account: Option<&str>) -> Caller {
+    Caller::from_token(&ApiTokenConfig {
+        name: "scoped".to_string(),
+        token: "tok".to_string(),
+        scopes,
+        channel: None,
+        account: account.map(String::from),
+    })
+}
+
+#[tokio::test]
+async fn missing_scope_is_forbidden() {
+    let state = build_state(ExoclawConfig::default());
+    let caller = scoped_caller(vec![Scope::ChatSend], None);
+
+    let RpcResult::Response(resp) = handle_rpc(
+        r#"{"jsonrpc":"2.0","id":1,"method":"session.list"}"#,
+        &caller,
+        &state,
+    )
+    .await
+    else {
+        panic!("expected response");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(parsed["error"]["code"], -32005);
+    assert_eq!(parsed["error"]["message"], "missing scope: session:read");
+    assert_eq!(parsed["error"]["data"]["scope"], "session:read");
+
+    // Unmapped methods need admin, even ones that do not exist.
+    let RpcResult::Response(resp) =
+        handle_rpc(r#"{"id":"2","method":"auth.reload"}"#, &caller, &state).await
+    else {
+        panic!("expected response");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(parsed["error"], "missing scope: admin");
+
+    // ping needs no scope.
+    let RpcResult::Response(resp) =
+        handle_rpc(r#"{"id":"3","method":"ping"}"#, &caller, &state).await
+    else {
+        panic!("expected response");
+    };
+    assert!(resp.contains("pong"));
+}
+
+#[tokio::test]
+async fn pinned_caller_sees_only_its_sessions() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+    seed_session(&state, "default:ws:alice:main", 1).await;
+    seed_session(&state, "default:ws:bob:main", 1).await;
+    let caller = scoped_caller(vec![Scope::ChatSend, Scope::SessionRead], Some("alice"));
+
+    let RpcResult::Response(resp) =
+        handle_rpc(r#"{"id":"1","method":"session.list"}"#, &caller, &state).await
+    else {
+        panic!("expected response");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    let sessions = parsed["result"]["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["key"], "default:ws:alice:main");
+
+    let RpcResult::Response(resp) = handle_rpc(
+        r#"{"id":"2","method":"session.get","params":{"key":"default:ws:bob:main"}}"#,
+        &caller,
+        &state,
+    )
+    .await
+    else {
+        panic!("expected response");
+    };
+    assert!(resp.contains("session not found"));
+
+    let RpcResult::Response(resp) = handle_rpc(
+        r#"{"jsonrpc":"2.0","id":3,"method":"chat.send","params":{"channel":"ws","account":"bob","content":"hi"}}"#,
+        &caller,
+        &state,
+    )
+    .await
+    else {
+        panic!("expected rejection");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(parsed["error"]["code"], -32005);
+}
+
+#[tokio::test]
+async fn chat_send_over_session_limit_is_rate_limited() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    config.gateway.rate_limits.session = Some(RateLimit {
+        per_minute: 1,
+        burst: Some(1),
+    });
+    let state = build_state(config);
+    let send = |id: u32| {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":{id},"method":"chat.send","params":{{"channel":"ws","account":"me","content":"hi"}}}}"#
+        )
+    };
+
+    let RpcResult::Stream { mut rx, .. } = handle_rpc(&send(1), &caller(), &state).await else {
+        panic!("expected stream");
+    };
+    while rx.recv().await.is_some() {}
+
+    let RpcResult::Response(resp) = handle_rpc(&send(2), &caller(), &state).await else {
+        panic!("expected rejection");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(parsed["error"]["code"], -32006);
+    assert_eq!(parsed["error"]["data"]["dimension"], "session");
+    assert!(
+        parsed["error"]["data"]["retry_after_secs"]
+            .as_u64()
+            .unwrap()
+            >= 1
+    );
+
+    // Other sessions have their own bucket.
+    let other = r#"{"jsonrpc":"2.0","id":3,"method":"chat.send","params":{"channel":"ws","account":"you","content":"hi"}}"#;
+    assert!(matches!(
+        handle_rpc(other, &caller(), &state).await,
+        RpcResult::Stream { .. }
+    ));
+}
+
+#[tokio::test]
+async fn rate_limited_chat_send_opens_no_session() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    config.gateway.rate_limits.token = Some(RateLimit {
+        per_minute: 1,
+        burst: Some(1),
+    });
+    let state = build_state(config);
+    let send = |account: &str| {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"chat.send","params":{{"channel":"ws","account":"{account}","content":"hi"}}}}"#
+        )
+    };
+
+    let RpcResult::Stream { mut rx, .. } = handle_rpc(&send("me"), &caller(), &state).await else {
+        panic!("expected stream");
+    };
+    while rx.recv().await.is_some() {}
+
+    let RpcResult::Response(resp) = handle_rpc(&send("you"), &caller(), &state).await else {
+        panic!("expected rejection");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(parsed["error"]["code"], -32006);
+    assert_eq!(state.router.read().await.session_count(), 1);
+}
+
+#[tokio::test]
+async fn chat_send_enforces_content_and_stream_limits() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    config.gateway.max_content_chars = 5;
+    let state = build_state(config);
+
+    let RpcResult::Response(resp) = handle_rpc(
+        r#"{"jsonrpc":"2.0","id":1,"method":"chat.send","params":{"channel":"ws","account":"me","content":"too long"}}"#,
+        &caller(),
+        &state,
+    )
+    .await
+    else {
+        panic!("expected rejection");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(parsed["error"]["code"], -32602);
+
+    // The connection's only stream slot is taken.
+    let streams = Slots::new(1);
+    let _held = streams.try_take().unwrap();
+    let mut frames = state.hub.subscribe("default:ws:me:main");
+    let RpcResult::Response(resp) = handle_rpc(
+        r#"{"jsonrpc":"2.0","id":2,"method":"chat.send","params":{"channel":"ws","account":"me","content":"hi"}}"#,
+        &caller().with_streams(streams),
+        &state,
+    )
+    .await
+    else {
+        panic!("expected rejection");
+    };
+    let parsed: serde_json::Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(parsed["error"]["code"], -32007);
+    assert_eq!(
+        parsed["error"]["data"]["limit"],
+        "max_streams_per_connection"
+    );
+    assert_eq!(state.streams.in_use(), 0);
+
+    // Rejected calls leave no trace in the session or on its subscribers.
+    assert_eq!(state.store.read().await.count(), 0);
+    assert_eq!(state.router.read().await.session_count(), 0);
+    assert!(frames.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn rpc_calls_are_counted_by_method_and_outcome() {
+    let state = build_state(ExoclawConfig::default());
+    rpc_response(
+        &state,
+        r#"{"jsonrpc":"2.0","id":1,"method":"session.history"}"#,
+    )
+    .await;
+    rpc_response(
+        &state,
+        r#"{"jsonrpc":"2.0","id":2,"method":"no.such/method"}"#,
+    )
+    .await;
+
+    let text = exoclaw::metrics::render(&[]);
+    assert!(text.contains(
+        r#"exoclaw_rpc_requests_total{method="session.history",outcome="invalid_params"}"#
+    ));
+    assert!(
+        text.contains(r#"exoclaw_rpc_requests_total{method="unknown",outcome="method_not_found"}"#)
+    );
+    assert!(!text.contains("no.such/method"));
+}
+
+#[tokio::test]
+async fn plugins_load_inspect_reload_and_unload_at_runtime() {
+    let state = build_state(ExoclawConfig::default());
+    let agent = AgentDefConfig::default();
+    let path = format!(
+        "{}/examples/echo-plugin/target/wasm32-unknown-unknown/release/echo_plugin.wasm",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    assert!(state.tool_schemas_for(&agent).await.is_empty());
+
+    let load = serde_json::json!({
+        "jsonrpc": "2.0", "id": 1, "method": "plugin.load",
+        "params": {
+            "name": "echo",
+            "path": path,
+            "capabilities": ["http:api.example.com"],
+            "require_approval": true,
+        },
+    });
+    let resp = rpc_response(&state, &load.to_string()).await;
+    assert_eq!(resp["result"]["plugin_type"], "tool", "{resp}");
+    assert_eq!(resp["result"]["allowed_hosts"][0], "api.example.com");
+    assert_eq!(resp["result"]["require_approval"], true);
+    assert_eq!(state.plugins.read().await.approval_tools(), vec!["echo"]);
+    assert_eq!(state.tool_schemas_for(&agent).await.len(), 1);
+
+    // Same name again is a conflict, not a silent replace.
+    let resp = rpc_response(&state, &load.to_string()).await;
+    assert_eq!(resp["error"]["code"], -32004);
+
+    let resp = rpc_response(
+        &state,
+        r#"{"jsonrpc":"2.0","id":2,"method":"plugin.reload","params":{"name":"echo"}}"#,
+    )
+    .await;
+    assert_eq!(resp["result"]["path"], path.as_str());
+    assert_eq!(resp["result"]["capabilities"][0], "http:api.example.com");
+    assert_eq!(resp["result"]["require_approval"], true);
+
+    // A tool call in flight holds the host; unload waits for it.
+    let in_flight = state.plugins.clone().read_owned().await;
+    let unload = tokio::spawn({
+        let state = state.clone();
+        async move {
+            rpc_response(
+                &state,
+                r#"{"jsonrpc":"2.0","id":3,"method":"plugin.unload","params":{"name":"echo"}}"#,
+            )
+            .await
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!unload.is_finished());
+    drop(in_flight);
+    let resp = unload.await.unwrap();
+    assert_eq!(resp["result"]["unloaded"], "echo");
+    assert!(state.tool_schemas_for(&agent).await.is_empty());
+
+    let resp = rpc_response(
+        &state,
+        r#"{"jsonrpc":"2.0","id":4,"method":"plugin.inspect","params":{"name":"echo"}}"#,
+    )
+    .await;
+    assert_eq!(resp["error"]["code"], -32003);
+
+    let bad = serde_json::json!({
+        "jsonrpc": "2.0", "id": 5, "method": "plugin.load",
+        "params": { "name": "bad", "path": "/tmp/nonexistent.wasm" },
+    });
+    let resp = rpc_response(&state, &bad.to_string()).await;
+    assert_eq!(resp["error"]["code"], -32602);
+}
+
+#[tokio::test]
+async fn config_reload_swaps_bindings_souls_and_config_plugins() {
+    let state = build_state(ExoclawConfig::default());
+    let echo = format!(
+        "{}/examples/echo-plugin/target/wasm32-unknown-unknown/release/echo_plugin.wasm",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    // Loaded at runtime, not from config: a reload leaves it alone.
+    state
+        .plugins
+        .write()
+        .await
+        .register("adhoc", &echo, vec![])
+        .unwrap();
+    state
+        .router
+        .write()
+        .await
+        .resolve("slack", "me", None, None, None);
+
+    let soul = std::env::temp_dir().join(format!("exoclaw-soul-{}.md", uuid::Uuid::new_v4()));
+    std::fs::write(&soul, "You are terse.").unwrap();
+    let mut config = ExoclawConfig::default();
+    config.agents.push(AgentDefConfig {
+        id: "work".to_string(),
+        soul_path: Some(soul.display().to_string()),
+        ..AgentDefConfig::default()
+    });
+    config.bindings.push(exoclaw::config::BindingConfig {
+        agent_id: "work".to_string(),
+        channel: Some("slack".to_string()),
+        account_id: None,
+        peer_id: None,
+        guild_id: None,
+        team_id: None,
+    });
+    config.plugins.push(exoclaw::config::PluginConfig {
+        name: "echo".to_string(),
+        path: echo.clone(),
+        capabilities: vec![],
+        require_approval: false,
+    });
+
+    let summary = reload::apply(&state, config.clone()).await.unwrap();
+    assert_eq!(summary.plugins_loaded, vec!["echo"]);
+    assert_eq!((summary.bindings, summary.souls), (1, 1));
+    assert!(state.config.get().find_agent("work").is_some());
+    let route = state
+        .router
+        .write()
+        .await
+        .resolve("slack", "me", None, None, None);
+    assert_eq!(route.agent_id, "work");
+    // Sessions seen before the reload are kept.
+    assert_eq!(state.router.read().await.session_count(), 2);
+    let soul_text = state.memory.write().await.soul.get_content("work");
+    assert_eq!(soul_text.as_deref(), Some("You are terse."));
+
+    // A config that cannot be applied changes nothing.
+    let mut broken = config.clone();
+    broken.plugins[0].path = "/tmp/nonexistent.wasm".to_string();
+    broken.bindings.clear();
+    let err = reload::apply(&state, broken).await.unwrap_err();
+    assert!(err.to_string().contains("plugin 'echo'"), "{err}");
+    assert_eq!(state.config.get().bindings.len(), 1);
+
+    // Dropping a plugin from config unloads it; unchanged entries stay.
+    config.plugins.clear();
+    let summary = reload::apply(&state, config).await.unwrap();
+    assert_eq!(summary.plugins_unloaded, vec!["echo"]);
+    let plugins = state.plugins.read().await;
+    assert!(plugins.has_plugin("adhoc") && !plugins.has_plugin("echo"));
+    drop(plugins);
+    let _ = std::fs::remove_file(&soul);
+}
+
+#[tokio::test]
+async fn session_subscribe_checks_scope_and_route_pins() {
+    let state = build_state(ExoclawConfig::default());
+    let subscribe = r#"{"jsonrpc":"2.0","id":1,"method":"session.subscribe","params":{"session_key":"default:ws:bob:main"}}"#;
+
+    let RpcResult::Subscription {
+        response,
+        session_key,
+        subscribe: true,
+    } = handle_rpc(subscribe, &caller(), &state).await
+    else {
+        panic!("expected subscription");
+    };
+    assert_eq!(session_key, "default:ws:bob:main");
+    let ack: serde_json::Value = serde_json::from_str(&response.unwrap()).unwrap();
+    assert_eq!(ack["result"]["subscribed"], "default:ws:bob:main");
+
+    let alice = scoped_caller(vec![Scope::SessionRead], Some("alice"));
+    let parsed = rpc_response_as(&state, subscribe, &alice).await;
+    assert_eq!(parsed["error"]["code"], -32005);
+
+    let no_read = scoped_caller(vec![Scope::ChatSend], None);
+    let parsed = rpc_response_as(&state, subscribe, &no_read).await;
+    assert_eq!(parsed["error"]["data"]["scope"], "session:read");
+}
+
+async fn rpc_response_as(state: &Arc<AppState>, msg: &str, caller: &Caller) -> serde_json::Value {
+    let RpcResult::Response(resp) = handle_rpc(msg, caller, state).await else {
+        panic!("expected response");
+    };
+    serde_json::from_str(&resp).unwrap()
+}
+
+#[tokio::test]
+async fn chat_send_publishes_user_message_and_frames_to_subscribers() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+    let session_key = "default:websocket:shared:main";
+    let mut frames = state.hub.subscribe(session_key);
+
+    let RpcResult::Stream { mut rx, .. } = handle_rpc(
+        r#"{"id":"p1","method":"chat.send","params":{"channel":"websocket","account":"shared","content":"hello both"}}"#,
+        &caller(),
+        &state,
+    )
+    .await
+    else {
+        panic!("expected stream");
+    };
+    while timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .is_some()
+    {}
+
+    let mut seen = Vec::new();
+    while let Ok(frame) = frames.try_recv() {
+        seen.push(serde_json::from_str::<serde_json::Value>(&frame).unwrap());
+    }
+    assert_eq!(seen[0]["event"], "user_message");
+    assert_eq!(seen[0]["data"]["content"], "hello both");
+    assert!(
+        seen.iter()
+            .all(|f| f["id"] == "p1" && f["session_key"] == session_key)
+    );
+    let last = seen.last().unwrap();
+    assert_eq!(last["event"], "done");
+    // Sequenced like the caller's own frames, so chat.resume works from them.
+    assert_eq!(last["seq"], seen.len() as u64 - 1);
+}
+
+#[tokio::test]
+async fn tool_approve_answers_only_visible_pending_calls() {
+    let state = build_state(ExoclawConfig::default());
+    let gate = ApprovalGate::new(
+        vec!["*".to_string()],
+        Arc::clone(&state.approvals),
+        "default:ws:alice:main",
+        Duration::from_secs(5),
+    );
+    let pending = gate.request("toolu_1");
+    let waiting = tokio::spawn(async move {
+        pending
+            .wait(&serde_json::json!({ "url": "https://example.com" }))
+            .await
+    });
+
+    let approve = r#"{"jsonrpc":"2.0","id":1,"method":"tool.approve","params":{"id":"toolu_1","input":{"url":"https://example.org"}}}"#;
+    let bob = scoped_caller(vec![Scope::ChatSend], Some("bob"));
+    let parsed = rpc_response_as(&state, approve, &bob).await;
+    assert_eq!(parsed["error"]["code"], -32003);
+
+    let parsed = rpc_response(&state, approve).await;
+    assert_eq!(parsed["result"]["approved"], "toolu_1");
+    let input = waiting.await.unwrap().unwrap();
+    assert_eq!(input["url"], "https://example.org");
+
+    // Already answered.
+    let parsed = rpc_response(
+        &state,
+        r#"{"jsonrpc":"2.0","id":2,"method":"tool.deny","params":{"id":"toolu_1"}}"#,
+    )
+    .await;
+    assert_eq!(parsed["error"]["code"], -32003);
+}
+
+#[tokio::test]
+async fn client_tools_need_a_connection_and_answer_only_its_own_calls() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+    let send = |tools: &str| {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"chat.send","params":{{"channel":"ws","account":"me","content":"hi","client_tools":{tools}}}}}"#
+        )
+    };
+
+    // HTTP callers have no connection to ask.
+    let parsed = rpc_response(&state, &send(r#"[{"name":"clipboard"}]"#)).await;
+    assert_eq!(parsed["error"]["code"], -32602);
+    assert!(
+        parsed["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("WebSocket")
+    );
+
+    let calls = Arc::new(ClientCalls::default());
+    let connection = caller().with_client_calls(Arc::clone(&calls));
+    let twice = send(r#"[{"name":"clipboard"},{"name":"clipboard"}]"#);
+    let parsed = rpc_response_as(&state, &twice, &connection).await;
+    assert_eq!(parsed["error"]["code"], -32602);
+    assert!(matches!(
+        handle_rpc(&send(r#"[{"name":"clipboard"}]"#), &connection, &state).await,
+        RpcResult::Stream { .. }
+    ));
+
+    let tools = ClientTools::new(
+        vec!["clipboard".to_string()],
+        Arc::clone(&calls),
+        Duration::from_secs(5),
+    );
+    let waiting = tokio::spawn(tools.request("toolu_1").wait());
+
+    let answer = r#"{"jsonrpc":"2.0","id":2,"method":"tool.result","params":{"id":"toolu_1","content":{"text":"copied"}}}"#;
+    let other = caller().with_client_calls(Arc::default());
+    let parsed = rpc_response_as(&state, answer, &other).await;
+    assert_eq!(parsed["error"]["code"], -32003);
+
+    let parsed = rpc_response_as(&state, answer, &connection).await;
+    assert_eq!(parsed["result"]["returned"], "toolu_1");
+    let result = waiting.await.unwrap();
+    assert!(!result.is_error);
+    assert_eq!(result.content, r#"{"text":"copied"}"#);
+}
+
+#[tokio::test]
+async fn chat_send_checks_and_stores_attachments() {
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    config.gateway.max_attachment_bytes = 16;
+    let state = build_state(config);
+    let send = |attachment: &str| {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"chat.send","params":{{"channel":"ws","account":"me","content":"what is this?","attachments":[{attachment}]}}}}"#
+        )
+    };
+
+    let too_big = r#"{"mime_type":"image/png","source":{"type":"base64","data":"MDEyMzQ1Njc4OTAxMjM0NTY3ODk="}}"#;
+    let parsed = rpc_response(&state, &send(too_big)).await;
+    assert_eq!(parsed["error"]["code"], -32602);
+    assert!(
+        parsed["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("attachment exceeds 16 bytes")
+    );
+
+    let png =
+        r#"{"mime_type":"image/png","source":{"type":"base64","data":"aGVsbG8="},"name":"hi.png"}"#;
+    let RpcResult::Stream { mut rx, .. } = handle_rpc(&send(png), &caller(), &state).await else {
+        panic!("expected stream");
+    };
+    while timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .is_some()
+    {}
+
+    let store = state.store.read().await;
+    let session = store.get("default:ws:me:main").unwrap();
+    assert_eq!(session.messages[0]["content"], "what is this?");
+    assert_eq!(
+        session.messages[0]["attachments"][0]["mime_type"],
+        "image/png"
+    );
+    // The transcript keeps a placeholder, not the bytes.
+    assert_eq!(session.messages[0]["attachments"][0]["size"], 5);
+    assert_eq!(session.messages[0]["attachments"][0]["name"], "hi.png");
+    assert!(session.messages[0]["attachments"][0]["source"].is_null());
+    drop(store);
+
+    // Nor does the next turn's context.
+    let mut memory = state.memory.write().await;
+    let context = memory.assemble_context("default:ws:me:main", "default", "next");
+    assert_eq!(
+        context[0].as_provider_message().unwrap()["content"],
+        "[attachment: hi.png (image/png, 5 bytes)]\nwhat is this?"
+    );
+}
+
+#[tokio::test]
+async fn openai_completion_cancelled_mid_run_is_not_reported_as_stop() {
+    use axum::body::{Bytes, to_bytes};
+    use axum::extract::{ConnectInfo, State};
+    use exoclaw::gateway::openai::chat_completions_handler;
+
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let state = build_state(config);
+
+    // Park the run on its session so it can be cancelled before it replies.
+    let lock = state.session_lock("default:openai:sdk:main").await;
+    let guard = lock.lock().await;
+
+    let body = r#"{"model":"default","user":"sdk","messages":[{"role":"user","content":"hi"}]}"#;
+    let completion = tokio::spawn(chat_completions_handler(
+        State(Arc::clone(&state)),
+        ConnectInfo("127.0.0.1:9".parse().unwrap()),
+        axum::http::HeaderMap::new(),
+        Bytes::from(body),
+    ));
+    timeout(Duration::from_secs(5), async {
+        while state.runs.count() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("run should start");
+    assert_eq!(state.runs.cancel_all(), 1);
+
+    let resp = timeout(Duration::from_secs(5), completion)
+        .await
+        .unwrap()
+        .unwrap();
+    drop(guard);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["choices"][0]["finish_reason"], "length");
+}
+
+#[tokio::test]
+async fn openai_completions_without_user_get_a_session_per_token() {
+    use axum::body::{Bytes, to_bytes};
+    use axum::extract::{ConnectInfo, State};
+    use exoclaw::gateway::keyring::Keys;
+    use exoclaw::gateway::openai::chat_completions_handler;
+
+    let mut config = ExoclawConfig::default();
+    config.agent.provider = "mock".to_string();
+    let mut state = Arc::into_inner(build_state(config)).unwrap();
+    let token = |name: &str| ApiTokenConfig {
+        name: name.to_string(),
+        token: format!("{name}-secret"),
+        scopes: vec![Scope::ChatSend],
+        channel: None,
+        account: None,
+    };
+    state.keyring = Keyring::new(Keys {
+        tokens: vec![token("alpha"), token("beta")],
+        ..Keys::default()
+    });
+    let state = Arc::new(state);
+
+    let complete = |secret: &str, body: &'static str| {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {secret}").parse().unwrap());
+        chat_completions_handler(
+            State(Arc::clone(&state)),
+            ConnectInfo("127.0.0.1:9".parse().unwrap()),
+            headers,
+            Bytes::from(body),
+        )
+    };
+
+    let hi = r#"{"model":"default","messages":[{"role":"user","content":"hi"}]}"#;
+    for secret in ["alpha-secret", "beta-secret"] {
+        let resp = complete(secret, hi).await;
+        assert_eq!(resp.status(), 200);
+    }
+    let store = state.store.read().await;
+    assert!(store.get("default:openai:alpha:main").is_some());
+    assert!(store.get("default:openai:beta:main").is_some());
+    assert!(store.get("default:openai:openai:main").is_none());
+    drop(store);
+
+    // Earlier turns and system messages are refused, not silently dropped.
+    let replayed = r#"{"model":"default","messages":[{"role":"system","content":"be brief"},{"role":"user","content":"hi"}]}"#;
+    let resp = complete("alpha-secret", replayed).await;
+    assert_eq!(resp.status(), 400);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("only the new user message")
+    );
+}
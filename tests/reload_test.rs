@@ -0,0 +1,121 @@
+//! Config hot-reload watches the file named by `EXOCLAW_CONFIG` and swaps
+//! the process-wide budget limits, so these tests live in their own binary.
+
+use tokio::time::{Duration, sleep};
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .expect("bind ephemeral")
+        .local_addr()
+        .expect("local addr")
+        .port()
+}
+
+async fn wait_for_health(port: u16) {
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{port}/health");
+    for _ in 0..80 {
+        if client
+            .get(&url)
+            .send()
+            .await
+            .is_ok_and(|r| r.status().is_success())
+        {
+            return;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!("gateway did not become healthy at {url}");
+}
+
+fn echo_wasm_path() -> String {
+    format!(
+        "{}/examples/echo-plugin/target/wasm32-unknown-unknown/release/echo_plugin.wasm",
+        env!("CARGO_MANIFEST_DIR")
+    )
+}
+
+async fn chat_status(port: u16) -> reqwest::StatusCode {
+    reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}/v1/chat?stream=false"))
+        .body(r#"{"channel":"http","account":"reload","content":"hello"}"#)
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn tool_count(port: u16) -> usize {
+    let models: serde_json::Value = reqwest::get(format!("http://127.0.0.1:{port}/v1/models"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    models["data"][0]["tools"].as_array().map_or(0, Vec::len)
+}
+
+/// Poll until `check` holds, for longer than the watcher's interval.
+async fn eventually<F, Fut>(mut check: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    for _ in 0..50 {
+        if check().await {
+            return true;
+        }
+        sleep(Duration::from_millis(200)).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn config_file_changes_are_applied_and_invalid_ones_ignored() {
+    let dir = std::env::temp_dir().join(format!("exoclaw-reload-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    let port = free_port();
+    let base = format!(
+        "[gateway]\nbind = \"127.0.0.1\"\nport = {port}\n\n[agent]\nprovider = \"anthropic\"\n"
+    );
+    std::fs::write(&path, &base).unwrap();
+    // SAFETY: set before the gateway starts; nothing else in this binary
+    // reads the environment.
+    unsafe {
+        std::env::set_var("EXOCLAW_CONFIG", &path);
+    }
+
+    let config = exoclaw::config::load().unwrap();
+    let gateway = tokio::spawn(async move {
+        let _ = exoclaw::gateway::run(config, None).await;
+    });
+    wait_for_health(port).await;
+    assert_eq!(tool_count(port).await, 0);
+
+    // Add a plugin and a budget the next message cannot fit in. The budget
+    // is checked before the provider is called.
+    let updated = format!(
+        "{base}\n[budgets]\nsession = 1\n\n[[plugins]]\nname = \"echo\"\npath = \"{}\"\n",
+        echo_wasm_path()
+    );
+    std::fs::write(&path, &updated).unwrap();
+    assert!(eventually(|| async { tool_count(port).await == 1 }).await);
+    assert_eq!(
+        chat_status(port).await,
+        reqwest::StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // An invalid file leaves the running config alone...
+    std::fs::write(&path, format!("{updated}\n[[agents]]\nid = \"\"\n")).unwrap();
+    sleep(Duration::from_secs(3)).await;
+    assert_eq!(tool_count(port).await, 1);
+
+    // ...and the next valid one is picked up.
+    std::fs::write(&path, &base).unwrap();
+    assert!(eventually(|| async { tool_count(port).await == 0 }).await);
+
+    gateway.abort();
+    let _ = gateway.await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -166,6 +166,17 @@ fn session_creation_on_first_message() {
     assert_eq!(router.session_count(), 1);
 }
 
+#[test]
+fn route_leaves_sessions_untouched_until_opened() {
+    let mut router = SessionRouter::new();
+    let route = router.route("ws", "me", None, None, None);
+    assert_eq!(route.session_key, "default:ws:me:main");
+    assert_eq!(router.session_count(), 0);
+
+    router.open(&route);
+    assert_eq!(router.session_count(), 1);
+}
+
 #[test]
 fn session_reuse_on_subsequent_messages() {
     let mut router = SessionRouter::new();
@@ -181,3 +192,58 @@ fn different_peers_create_different_sessions() {
     router.resolve("ws", "me", Some("peer2"), None, None);
     assert_eq!(router.session_count(), 2);
 }
+
+#[test]
+fn remove_session_forgets_state() {
+    let mut router = SessionRouter::new();
+    let key = router.resolve("ws", "me", None, None, None).session_key;
+
+    assert!(router.remove_session(&key));
+    assert_eq!(router.session_count(), 0);
+    assert!(!router.remove_session(&key));
+}
+
+#[test]
+fn set_bindings_replaces_default_agent_and_keeps_sessions() {
+    let mut router = SessionRouter::new();
+    router.resolve("ws", "me", None, None, None);
+
+    router.set_bindings(
+        "primary",
+        vec![make_binding(
+            "tg-agent",
+            Some("telegram"),
+            None,
+            None,
+            None,
+            None,
+        )],
+    );
+    assert_eq!(router.session_count(), 1);
+    let result = router.resolve("ws", "me", None, None, None);
+    assert_eq!(result.agent_id, "primary");
+    assert_eq!(result.matched_by, "default");
+    let result = router.resolve("telegram", "me", None, None, None);
+    assert_eq!(result.agent_id, "tg-agent");
+}
+
+#[test]
+fn route_agent_skips_bindings() {
+    let mut router = SessionRouter::new();
+    router.add_binding(make_binding(
+        "ws-agent",
+        Some("websocket"),
+        None,
+        None,
+        None,
+        None,
+    ));
+
+    let result = SessionRouter::route_agent("research", "websocket", "me", None);
+    assert_eq!(result.agent_id, "research");
+    assert_eq!(result.matched_by, "explicit");
+    assert_eq!(result.session_key, "research:websocket:me:main");
+    assert_eq!(router.session_count(), 0);
+    router.open(&result);
+    assert_eq!(router.session_count(), 1);
+}
@@ -0,0 +1,125 @@
+//! Graceful shutdown is triggered by SIGTERM to the whole process, so this
+//! test lives in its own binary.
+#![cfg(unix)]
+
+use axum::{Router, http::header, response::IntoResponse, routing::post};
+use exoclaw::config::ExoclawConfig;
+use tokio::time::{Duration, sleep, timeout};
+
+async fn slow_anthropic_handler() -> impl IntoResponse {
+    sleep(Duration::from_millis(800)).await;
+    let body = concat!(
+        "event: message_start\n",
+        "data: {\"message\":{\"usage\":{\"input_tokens\":5}}}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"delta\":{\"type\":\"text_delta\",\"text\":\"slow\"}}\n\n",
+        "event: message_stop\n",
+        "data: {}\n\n"
+    );
+    ([(header::CONTENT_TYPE, "text/event-stream")], body)
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+async fn wait_for_health(port: u16) {
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{port}/health");
+    for _ in 0..80 {
+        if client
+            .get(&url)
+            .send()
+            .await
+            .is_ok_and(|r| r.status().is_success())
+        {
+            return;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!("gateway did not become healthy at {url}");
+}
+
+#[tokio::test]
+async fn sigterm_drains_running_streams_before_exit() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let app = Router::new().route("/v1/messages", post(slow_anthropic_handler));
+        let _ = axum::serve(listener, app).await;
+    });
+    // SAFETY: set before the gateway starts; nothing else in this binary
+    // reads the environment.
+    unsafe {
+        std::env::set_var(
+            "EXOCLAW_ANTHROPIC_ENDPOINT",
+            format!("http://{addr}/v1/messages"),
+        );
+    }
+
+    let port = free_port();
+    let mut config = ExoclawConfig::default();
+    config.gateway.port = port;
+    config.gateway.shutdown_drain_secs = 5;
+    config.agent.api_key = Some("test-key".to_string());
+    let gateway = tokio::spawn(exoclaw::gateway::run(config, None));
+    wait_for_health(port).await;
+
+    let (sent_tx, sent_rx) = tokio::sync::oneshot::channel();
+    let client = tokio::task::spawn_blocking(move || {
+        let (mut socket, _) = tungstenite::connect(format!("ws://127.0.0.1:{port}/ws")).unwrap();
+        socket.read().unwrap(); // hello
+        let send = r#"{"id":"s1","method":"chat.send","params":{"channel":"websocket","account":"me","content":"hi"}}"#;
+        socket.send(tungstenite::Message::text(send)).unwrap();
+        let _ = sent_tx.send(());
+
+        let mut frames = Vec::new();
+        loop {
+            match socket.read() {
+                Ok(tungstenite::Message::Text(text)) => frames.push(text.to_string()),
+                Ok(tungstenite::Message::Close(frame)) => {
+                    return (frames, frame.map(|f| u16::from(f.code)));
+                }
+                Ok(_) => {}
+                Err(_) => return (frames, None),
+            }
+        }
+    });
+
+    sent_rx.await.unwrap();
+    sleep(Duration::from_millis(200)).await;
+    let status = std::process::Command::new("kill")
+        .args(["-TERM", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let stopped = timeout(Duration::from_secs(10), gateway).await;
+    assert!(
+        matches!(stopped, Ok(Ok(Ok(())))),
+        "gateway did not stop cleanly"
+    );
+
+    let (frames, close_code) = client.await.unwrap();
+    let position = |event: &str| {
+        frames
+            .iter()
+            .position(|f| f.contains(&format!(r#""event":"{event}""#)))
+            .unwrap_or_else(|| panic!("no {event} frame in {frames:?}"))
+    };
+    // Told about the shutdown first, then the run still finishes.
+    assert!(position("server_shutdown") < position("text"));
+    assert!(position("text") < position("done"));
+    assert_eq!(close_code, Some(1001));
+
+    // Nothing is accepted any more.
+    assert!(
+        reqwest::get(format!("http://127.0.0.1:{port}/health"))
+            .await
+            .is_err()
+    );
+}
@@ -65,3 +65,24 @@ fn get_mut_and_sessions_mut_allow_updates() {
         "agent-b"
     );
 }
+
+#[test]
+fn list_reset_and_remove_sessions() {
+    let mut store = SessionStore::new();
+    store.get_or_create("first", "agent-a");
+    store.get_or_create("second", "agent-a");
+    store.append_message("first", json!({"role":"user","content":"hello"}));
+
+    let keys: Vec<&str> = store.list().iter().map(|s| s.key.as_str()).collect();
+    assert_eq!(keys, vec!["first", "second"]);
+
+    assert!(store.reset("first"));
+    let session = store.get("first").expect("reset keeps the session");
+    assert!(session.messages.is_empty());
+    assert_eq!(session.message_count, 0);
+    assert!(!store.reset("missing"));
+
+    assert!(store.remove("second").is_some());
+    assert!(store.remove("second").is_none());
+    assert_eq!(store.count(), 1);
+}
@@ -1,4 +1,6 @@
-use exoclaw::types::{AgentMessage, Message, MessageContent, StreamEvent};
+use exoclaw::types::{
+    AgentMessage, Attachment, AttachmentSource, Message, MessageContent, StreamEvent,
+};
 use serde_json::json;
 
 #[test]
@@ -59,6 +61,119 @@ fn provider_message_for_tool_result() {
     assert_eq!(provider["content"][0]["is_error"], true);
 }
 
+#[test]
+fn provider_message_for_attachments() {
+    let raw = json!({
+        "mime_type": "application/pdf",
+        "source": { "type": "base64", "data": "JVBERi0=" },
+        "name": "invoice.pdf"
+    });
+    let pdf: Attachment = serde_json::from_value(raw).expect("deserialize Attachment");
+    let msg = Message::with_attachments("user", "total?", vec![pdf]);
+    assert!(matches!(msg.content, MessageContent::Attachments { .. }));
+
+    let provider = msg.as_provider_message().expect("provider message");
+    assert_eq!(provider["content"][0]["type"], "document");
+    assert_eq!(provider["content"][0]["title"], "invoice.pdf");
+    assert_eq!(
+        provider["content"][0]["source"]["media_type"],
+        "application/pdf"
+    );
+    assert_eq!(provider["content"][0]["source"]["data"], "JVBERi0=");
+    assert_eq!(
+        provider["content"][1],
+        json!({ "type": "text", "text": "total?" })
+    );
+
+    // No attachments, plain text.
+    let msg = Message::with_attachments("user", "hi", vec![]);
+    assert!(matches!(msg.content, MessageContent::Text { .. }));
+}
+
+#[test]
+fn stored_attachments_drop_inline_data() {
+    let pdf: Attachment = serde_json::from_value(json!({
+        "mime_type": "application/pdf",
+        "source": { "type": "base64", "data": "JVBERi0=" },
+        "name": "invoice.pdf"
+    }))
+    .unwrap();
+    let photo: Attachment = serde_json::from_value(json!({
+        "mime_type": "image/jpeg",
+        "source": { "type": "url", "url": "https://example.com/a.jpg" }
+    }))
+    .unwrap();
+    assert_eq!(pdf.size(), Some(5));
+    assert_eq!(
+        pdf.placeholder(),
+        json!({ "mime_type": "application/pdf", "size": 5, "name": "invoice.pdf" })
+    );
+    assert_eq!(
+        photo.placeholder()["source"]["url"],
+        "https://example.com/a.jpg"
+    );
+
+    let msg = Message::with_attachments("user", "total?", vec![pdf, photo.clone()]);
+    let MessageContent::Attachments { text, attachments } = msg.without_inline_data().content
+    else {
+        panic!("attachment by URL should be kept");
+    };
+    assert_eq!(
+        text,
+        "[attachment: invoice.pdf (application/pdf, 5 bytes)]\ntotal?"
+    );
+    assert_eq!(attachments, vec![photo]);
+}
+
+#[test]
+fn attachments_are_checked_for_type_encoding_and_size() {
+    let image = |mime_type: &str, source: AttachmentSource| Attachment {
+        mime_type: mime_type.into(),
+        source,
+        name: None,
+    };
+    let inline = |data: &str| AttachmentSource::Base64 { data: data.into() };
+    let url = |url: &str| AttachmentSource::Url { url: url.into() };
+
+    // "aGVsbG8=" decodes to 5 bytes.
+    assert!(image("image/png", inline("aGVsbG8=")).validate(5).is_ok());
+    let err = image("image/png", inline("aGVsbG8="))
+        .validate(4)
+        .unwrap_err();
+    assert_eq!(err, "attachment exceeds 4 bytes");
+    let err = image("image/png", inline("not base64!"))
+        .validate(64)
+        .unwrap_err();
+    assert!(err.contains("not valid base64"), "{err}");
+    let err = image("image/tiff", inline("aGVsbG8="))
+        .validate(64)
+        .unwrap_err();
+    assert!(
+        err.starts_with("unsupported attachment type 'image/tiff'"),
+        "{err}"
+    );
+
+    assert!(
+        image("image/jpeg", url("https://cdn.example.com/a.jpg"))
+            .validate(1)
+            .is_ok()
+    );
+    let err = image("image/jpeg", url("file:///etc/passwd"))
+        .validate(1)
+        .unwrap_err();
+    assert!(err.contains("must be http(s)"), "{err}");
+
+    let two = vec![
+        image("image/png", inline("aGVsbG8=")),
+        image("image/png", inline("aGVsbG8=")),
+    ];
+    assert!(Attachment::validate_all(&two, 2, 5).is_ok());
+    assert_eq!(
+        Attachment::validate_all(&two, 1, 5).unwrap_err(),
+        "2 attachments; the limit is 1"
+    );
+}
+
 #[test]
 fn agent_message_defaults_peer_to_main() {
     let raw = json!({
@@ -122,4 +237,7 @@ fn stream_event_to_frame_formats_wire_payloads() {
     let error = StreamEvent::Error("boom".into()).to_frame(request_id);
     assert_eq!(error["event"], "error");
     assert_eq!(error["data"], "boom");
+
+    let cancelled = StreamEvent::Cancelled.to_frame(request_id);
+    assert_eq!(cancelled["event"], "cancelled");
 }
@@ -55,7 +55,7 @@ pub fn parse_event(msg: &str) -> Option<StreamEvent> {
             let input = data.get("input").map(|v| v.to_string()).unwrap_or_default();
             Some(StreamEvent::ToolUse { name, input })
         }
-        "done" => Some(StreamEvent::Done),
+        "done" | "cancelled" => Some(StreamEvent::Done),
         "error" => {
             let data = v
                 .get("data")
@@ -41,6 +41,12 @@ fn parse_event_parses_tool_use() {
     );
 }
 
+#[wasm_bindgen_test]
+fn parse_event_treats_cancelled_as_done() {
+    let msg = r#"{"id":"chat1","event":"cancelled"}"#;
+    assert_eq!(parse_event(msg), Some(StreamEvent::Done));
+}
+
 #[wasm_bindgen_test]
 fn markdown_escapes_inline_html() {
     let rendered = markdown::render(r#"<script>alert("xss")</script>"#);